@@ -0,0 +1,74 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// temp files carry this marker so a scan running alongside never counts them
+pub const TEMP_MARKER: &str = ".lc-tmp-";
+
+// set by --fsync; network filesystems may otherwise acknowledge a rename
+// before the data behind it is durable
+pub static FSYNC: AtomicBool = AtomicBool::new(false);
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}{TEMP_MARKER}{}", std::process::id()))
+}
+
+// readers see either the old file or the new one, never a truncated mix
+pub fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents)?;
+        if FSYNC.load(Ordering::Relaxed) {
+            file.sync_all()?;
+        }
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result?;
+
+    #[cfg(unix)]
+    if FSYNC.load(Ordering::Relaxed) {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+// appending in place could expose a half-written record, so rewrite and swap instead
+pub fn append(path: &Path, record: &[u8]) -> io::Result<()> {
+    let mut contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    contents.extend_from_slice(record);
+    write(path, &contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_whole_file() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("lc-test-{}-atomic", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("report.json");
+
+        write(&path, b"{\"old\":true}")?;
+        write(&path, b"{}")?;
+        append(&path, b"\n")?;
+        assert_eq!(fs::read(&path)?, b"{}\n");
+        assert!(!temp_path(&path).exists());
+        assert!(temp_path(&path).to_string_lossy().contains(TEMP_MARKER));
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::LcError;
+use crate::glob::Glob;
+use crate::vfs::FileSystem;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// the linguist attributes GitHub reads to classify a file; None where no line says
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Linguist {
+    pub vendored: Option<bool>,
+    pub generated: Option<bool>,
+    pub language: Option<String>,
+}
+
+impl Linguist {
+    // GitHub leaves these out of a repository's language numbers
+    pub fn excluded(&self) -> bool {
+        self.vendored == Some(true) || self.generated == Some(true)
+    }
+
+    fn merge(&mut self, later: &Linguist) {
+        self.vendored = later.vendored.or(self.vendored);
+        self.generated = later.generated.or(self.generated);
+        if later.language.is_some() {
+            self.language.clone_from(&later.language);
+        }
+    }
+}
+
+// one .gitattributes, keeping only lines that set a linguist attribute
+pub struct Gitattributes {
+    base: PathBuf,
+    rules: Vec<(Glob, Linguist)>,
+}
+
+fn flag(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+impl Gitattributes {
+    pub fn parse(base: &Path, contents: &str) -> Gitattributes {
+        let mut rules = Vec::new();
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next().filter(|pattern| !pattern.starts_with('#')) else {
+                continue;
+            };
+            let mut linguist = Linguist::default();
+            for attribute in fields {
+                let (name, value) = match attribute.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => match attribute.strip_prefix('-') {
+                        Some(name) => (name, Some("false")),
+                        None => (attribute, Some("true")),
+                    },
+                };
+                match name {
+                    "linguist-vendored" => linguist.vendored = value.and_then(flag),
+                    "linguist-generated" => linguist.generated = value.and_then(flag),
+                    "linguist-language" => linguist.language = value.map(str::to_string),
+                    _ => {}
+                }
+            }
+            if linguist != Linguist::default() {
+                rules.push((Glob::new(pattern), linguist));
+            }
+        }
+        Gitattributes {
+            base: base.to_path_buf(),
+            rules,
+        }
+    }
+}
+
+pub fn load(fs: &dyn FileSystem, directory_path: &Path) -> Result<Option<Gitattributes>, LcError> {
+    let path = directory_path.join(".gitattributes");
+    match fs.read(&path) {
+        Ok(contents) => Ok(Some(Gitattributes::parse(
+            directory_path,
+            &String::from_utf8_lossy(&contents),
+        ))),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(LcError::Ignore { path, source }),
+    }
+}
+
+// files outermost first; within and across them a later line overrides an earlier one,
+// one attribute at a time
+pub fn linguist(files: &[&Gitattributes], path: &Path) -> Linguist {
+    let mut linguist = Linguist::default();
+    for file in files {
+        let Ok(relative) = path.strip_prefix(&file.base) else {
+            continue;
+        };
+        for (glob, rule) in &file.rules {
+            if glob.is_match(relative, false) {
+                linguist.merge(rule);
+            }
+        }
+    }
+    linguist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence() {
+        let root = Gitattributes::parse(
+            Path::new("repo"),
+            "# vendored code\nvendor/** linguist-vendored\n*.pb.go linguist-generated=true\n\
+             *.inc linguist-language=PHP\nvendor/ours/** -linguist-vendored text eol=lf\n",
+        );
+        let nested = Gitattributes::parse(Path::new("repo/api"), "*.pb.go -linguist-generated\n");
+        let files = [&root, &nested];
+        let at = |path: &str| linguist(&files, Path::new(path));
+
+        assert!(at("repo/vendor/lib/a.c").excluded());
+        assert!(!at("repo/vendor/ours/a.c").excluded());
+        assert!(at("repo/gen/x.pb.go").excluded());
+        assert!(!at("repo/api/x.pb.go").excluded());
+        assert_eq!(at("repo/lib/a.inc").language.as_deref(), Some("PHP"));
+        assert_eq!(at("repo/lib/a.rs"), Linguist::default());
+    }
+}
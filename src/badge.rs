@@ -0,0 +1,126 @@
+use crate::html::escape;
+use crate::json::Json;
+
+// what a badge says when no --label or --color is given
+pub const LABEL: &str = "lines of code";
+pub const COLOR: &str = "blue";
+
+// shields.io's named colors, so `--color` means the same in the JSON and the SVG
+const COLORS: &[(&str, &str)] = &[
+    ("brightgreen", "#4c1"),
+    ("green", "#97ca00"),
+    ("yellowgreen", "#a4a61d"),
+    ("yellow", "#dfb317"),
+    ("orange", "#fe7d37"),
+    ("red", "#e05d44"),
+    ("blue", "#007ec6"),
+    ("lightgrey", "#9f9f9f"),
+    ("grey", "#555"),
+];
+
+// short enough for a badge: 950, 6.3k, 123k, 1.2M
+pub fn compact(lines: u128) -> String {
+    const UNITS: [(u128, &str); 3] = [(1_000_000_000, "G"), (1_000_000, "M"), (1_000, "k")];
+    for (scale, unit) in UNITS {
+        if lines >= scale {
+            let value = lines as f64 / scale as f64;
+            return match value < 100.0 {
+                true => format!("{:.1}{unit}", (value * 10.0).floor() / 10.0),
+                false => format!("{}{unit}", value.floor()),
+            };
+        }
+    }
+    lines.to_string()
+}
+
+// a shields.io name, or hex digits with or without the leading '#'
+pub fn hex(color: &str) -> Option<String> {
+    if let Some((_, hex)) = COLORS.iter().find(|(name, _)| *name == color) {
+        return Some(hex.to_string());
+    }
+    let digits = color.strip_prefix('#').unwrap_or(color);
+    let valid = matches!(digits.len(), 3 | 6) && digits.chars().all(|c| c.is_ascii_hexdigit());
+    valid.then(|| format!("#{}", digits.to_ascii_lowercase()))
+}
+
+// the shields.io "endpoint" schema: https://shields.io/badges/endpoint-badge
+pub fn endpoint(label: &str, message: &str, color: &str) -> Json {
+    Json::object(vec![
+        ("schemaVersion", Json::Int(1)),
+        ("label", Json::str(label)),
+        ("message", Json::str(message)),
+        ("color", Json::str(color.trim_start_matches('#'))),
+    ])
+}
+
+// no font metrics here, so text is sized at Verdana 11px's average advance
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+// the flat shields.io style, for repos that can't reach shields.io
+pub fn svg(label: &str, message: &str, color: &str) -> String {
+    let (left, right) = (text_width(label), text_width(message));
+    let width = left + right;
+    let (label, message) = (escape(label), escape(message));
+    let text = |x: usize, value: &str| {
+        format!(
+            "<text x=\"{x}\" y=\"15\" fill=\"#010101\" fill-opacity=\".3\">{value}</text>\
+             <text x=\"{x}\" y=\"14\">{value}</text>"
+        )
+    };
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"20\" role=\"img\" \
+         aria-label=\"{label}: {message}\"><title>{label}: {message}</title>\
+         <linearGradient id=\"s\" x2=\"0\" y2=\"100%\"><stop offset=\"0\" stop-color=\"#bbb\" \
+         stop-opacity=\".1\"/><stop offset=\"1\" stop-opacity=\".1\"/></linearGradient>\
+         <clipPath id=\"r\"><rect width=\"{width}\" height=\"20\" rx=\"3\" fill=\"#fff\"/></clipPath>\
+         <g clip-path=\"url(#r)\"><rect width=\"{left}\" height=\"20\" fill=\"#555\"/>\
+         <rect x=\"{left}\" width=\"{right}\" height=\"20\" fill=\"{color}\"/>\
+         <rect width=\"{width}\" height=\"20\" fill=\"url(#s)\"/></g>\
+         <g fill=\"#fff\" text-anchor=\"middle\" \
+         font-family=\"Verdana,Geneva,DejaVu Sans,sans-serif\" font-size=\"11\">{}{}</g></svg>\n",
+        text(left / 2, &label),
+        text(left + right / 2, &message)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_counts() {
+        assert_eq!(compact(0), "0");
+        assert_eq!(compact(950), "950");
+        assert_eq!(compact(6_299), "6.2k");
+        assert_eq!(compact(123_456), "123k");
+        assert_eq!(compact(1_250_000), "1.2M");
+    }
+
+    #[test]
+    fn endpoint_json() {
+        assert_eq!(
+            endpoint(LABEL, &compact(6_300), "#FF8800").to_string(),
+            r##"{"schemaVersion":1,"label":"lines of code","message":"6.3k","color":"FF8800"}"##
+        );
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(hex("blue").as_deref(), Some("#007ec6"));
+        assert_eq!(hex("#FF8800").as_deref(), Some("#ff8800"));
+        assert_eq!(hex("abc").as_deref(), Some("#abc"));
+        assert_eq!(hex("ultraviolet"), None);
+        assert_eq!(hex("#12345"), None);
+    }
+
+    #[test]
+    fn svg_badge() {
+        let badge = svg("a<b", "6.3k", "#007ec6");
+        assert!(badge.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"69\" "));
+        assert!(badge.contains("aria-label=\"a&lt;b: 6.3k\""));
+        assert!(badge.contains("<rect x=\"31\" width=\"38\" height=\"20\" fill=\"#007ec6\"/>"));
+        assert!(badge.contains("<text x=\"50\" y=\"14\">6.3k</text>"));
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+// the tree `lc bench --fixture synthetic` times: the same files, byte for byte, on every
+// machine and every run, so throughput can be compared between builds
+const MODULES: usize = 8;
+const DIRS: usize = 40;
+const FILES_PER_DIR: usize = 50;
+const EXTENSIONS: &[&str] = &["rs", "py", "ts", "md", "txt", "json"];
+const SEED: u64 = 0x5eed_c0de_5ca1_ab1e;
+
+// xorshift64; nothing here needs better than deterministic
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
+// paths relative to the root and their contents, about `size` bytes in all; lines of every
+// length up to a long one, blank ones, and the odd CRLF
+pub fn synthetic(size: u64) -> Vec<(String, Vec<u8>)> {
+    let mut rng = Rng(SEED);
+    let per_file = (size / (DIRS * FILES_PER_DIR) as u64).max(1) as usize;
+    let mut files = Vec::with_capacity(DIRS * FILES_PER_DIR);
+    for dir in 0..DIRS {
+        for file in 0..FILES_PER_DIR {
+            let extension = EXTENSIONS[rng.below(EXTENSIONS.len() as u64) as usize];
+            let path = format!("module{}/dir{dir}/file{file}.{extension}", dir % MODULES);
+            let mut content = Vec::with_capacity(per_file + 128);
+            while content.len() < per_file {
+                let width = rng.below(120);
+                content.extend((0..width).map(|_| b' ' + rng.below(95) as u8));
+                match rng.below(20) {
+                    0 => content.extend_from_slice(b"\r\n"),
+                    _ => content.push(b'\n'),
+                }
+            }
+            files.push((path, content));
+        }
+    }
+    files
+}
+
+pub fn write(root: &Path, files: &[(String, Vec<u8>)]) -> io::Result<()> {
+    for (path, content) in files {
+        let path = root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+// bytes per second
+pub fn throughput(bytes: u64, elapsed: Duration) -> f64 {
+    bytes as f64 / elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::RealFs;
+    use crate::walk::{count_lines, Options, Walker};
+
+    #[test]
+    fn synthetic_tree() -> io::Result<()> {
+        let size = 4 * 1024 * 1024;
+        let files = synthetic(size);
+        assert_eq!(files, synthetic(size));
+        assert_eq!(files.len(), DIRS * FILES_PER_DIR);
+        let bytes: u64 = files.iter().map(|(_, content)| content.len() as u64).sum();
+        assert!((size..size * 11 / 10).contains(&bytes));
+
+        let root = std::env::temp_dir().join(format!("lc-test-{}-bench", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write(&root, &files)?;
+        let tree = Walker::new(&RealFs, Options::default()).walk(&root)?;
+        fs::remove_dir_all(&root)?;
+        let lines: u128 = files.iter().map(|(_, content)| count_lines(content)).sum();
+        assert_eq!(
+            (tree.files(), tree.bytes, tree.lines),
+            (files.len(), bytes, lines)
+        );
+        Ok(())
+    }
+}
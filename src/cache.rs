@@ -0,0 +1,291 @@
+use crate::atomic;
+use crate::error::LcError;
+use crate::vfs::Metadata;
+use crate::CACHE_FILE;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+const VERSION: &str = "lc-cache v2";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Record {
+    size: u64,
+    modified: u128,
+    lines: u128,
+    // an `lc:language=` marker found in the file, which a hit cannot re-read
+    language: Option<String>,
+}
+
+// files without an mtime can never be validated, so they never hit
+fn stamp(metadata: &Metadata) -> Option<(u64, u128)> {
+    let modified = metadata.modified?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len, modified.as_nanos()))
+}
+
+// what a record holds besides its path: a nanosecond mtime, a size in the hundreds of
+// kilobytes, a line count in the thousands, no language, and the tabs and newline between
+const RECORD_OVERHEAD: u64 = 19 + 6 + 4 + 5;
+
+// about how large `save` makes the cache for `files` whose paths come to `path_bytes`,
+// for sizing it from metadata before a single file is read
+pub fn estimated_size(files: usize, path_bytes: u64) -> u64 {
+    VERSION.len() as u64 + 1 + files as u64 * RECORD_OVERHEAD + path_bytes
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub bytes_saved: u64,
+    pub bytes_read: u64,
+    pub read_time: Duration,
+}
+
+impl CacheStats {
+    // what the hits would have cost at the throughput the misses actually got
+    pub fn time_saved(&self) -> Duration {
+        if self.bytes_read == 0 {
+            return Duration::ZERO;
+        }
+        let ratio = self.bytes_saved as f64 / self.bytes_read as f64;
+        self.read_time.mul_f64(ratio)
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    previous: HashMap<PathBuf, Record>,
+    current: HashMap<PathBuf, Record>,
+    stats: CacheStats,
+}
+
+// line counts keyed by path, trusted while size and mtime are unchanged. every scanned root
+// keeps its own file, so scans of different trees never evict each other's entries
+pub struct Cache {
+    roots: Vec<PathBuf>,
+    inner: Mutex<Inner>,
+}
+
+impl Cache {
+    pub fn load(roots: &[PathBuf]) -> Cache {
+        let mut previous = HashMap::new();
+        for root in roots {
+            let contents = fs::read_to_string(root.join(CACHE_FILE)).unwrap_or_default();
+            let mut lines = contents.lines();
+            if lines.next() != Some(VERSION) {
+                continue;
+            }
+            for line in lines {
+                let mut fields = line.splitn(5, '\t');
+                let record = (|| {
+                    let modified = fields.next()?.parse().ok()?;
+                    let size = fields.next()?.parse().ok()?;
+                    let lines = fields.next()?.parse().ok()?;
+                    let language = match fields.next()? {
+                        "" => None,
+                        language => Some(language.to_string()),
+                    };
+                    let path = PathBuf::from(fields.next()?);
+                    Some((
+                        path,
+                        Record {
+                            size,
+                            modified,
+                            lines,
+                            language,
+                        },
+                    ))
+                })();
+                if let Some((path, record)) = record {
+                    previous.insert(path, record);
+                }
+            }
+        }
+
+        Cache {
+            roots: roots.to_vec(),
+            inner: Mutex::new(Inner {
+                previous,
+                ..Inner::default()
+            }),
+        }
+    }
+
+    // line count and language marker
+    pub fn lookup(&self, path: &Path, metadata: &Metadata) -> Option<(u128, Option<String>)> {
+        let mut inner = self.inner.lock().unwrap();
+        let cached = inner.previous.get(path)?.clone();
+        if stamp(metadata) != Some((cached.size, cached.modified)) {
+            return None;
+        }
+        inner.stats.hits += 1;
+        inner.stats.bytes_saved += cached.size;
+        let found = (cached.lines, cached.language.clone());
+        inner.current.insert(path.to_path_buf(), cached);
+        Some(found)
+    }
+
+    pub fn store(
+        &self,
+        path: &Path,
+        metadata: &Metadata,
+        lines: u128,
+        language: Option<&str>,
+        elapsed: Duration,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.stats.misses += 1;
+        inner.stats.bytes_read += metadata.len;
+        inner.stats.read_time += elapsed;
+        if let Some((size, modified)) = stamp(metadata) {
+            let record = Record {
+                size,
+                modified,
+                lines,
+                language: language.map(String::from),
+            };
+            inner.current.insert(path.to_path_buf(), record);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.inner.lock().unwrap().stats
+    }
+
+    // only what this run saw is kept, so deleted files age out on their own. a file goes to
+    // the innermost root it is under, or the first root when it is under none
+    pub fn save(&self) -> Result<(), LcError> {
+        let inner = self.inner.lock().unwrap();
+        let mut contents = vec![format!("{VERSION}\n"); self.roots.len()];
+        for (path, record) in &inner.current {
+            let under = (self.roots.iter().enumerate())
+                .filter(|(_, root)| path.starts_with(root))
+                .max_by_key(|(_, root)| root.components().count())
+                .map_or(0, |(index, _)| index);
+            let path = match path.to_str() {
+                Some(path) if !path.contains('\n') => path,
+                _ => continue,
+            };
+            contents[under].push_str(&format!(
+                "{}\t{}\t{}\t{}\t{path}\n",
+                record.modified,
+                record.size,
+                record.lines,
+                record.language.as_deref().unwrap_or_default()
+            ));
+        }
+        for (root, contents) in self.roots.iter().zip(contents) {
+            let path = root.join(CACHE_FILE);
+            atomic::write(&path, contents.as_bytes())
+                .map_err(|source| LcError::Cache { path, source })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::Kind;
+    use std::slice;
+    use std::time::SystemTime;
+
+    #[test]
+    fn hit_after_save() -> Result<(), LcError> {
+        let root = std::env::temp_dir().join(format!("lc-test-{}-cache", std::process::id()));
+        fs::create_dir_all(&root)?;
+        let metadata = Metadata {
+            kind: Kind::File,
+            len: 120,
+            modified: Some(SystemTime::now()),
+        };
+        let file = root.join("a.rs");
+
+        let cache = Cache::load(slice::from_ref(&root));
+        assert_eq!(cache.lookup(&file, &metadata), None);
+        cache.store(&file, &metadata, 7, Some("sql"), Duration::from_millis(4));
+        cache.save()?;
+
+        let cache = Cache::load(slice::from_ref(&root));
+        assert_eq!(
+            cache.lookup(&file, &metadata),
+            Some((7, Some("sql".to_string())))
+        );
+        let changed = Metadata {
+            len: 121,
+            ..metadata
+        };
+        assert_eq!(cache.lookup(&file, &changed), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.bytes_saved), (1, 120));
+        Ok(())
+    }
+
+    #[test]
+    fn one_file_per_root() -> Result<(), LcError> {
+        let base = std::env::temp_dir().join(format!("lc-test-{}-cache-roots", std::process::id()));
+        let (a, b) = (base.join("a"), base.join("b"));
+        fs::create_dir_all(&a)?;
+        fs::create_dir_all(&b)?;
+        let metadata = Metadata {
+            kind: Kind::File,
+            len: 120,
+            modified: Some(SystemTime::now()),
+        };
+
+        // `lc --cache a` then `lc --cache b`, each leaving the other's entries alone
+        let cache = Cache::load(slice::from_ref(&a));
+        cache.store(&a.join("x.rs"), &metadata, 7, None, Duration::ZERO);
+        cache.save()?;
+        let cache = Cache::load(slice::from_ref(&b));
+        cache.store(&b.join("y.rs"), &metadata, 3, None, Duration::ZERO);
+        cache.save()?;
+
+        let both = Cache::load(&[a.clone(), b.clone()]);
+        assert_eq!(both.lookup(&a.join("x.rs"), &metadata), Some((7, None)));
+        assert_eq!(both.lookup(&b.join("y.rs"), &metadata), Some((3, None)));
+        both.save()?;
+        let cache = Cache::load(slice::from_ref(&b));
+        assert_eq!(cache.lookup(&a.join("x.rs"), &metadata), None);
+        assert_eq!(cache.lookup(&b.join("y.rs"), &metadata), Some((3, None)));
+        Ok(())
+    }
+
+    #[test]
+    fn estimated() -> Result<(), LcError> {
+        let root = std::env::temp_dir().join(format!("lc-test-{}-estimate", std::process::id()));
+        fs::create_dir_all(&root)?;
+        let metadata = Metadata {
+            kind: Kind::File,
+            len: 120_000,
+            modified: Some(SystemTime::now()),
+        };
+        let file = root.join("a.rs");
+
+        let cache = Cache::load(slice::from_ref(&root));
+        cache.store(&file, &metadata, 3_000, None, Duration::ZERO);
+        cache.save()?;
+        let path_bytes = file.as_os_str().len() as u64;
+        assert_eq!(
+            estimated_size(1, path_bytes),
+            fs::metadata(root.join(CACHE_FILE))?.len()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn time_saved() {
+        let stats = CacheStats {
+            bytes_saved: 300,
+            bytes_read: 100,
+            read_time: Duration::from_millis(10),
+            ..CacheStats::default()
+        };
+        assert_eq!(stats.time_saved(), Duration::from_millis(30));
+        assert_eq!(CacheStats::default().time_saved(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::report::relative;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change {
+    pub path: PathBuf,
+    pub before: Option<u128>,
+    pub after: Option<u128>,
+}
+
+impl Change {
+    pub fn delta(&self) -> i128 {
+        self.after.unwrap_or(0) as i128 - self.before.unwrap_or(0) as i128
+    }
+}
+
+fn files(tree: &Entry) -> BTreeMap<PathBuf, &Entry> {
+    fn visit<'a>(tree: &Entry, entry: &'a Entry, files: &mut BTreeMap<PathBuf, &'a Entry>) {
+        match entry.kind {
+            Kind::Dir => {
+                for child in &entry.children {
+                    visit(tree, child, files);
+                }
+            }
+            _ => {
+                files.insert(relative(tree, &entry.path).to_path_buf(), entry);
+            }
+        }
+    }
+
+    let mut files = BTreeMap::new();
+    visit(tree, tree, &mut files);
+    files
+}
+
+fn file_lines(tree: &Entry) -> BTreeMap<PathBuf, u128> {
+    files(tree)
+        .into_iter()
+        .map(|(path, entry)| (path, entry.lines))
+        .collect()
+}
+
+// files are matched by their path relative to each root; unchanged files are left out
+pub fn diff(before: &Entry, after: &Entry) -> Vec<Change> {
+    let (mut before, after) = (file_lines(before), file_lines(after));
+    let mut changes = Vec::new();
+    for (path, lines) in after {
+        let previous = before.remove(&path);
+        if previous != Some(lines) {
+            changes.push(Change {
+                path,
+                before: previous,
+                after: Some(lines),
+            });
+        }
+    }
+    changes.extend(before.into_iter().map(|(path, lines)| Change {
+        path,
+        before: Some(lines),
+        after: None,
+    }));
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TreeChange {
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Modified {
+        path: PathBuf,
+        before: u128,
+        after: u128,
+    },
+    Added {
+        path: PathBuf,
+        lines: u128,
+    },
+    Removed {
+        path: PathBuf,
+        lines: u128,
+    },
+}
+
+impl TreeChange {
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            TreeChange::Moved { to: path, .. }
+            | TreeChange::Modified { path, .. }
+            | TreeChange::Added { path, .. }
+            | TreeChange::Removed { path, .. } => path,
+        }
+    }
+
+    pub fn delta(&self) -> i128 {
+        match self {
+            TreeChange::Moved { .. } => 0,
+            TreeChange::Modified { before, after, .. } => *after as i128 - *before as i128,
+            TreeChange::Added { lines, .. } => *lines as i128,
+            TreeChange::Removed { lines, .. } => -(*lines as i128),
+        }
+    }
+}
+
+// files without a fingerprint (binaries sized from metadata) only compare by size
+fn same_content(a: &Entry, b: &Entry) -> bool {
+    match (a.hash, b.hash) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.bytes == b.bytes && a.lines == b.lines,
+    }
+}
+
+// both trees need to have been walked with `Options::hashes`; a file that disappeared
+// from one path and appeared under another with the same content is a move
+pub fn tree_diff(before: &Entry, after: &Entry) -> Vec<TreeChange> {
+    let (mut before, after) = (files(before), files(after));
+    let mut changes = Vec::new();
+    let mut added = Vec::new();
+    for (path, entry) in after {
+        match before.remove(&path) {
+            Some(previous) if same_content(previous, entry) => {}
+            Some(previous) => changes.push(TreeChange::Modified {
+                path,
+                before: previous.lines,
+                after: entry.lines,
+            }),
+            None => added.push((path, entry)),
+        }
+    }
+
+    let mut by_hash: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    for (path, entry) in &before {
+        if let Some(hash) = entry.hash {
+            by_hash.entry(hash).or_default().push(path.clone());
+        }
+    }
+    for (path, entry) in added {
+        let from = entry
+            .hash
+            .and_then(|hash| by_hash.get_mut(&hash))
+            .and_then(|paths| paths.pop());
+        match from {
+            Some(from) => {
+                before.remove(&from);
+                changes.push(TreeChange::Moved { from, to: path });
+            }
+            None => changes.push(TreeChange::Added {
+                path,
+                lines: entry.lines,
+            }),
+        }
+    }
+    changes.extend(before.into_iter().map(|(path, entry)| TreeChange::Removed {
+        path,
+        lines: entry.lines,
+    }));
+    changes.sort_by(|a, b| a.path().cmp(b.path()));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+    use std::path::Path;
+
+    #[test]
+    fn changed_added_removed() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("old/same.rs", "1\n");
+        memory.insert("old/grown.rs", "1\n");
+        memory.insert("old/gone.rs", "1\n2\n");
+        memory.insert("new/same.rs", "1\n");
+        memory.insert("new/grown.rs", "1\n2\n3\n");
+        memory.insert("new/sub/added.rs", "1\n");
+        let walker = Walker::new(&memory, Options::default());
+        let (old, new) = (
+            walker.walk(Path::new("old"))?,
+            walker.walk(Path::new("new"))?,
+        );
+
+        let changes = diff(&old, &new);
+        let change = |path: &str, before, after| Change {
+            path: PathBuf::from(path),
+            before,
+            after,
+        };
+        assert_eq!(
+            changes,
+            [
+                change("gone.rs", Some(2), None),
+                change("grown.rs", Some(1), Some(3)),
+                change("sub/added.rs", None, Some(1)),
+            ]
+        );
+        assert_eq!(changes.iter().map(Change::delta).sum::<i128>(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn moves_by_content() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("old/same.rs", "1\n");
+        memory.insert("old/src/util.rs", "fn util() {}\n");
+        memory.insert("old/edited.rs", "1\n");
+        memory.insert("old/gone.rs", "1\n2\n");
+        memory.insert("new/same.rs", "1\n");
+        memory.insert("new/lib/helpers.rs", "fn util() {}\n");
+        memory.insert("new/edited.rs", "2\n3\n");
+        memory.insert("new/fresh.rs", "1\n2\n3\n");
+        let options = Options {
+            hashes: true,
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        let (old, new) = (
+            walker.walk(Path::new("old"))?,
+            walker.walk(Path::new("new"))?,
+        );
+
+        let changes = tree_diff(&old, &new);
+        assert_eq!(
+            changes,
+            [
+                TreeChange::Modified {
+                    path: PathBuf::from("edited.rs"),
+                    before: 1,
+                    after: 2
+                },
+                TreeChange::Added {
+                    path: PathBuf::from("fresh.rs"),
+                    lines: 3
+                },
+                TreeChange::Removed {
+                    path: PathBuf::from("gone.rs"),
+                    lines: 2
+                },
+                TreeChange::Moved {
+                    from: PathBuf::from("src/util.rs"),
+                    to: PathBuf::from("lib/helpers.rs")
+                },
+            ]
+        );
+        assert_eq!(changes.iter().map(TreeChange::delta).sum::<i128>(), 2);
+        Ok(())
+    }
+}
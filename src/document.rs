@@ -0,0 +1,616 @@
+use crate::walk::count_lines;
+use std::io;
+use std::path::Path;
+
+// office documents and PDFs: their bytes are no measure of their text, so they are
+// counted as documents with pages, and as lines only when their text is extracted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Pdf,
+    Docx,
+    Odt,
+}
+
+pub fn format(path: &Path) -> Option<Format> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "pdf" => Some(Format::Pdf),
+        "docx" => Some(Format::Docx),
+        "odt" => Some(Format::Odt),
+        _ => None,
+    }
+}
+
+// most a single inflated part may grow to, so a zip bomb can't take the scan down
+const INFLATE_CAP: usize = 256 * 1024 * 1024;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+struct Bits<'a> {
+    data: &'a [u8],
+    // in bits
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn bits(&mut self, count: u32) -> io::Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or_else(|| invalid("truncated deflate stream"))?;
+            value |= u32::from(byte >> (self.position % 8) & 1) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+// canonical huffman codes decoded a bit at a time, as in zlib's puff.c
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for length in 1..16 {
+            for (symbol, _) in lengths.iter().enumerate().filter(|(_, l)| **l == length) {
+                symbols.push(symbol as u16);
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= bits.bits(1)? as i32;
+            let count = i32::from(self.counts[length]);
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("bad huffman code"))
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn dynamic_tables(bits: &mut Bits) -> io::Result<(Huffman, Huffman)> {
+    let literals = bits.bits(5)? as usize + 257;
+    let distances = bits.bits(5)? as usize + 1;
+    let mut code_lengths = [0u8; 19];
+    for &index in CODE_LENGTH_ORDER.iter().take(bits.bits(4)? as usize + 4) {
+        code_lengths[index] = bits.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let (value, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| invalid("repeat with no length"))?;
+                (previous, 3 + bits.bits(2)?)
+            }
+            17 => (0, 3 + bits.bits(3)?),
+            _ => (0, 11 + bits.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literals + distances {
+        return Err(invalid("code lengths overrun"));
+    }
+    Ok((
+        Huffman::new(&lengths[..literals]),
+        Huffman::new(&lengths[literals..]),
+    ))
+}
+
+// raw deflate (RFC 1951), as found in zip entries and, behind a two-byte header, PDF streams
+pub fn inflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut bits = Bits { data, position: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                let start = bits.position.div_ceil(8);
+                let header = data
+                    .get(start..start + 4)
+                    .ok_or_else(|| invalid("truncated stored block"))?;
+                let length = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let block = data
+                    .get(start + 4..start + 4 + length)
+                    .ok_or_else(|| invalid("truncated stored block"))?;
+                out.extend_from_slice(block);
+                bits.position = (start + 4 + length) * 8;
+            }
+            kind @ (1 | 2) => {
+                let (literals, distances) = match kind {
+                    1 => {
+                        let mut lengths = [8u8; 288];
+                        lengths[144..256].fill(9);
+                        lengths[256..280].fill(7);
+                        (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+                    }
+                    _ => dynamic_tables(&mut bits)?,
+                };
+                loop {
+                    let symbol = literals.decode(&mut bits)? as usize;
+                    if symbol < 256 {
+                        out.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        break;
+                    }
+                    let index = symbol - 257;
+                    if index >= LENGTH_BASE.len() {
+                        return Err(invalid("bad length symbol"));
+                    }
+                    let length = LENGTH_BASE[index] as usize
+                        + bits.bits(LENGTH_EXTRA[index].into())? as usize;
+                    let index = distances.decode(&mut bits)? as usize;
+                    if index >= DISTANCE_BASE.len() {
+                        return Err(invalid("bad distance symbol"));
+                    }
+                    let distance = DISTANCE_BASE[index] as usize
+                        + bits.bits(DISTANCE_EXTRA[index].into())? as usize;
+                    if distance > out.len() {
+                        return Err(invalid("distance before start of output"));
+                    }
+                    let start = out.len() - distance;
+                    for i in 0..length {
+                        out.push(out[start + i]);
+                    }
+                    if out.len() > INFLATE_CAP {
+                        return Err(invalid("inflates past the size cap"));
+                    }
+                }
+            }
+            _ => return Err(invalid("reserved block type")),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn u16_at(data: &[u8], at: usize) -> io::Result<usize> {
+    let bytes = data
+        .get(at..at + 2)
+        .ok_or_else(|| invalid("truncated zip"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+fn u32_at(data: &[u8], at: usize) -> io::Result<usize> {
+    let bytes = data
+        .get(at..at + 4)
+        .ok_or_else(|| invalid("truncated zip"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+// one named member of a zip archive, found through the central directory
+pub fn zip_entry(zip: &[u8], name: &str) -> io::Result<Option<Vec<u8>>> {
+    let end = (0..zip.len().saturating_sub(21))
+        .rev()
+        .take(0xffff + 22)
+        .find(|&at| zip[at..].starts_with(b"PK\x05\x06"))
+        .ok_or_else(|| invalid("no zip end of central directory"))?;
+    let mut at = u32_at(zip, end + 16)?;
+    for _ in 0..u16_at(zip, end + 10)? {
+        if !zip
+            .get(at..)
+            .is_some_and(|rest| rest.starts_with(b"PK\x01\x02"))
+        {
+            return Err(invalid("bad zip central directory"));
+        }
+        let method = u16_at(zip, at + 10)?;
+        let size = u32_at(zip, at + 20)?;
+        let name_length = u16_at(zip, at + 28)?;
+        let entry = zip
+            .get(at + 46..at + 46 + name_length)
+            .ok_or_else(|| invalid("truncated zip"))?;
+        if entry == name.as_bytes() {
+            let local = u32_at(zip, at + 42)?;
+            let start = local + 30 + u16_at(zip, local + 26)? + u16_at(zip, local + 28)?;
+            let data = zip
+                .get(start..start + size)
+                .ok_or_else(|| invalid("truncated zip"))?;
+            return match method {
+                0 => Ok(Some(data.to_vec())),
+                8 => inflate(data).map(Some),
+                _ => Err(invalid("unsupported zip compression")),
+            };
+        }
+        at += 46 + name_length + u16_at(zip, at + 30)? + u16_at(zip, at + 32)?;
+    }
+    Ok(None)
+}
+
+// the text of an XML part, one line per closed paragraph element
+fn xml_lines(xml: &[u8], paragraph_ends: &[&[u8]]) -> u128 {
+    let (mut lines, mut text, mut at) = (String::new(), false, 0);
+    while at < xml.len() {
+        match xml[at] {
+            b'<' => {
+                let close = xml[at..]
+                    .iter()
+                    .position(|byte| *byte == b'>')
+                    .map_or(xml.len(), |end| at + end + 1);
+                if text
+                    && paragraph_ends
+                        .iter()
+                        .any(|end| xml[at..close].starts_with(end))
+                {
+                    lines.push('\n');
+                    text = false;
+                }
+                at = close;
+            }
+            byte => {
+                text |= !byte.is_ascii_whitespace();
+                at += 1;
+            }
+        }
+    }
+    count_lines(lines.as_bytes())
+}
+
+fn xml_value<'a>(xml: &'a [u8], after: &[u8], until: u8) -> Option<&'a [u8]> {
+    let start = xml
+        .windows(after.len())
+        .position(|window| window == after)?
+        + after.len();
+    let length = xml[start..].iter().position(|byte| *byte == until)?;
+    Some(&xml[start..start + length])
+}
+
+fn number(value: Option<&[u8]>) -> Option<u64> {
+    std::str::from_utf8(value?).ok()?.trim().parse().ok()
+}
+
+// the streams of a PDF, inflated where they are FlateDecode and left alone otherwise;
+// true marks the inflated ones, whose contents the raw file doesn't show
+fn pdf_streams(pdf: &[u8]) -> Vec<(bool, Vec<u8>)> {
+    let mut streams = Vec::new();
+    let mut at = 0;
+    while let Some(found) = pdf[at..].windows(6).position(|window| window == b"stream") {
+        let keyword = at + found;
+        at = keyword + 6;
+        if pdf[..keyword].ends_with(b"end") {
+            continue;
+        }
+        let mut start = at;
+        start += pdf[start..]
+            .iter()
+            .take(2)
+            .take_while(|byte| matches!(byte, b'\r' | b'\n'))
+            .count();
+        let Some(length) = pdf[start..]
+            .windows(9)
+            .position(|window| window == b"endstream")
+        else {
+            break;
+        };
+        let data = &pdf[start..start + length];
+        let dictionary = &pdf[keyword.saturating_sub(512)..keyword];
+        let dictionary = match dictionary.windows(3).rposition(|window| window == b"obj") {
+            Some(object) => &dictionary[object..],
+            None => dictionary,
+        };
+        let flate = dictionary
+            .windows(11)
+            .any(|window| window == b"FlateDecode");
+        match flate {
+            // past the zlib header; a trailing checksum is never reached
+            true => {
+                if let Ok(inflated) = inflate(data.get(2..).unwrap_or_default()) {
+                    streams.push((true, inflated));
+                }
+            }
+            false => streams.push((false, data.to_vec())),
+        }
+        at = start + length + 9;
+    }
+    streams
+}
+
+// `/Type /Page` objects, in the file itself or in compressed object streams
+fn pdf_pages(pdf: &[u8], streams: &[(bool, Vec<u8>)]) -> u64 {
+    fn count(data: &[u8]) -> u64 {
+        let mut pages = 0;
+        for (at, _) in data
+            .windows(5)
+            .enumerate()
+            .filter(|(_, window)| *window == b"/Type")
+        {
+            let rest = &data[at + 5..];
+            let rest = &rest[rest
+                .iter()
+                .take_while(|byte| byte.is_ascii_whitespace())
+                .count()..];
+            if rest.starts_with(b"/Page") && !rest.get(5).is_some_and(u8::is_ascii_alphanumeric) {
+                pages += 1;
+            }
+        }
+        pages
+    }
+    let inflated = streams.iter().filter(|(inflated, _)| *inflated);
+    count(pdf) + inflated.map(|(_, stream)| count(stream)).sum::<u64>()
+}
+
+// lines of shown text in content streams: strings drawn between BT and ET, broken where the
+// text position moves down. an estimate: it can't see through font encodings
+fn pdf_lines(streams: &[(bool, Vec<u8>)]) -> u128 {
+    let mut lines = 0;
+    for (_, stream) in streams {
+        let (mut text, mut operands, mut at) = (false, Vec::new(), 0);
+        while at < stream.len() {
+            match stream[at] {
+                b'(' => {
+                    let mut depth = 0;
+                    while at < stream.len() {
+                        match stream[at] {
+                            b'\\' => at += 1,
+                            b'(' => depth += 1,
+                            b')' => depth -= 1,
+                            _ => {}
+                        }
+                        at += 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    text = true;
+                    operands.clear();
+                }
+                byte if byte.is_ascii_whitespace() || matches!(byte, b'[' | b']' | b'<' | b'>') => {
+                    at += 1
+                }
+                _ => {
+                    let length = stream[at..]
+                        .iter()
+                        .take_while(|byte| !byte.is_ascii_whitespace() && !b"()[]<>".contains(byte))
+                        .count()
+                        .max(1);
+                    let token = &stream[at..at + length];
+                    at += length;
+                    let moves_down = match token {
+                        b"T*" | b"'" | b"\"" | b"ET" => true,
+                        b"Td" | b"TD" => operands.last().is_some_and(|y: &f64| *y != 0.0),
+                        _ => false,
+                    };
+                    if text && moves_down {
+                        lines += 1;
+                        text = false;
+                    }
+                    match std::str::from_utf8(token)
+                        .ok()
+                        .and_then(|token| token.parse().ok())
+                    {
+                        Some(value) => operands.push(value),
+                        None => operands.clear(),
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+// pages, and lines of text when `extract` asks for them
+pub fn read(format: Format, content: &[u8], extract: bool) -> io::Result<(Option<u64>, u128)> {
+    match format {
+        Format::Pdf => {
+            let streams = pdf_streams(content);
+            let pages = pdf_pages(content, &streams);
+            let lines = ternary!(extract => pdf_lines(&streams); 0);
+            Ok((Some(pages).filter(|pages| *pages > 0), lines))
+        }
+        Format::Docx => {
+            let properties = zip_entry(content, "docProps/app.xml")?.unwrap_or_default();
+            let pages = number(xml_value(&properties, b"<Pages>", b'<'));
+            let lines = match extract {
+                true => match zip_entry(content, "word/document.xml")? {
+                    Some(document) => xml_lines(&document, &[b"</w:p>"]),
+                    None => 0,
+                },
+                false => 0,
+            };
+            Ok((pages, lines))
+        }
+        Format::Odt => {
+            let meta = zip_entry(content, "meta.xml")?.unwrap_or_default();
+            let pages = number(xml_value(&meta, b"meta:page-count=\"", b'"'));
+            let lines = match extract {
+                true => match zip_entry(content, "content.xml")? {
+                    Some(content) => xml_lines(&content, &[b"</text:p>", b"</text:h>"]),
+                    None => 0,
+                },
+                false => 0,
+            };
+            Ok((pages, lines))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a zip with every member stored uncompressed, enough to exercise the reader
+    fn stored_zip(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let (mut zip, mut directory) = (Vec::new(), Vec::new());
+        for (name, data) in members {
+            let offset = zip.len() as u32;
+            let sizes = [(data.len() as u32).to_le_bytes(); 2].concat();
+            zip.extend_from_slice(
+                b"PK\x03\x04\x14\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00",
+            );
+            zip.extend_from_slice(&sizes);
+            zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            zip.extend_from_slice(&[0, 0]);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(data);
+
+            directory.extend_from_slice(
+                b"PK\x01\x02\x14\x00\x14\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00",
+            );
+            directory.extend_from_slice(&sizes);
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let (offset, size) = (zip.len() as u32, directory.len() as u32);
+        let count = (members.len() as u16).to_le_bytes();
+        zip.extend_from_slice(&directory);
+        zip.extend_from_slice(b"PK\x05\x06\x00\x00\x00\x00");
+        zip.extend_from_slice(&[count, count].concat());
+        zip.extend_from_slice(&size.to_le_bytes());
+        zip.extend_from_slice(&offset.to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    #[test]
+    fn inflates() -> io::Result<()> {
+        // python: zlib.compressobj(9, zlib.DEFLATED, -15), fixed then dynamic huffman
+        assert_eq!(inflate(&[0x4b, 0x4c, 0x4a, 0x06, 0x00])?, b"abc");
+        let dynamic = [
+            0xad, 0xd2, 0x3d, 0x0e, 0x82, 0x50, 0x10, 0x45, 0xe1, 0xde, 0x55, 0xcc, 0x02, 0x2c,
+            0xbc, 0x8a, 0x7f, 0xcb, 0x21, 0x61, 0x08, 0x24, 0x4f, 0x1f, 0x0c, 0x63, 0x88, 0xbb,
+            0x27, 0x74, 0xf6, 0x9e, 0xe6, 0x16, 0xb7, 0x38, 0xd5, 0x57, 0xc6, 0xb7, 0xdb, 0xc9,
+            0x6a, 0x6f, 0x39, 0xb8, 0xcd, 0x9f, 0x36, 0xd2, 0xa3, 0x7c, 0x2d, 0x7c, 0xaa, 0x91,
+            0x47, 0x5b, 0xc7, 0x1c, 0x6c, 0xa9, 0x2f, 0xdf, 0x1f, 0x6f, 0xd3, 0x3b, 0x5b, 0x6b,
+            0x74, 0xcb, 0xef, 0x1e, 0xca, 0x1e, 0x11, 0x11, 0x39, 0x13, 0x91, 0x0b, 0x11, 0x69,
+            0x88, 0xc8, 0x95, 0x88, 0xdc, 0x88, 0xc8, 0x9d, 0x88, 0x3c, 0x88, 0xc8, 0x13, 0xc1,
+            0xc6, 0x90, 0x45, 0xcc, 0x0a, 0x41, 0x2b, 0x44, 0xad, 0x10, 0xb6, 0x42, 0xdc, 0x0a,
+            0x81, 0x2b, 0x44, 0xae, 0x10, 0xba, 0xfa, 0xd7, 0xee, 0x06,
+        ];
+        let expected: String = (0..20)
+            .map(|i| {
+                format!("line {i} of the quarterly report, with some repeated words words words\n")
+            })
+            .collect();
+        assert_eq!(inflate(&dynamic)?, expected.as_bytes());
+        assert!(inflate(&[0x4b, 0x4c]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn office_documents() -> io::Result<()> {
+        let docx = stored_zip(&[
+            ("docProps/app.xml", b"<Properties><Pages>3</Pages></Properties>"),
+            (
+                "word/document.xml",
+                b"<w:body><w:p><w:r><w:t>Title</w:t></w:r></w:p><w:p/><w:p><w:t>Body</w:t></w:p></w:body>",
+            ),
+        ]);
+        assert_eq!(read(Format::Docx, &docx, false)?, (Some(3), 0));
+        assert_eq!(read(Format::Docx, &docx, true)?, (Some(3), 2));
+
+        let odt = stored_zip(&[
+            (
+                "meta.xml",
+                b"<meta:document-statistic meta:page-count=\"7\"/>",
+            ),
+            (
+                "content.xml",
+                b"<text:h>Heading</text:h><text:p>one</text:p><text:p> </text:p>",
+            ),
+        ]);
+        assert_eq!(read(Format::Odt, &odt, true)?, (Some(7), 2));
+        assert!(read(Format::Docx, b"not a zip", false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pdf_text() -> io::Result<()> {
+        let pdf = b"%PDF-1.4\n1 0 obj << /Type /Pages /Count 2 >> endobj\n\
+            2 0 obj << /Type /Page >> endobj\n3 0 obj << /Type/Page >> endobj\n\
+            4 0 obj << /Length 60 >>\nstream\nBT (Hello) Tj 0 -14 Td (Wor\\)ld) Tj 50 0 Td (!) Tj ET\nendstream\nendobj\n";
+        assert_eq!(read(Format::Pdf, pdf, false)?, (Some(2), 0));
+        assert_eq!(read(Format::Pdf, pdf, true)?, (Some(2), 2));
+        Ok(())
+    }
+
+    #[test]
+    fn hostile_input() {
+        // xorshift, so a failing input reproduces
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let docx = stored_zip(&[
+            (
+                "docProps/app.xml",
+                b"<Properties><Pages>3</Pages></Properties>",
+            ),
+            ("word/document.xml", b"<w:p><w:t>(Title</w:t></w:p>"),
+        ]);
+        let pdf = b"%PDF-1.4\n1 0 obj << /Count 2 >> endobj\n2 0 obj << /Length 9 >>\n\
+            stream\nBT (a\\) Tj ET\nendstream\nendobj\n";
+        for _ in 0..2000 {
+            // damaged copies of real documents reach deeper than plain noise does
+            let mut content = match next() % 4 {
+                0 => docx.clone(),
+                1 => pdf.to_vec(),
+                2 => b"PK\x03\x04".to_vec(),
+                _ => Vec::new(),
+            };
+            let noise = next() as usize % 64;
+            content.extend((0..noise).map(|_| next() as u8));
+            for _ in 0..next() % 4 {
+                if !content.is_empty() {
+                    let at = next() as usize % content.len();
+                    content[at] = next() as u8;
+                }
+            }
+            if next() % 3 == 0 {
+                content.truncate(next() as usize % (content.len() + 1));
+            }
+            // any answer or any error will do, as long as nothing panics
+            for format in [Format::Pdf, Format::Docx, Format::Odt] {
+                let _ = read(format, &content, true);
+            }
+            let _ = inflate(&content);
+        }
+    }
+}
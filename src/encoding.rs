@@ -0,0 +1,149 @@
+use crate::walk::count_lines;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8 (bom)",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+}
+
+// how much of a file the BOM-less UTF-16 guess looks at
+const SNIFF: usize = 512;
+
+// the only encodings where counting newline bytes gives the wrong answer
+pub fn sniff_utf16(content: &[u8]) -> Option<Encoding> {
+    match content {
+        [0xff, 0xfe, ..] => return Some(Encoding::Utf16Le),
+        [0xfe, 0xff, ..] => return Some(Encoding::Utf16Be),
+        _ => {}
+    }
+
+    // mostly-ASCII UTF-16 without a BOM has a zero in every other byte
+    let sample = &content[..content.len().min(SNIFF) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even = sample.iter().step_by(2).filter(|byte| **byte == 0).count();
+    let odd = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|byte| **byte == 0)
+        .count();
+    match (even, odd) {
+        (0, odd) if odd * 10 >= pairs * 9 => Some(Encoding::Utf16Le),
+        (even, 0) if even * 10 >= pairs * 9 => Some(Encoding::Utf16Be),
+        _ => None,
+    }
+}
+
+pub fn detect(content: &[u8]) -> Encoding {
+    if let Some(utf16) = sniff_utf16(content) {
+        return utf16;
+    }
+    if content.starts_with(&[0xef, 0xbb, 0xbf]) {
+        return Encoding::Utf8Bom;
+    }
+
+    match std::str::from_utf8(content) {
+        Ok(_) => Encoding::Utf8,
+        // a cut-off sequence at the very end is still UTF-8
+        Err(err) if err.error_len().is_none() => Encoding::Utf8,
+        // Latin-1 text has no NULs; binaries almost always do
+        Err(_) if !content.contains(&0) => Encoding::Latin1,
+        Err(_) => Encoding::Utf8,
+    }
+}
+
+fn utf16_units(content: &[u8], little_endian: bool) -> impl Iterator<Item = u16> + '_ {
+    content
+        .chunks_exact(2)
+        .map(move |pair| match little_endian {
+            true => u16::from_le_bytes([pair[0], pair[1]]),
+            false => u16::from_be_bytes([pair[0], pair[1]]),
+        })
+}
+
+// (lines, chars) with the same line rules as `str::lines`; a BOM is not a character
+pub fn count(content: &[u8], encoding: Encoding) -> (u128, u128) {
+    match encoding {
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let little_endian = encoding == Encoding::Utf16Le;
+            let mut units = utf16_units(content, little_endian).peekable();
+            if units.peek() == Some(&0xfeff) {
+                units.next();
+            }
+            let (mut lines, mut chars, mut last) = (0u128, 0u128, None);
+            for unit in units {
+                lines += u128::from(unit == 0x0a);
+                // the trailing half of a surrogate pair is not a character of its own
+                chars += u128::from(!(0xdc00..0xe000).contains(&unit));
+                last = Some(unit);
+            }
+            match last {
+                Some(0x0a) | None => (lines, chars),
+                Some(_) => (lines + 1, chars),
+            }
+        }
+        Encoding::Utf8Bom => count(&content[3..], Encoding::Utf8),
+        Encoding::Utf8 => {
+            let continuation = content.iter().filter(|byte| **byte & 0xc0 == 0x80).count();
+            (count_lines(content), (content.len() - continuation) as u128)
+        }
+        Encoding::Latin1 => (count_lines(content), content.len() as u128),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = ternary!(bom => vec![0xff, 0xfe]; Vec::new());
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    #[test]
+    fn detection() {
+        assert_eq!(detect(b"plain\n"), Encoding::Utf8);
+        assert_eq!(detect("caf\u{e9}\n".as_bytes()), Encoding::Utf8);
+        assert_eq!(detect(b"\xef\xbb\xbfhi"), Encoding::Utf8Bom);
+        assert_eq!(detect(&utf16le("hi\n", true)), Encoding::Utf16Le);
+        assert_eq!(
+            detect(&utf16le("hello there\r\n", false)),
+            Encoding::Utf16Le
+        );
+        assert_eq!(detect(b"\xfe\xff\x00h"), Encoding::Utf16Be);
+        assert_eq!(detect(b"caf\xe9\n"), Encoding::Latin1);
+    }
+
+    #[test]
+    fn counting() {
+        let text = "one\r\ntwo \u{1f980}\nthree";
+        assert_eq!(count(&utf16le(text, true), Encoding::Utf16Le), (3, 16));
+        assert_eq!(count(text.as_bytes(), Encoding::Utf8), (3, 16));
+        assert_eq!(count(b"\xef\xbb\xbfa\n", Encoding::Utf8Bom), (1, 2));
+        assert_eq!(count(b"caf\xe9\n", Encoding::Latin1), (1, 5));
+        // U+010A shares its low byte with '\n' but is not a line break
+        assert_eq!(
+            count(&utf16le("\u{10a}\u{10a}", true), Encoding::Utf16Le),
+            (1, 2)
+        );
+    }
+}
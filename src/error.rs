@@ -0,0 +1,90 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+// what can go wrong in a walk or around it, split so a caller can tell a broken tree from
+// a bad request; anything not singled out below stays `Io`
+#[derive(Debug)]
+pub enum LcError {
+    Io(io::Error),
+    // a .gitignore that exists but could not be read; skipping it would count ignored files
+    Ignore { path: PathBuf, source: io::Error },
+    Cache { path: PathBuf, source: io::Error },
+    // `command` is everything after `git`, `reason` its exit status or why it never ran
+    Git { command: String, reason: String },
+    Cancelled,
+    InvalidOptions(String),
+}
+
+impl fmt::Display for LcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LcError::Io(err) => write!(f, "{err}"),
+            LcError::Ignore { path, source } => write!(f, "{}: {source}", path.display()),
+            LcError::Cache { path, source } => {
+                write!(f, "cannot save cache {}: {source}", path.display())
+            }
+            LcError::Git { command, reason } => write!(f, "git {command} failed ({reason})"),
+            LcError::Cancelled => write!(f, "cancelled"),
+            LcError::InvalidOptions(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl error::Error for LcError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LcError::Io(source)
+            | LcError::Ignore { source, .. }
+            | LcError::Cache { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LcError {
+    fn from(err: io::Error) -> LcError {
+        LcError::Io(err)
+    }
+}
+
+// for callers that only speak io::Result; the category survives as the inner error
+impl From<LcError> for io::Error {
+    fn from(err: LcError) -> io::Error {
+        match err {
+            LcError::Io(err) => err,
+            LcError::Cancelled => io::Error::new(io::ErrorKind::Interrupted, err),
+            LcError::InvalidOptions(_) => io::Error::new(io::ErrorKind::InvalidInput, err),
+            err => io::Error::other(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let err = io::Error::from(LcError::Cancelled);
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        let inner = err.into_inner().unwrap().downcast::<LcError>().unwrap();
+        assert!(matches!(*inner, LcError::Cancelled));
+
+        let err = LcError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn messages() {
+        let err = LcError::Git {
+            command: "pull --ff-only".to_string(),
+            reason: "exit status: 1".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "git pull --ff-only failed (exit status: 1)"
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::io::{self, Write};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// `--exec-per-file`: a shell command run once for every counted file that passes the
+// filters, with `{}` standing for the path
+pub struct Hook {
+    pub command: String,
+    pub min_lines: u128,
+    pub min_bytes: u64,
+    pub jobs: usize,
+}
+
+// single quotes keep everything literal except a single quote itself
+fn quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new(ternary!(cfg!(windows) => "cmd"; "sh"));
+    shell
+        .arg(ternary!(cfg!(windows) => "/C"; "-c"))
+        .arg(command);
+    shell
+}
+
+fn files<'a>(entry: &'a Entry, hook: &Hook, files: &mut Vec<&'a Entry>) {
+    match entry.kind {
+        Kind::Dir => entry
+            .children
+            .iter()
+            .for_each(|child| self::files(child, hook, files)),
+        _ if entry.lines >= hook.min_lines && entry.bytes >= hook.min_bytes => files.push(entry),
+        _ => {}
+    }
+}
+
+// each command's output is relayed to stderr in one piece once it exits, so concurrent
+// commands never interleave and a structured report on stdout stays parseable;
+// returns how many ran and how many failed
+pub fn run(hook: &Hook, tree: &Entry) -> (usize, usize) {
+    let mut queue = Vec::new();
+    files(tree, hook, &mut queue);
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let relay = Mutex::new(());
+
+    thread::scope(|scope| {
+        for _ in 0..hook.jobs.clamp(1, queue.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(entry) = queue.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let path = entry.path.to_string_lossy();
+                    let command = hook.command.replace("{}", &quote(&path));
+                    let result = shell(&command)
+                        .env("LC_PATH", &entry.path)
+                        .env("LC_LINES", entry.lines.to_string())
+                        .env("LC_BYTES", entry.bytes.to_string())
+                        .output();
+                    let _relay = relay.lock().unwrap();
+                    let ok = match result {
+                        Ok(output) => {
+                            let mut stderr = io::stderr().lock();
+                            let _ = stderr.write_all(&output.stdout);
+                            let _ = stderr.write_all(&output.stderr);
+                            if !output.status.success() {
+                                eprintln!("lc: {command}: {}", output.status);
+                            }
+                            output.status.success()
+                        }
+                        Err(err) => {
+                            eprintln!("lc: {command}: {err}");
+                            false
+                        }
+                    };
+                    if !ok {
+                        failed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+    (queue.len(), failed.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+    use std::path::Path;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("a b.rs"), "'a b.rs'");
+        assert_eq!(quote("it's.rs"), r"'it'\''s.rs'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn filtered_runs() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/small.rs", "1\n");
+        memory.insert("r/sub/big.rs", "1\n2\n3\n");
+        memory.insert("r/sub/fail.rs", "1\n2\n3\n4\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let hook = Hook {
+            command: "test \"$LC_LINES\" -eq 3 && test {} = \"$LC_PATH\"".to_string(),
+            min_lines: 2,
+            min_bytes: 0,
+            jobs: 4,
+        };
+        assert_eq!(run(&hook, &tree), (2, 1));
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use crate::report::relative;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS scans (
+  id INTEGER PRIMARY KEY,
+  timestamp TEXT NOT NULL,
+  root TEXT NOT NULL,
+  lines INTEGER NOT NULL,
+  bytes INTEGER NOT NULL,
+  files INTEGER NOT NULL,
+  directories INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS files (
+  scan_id INTEGER NOT NULL REFERENCES scans(id),
+  path TEXT NOT NULL,
+  lines INTEGER NOT NULL,
+  bytes INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS dirs (
+  scan_id INTEGER NOT NULL REFERENCES scans(id),
+  path TEXT NOT NULL,
+  lines INTEGER NOT NULL,
+  bytes INTEGER NOT NULL
+);
+";
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+// one transaction per scan; every run adds a row to `scans` and its files and dirs under it
+pub fn sqlite_script(tree: &Entry, timestamp: &str) -> String {
+    fn rows(tree: &Entry, entry: &Entry, script: &mut String) {
+        let table = ternary!(entry.kind == Kind::Dir => "dirs"; "files");
+        script.push_str(&format!(
+            "INSERT INTO {table} VALUES ((SELECT id FROM current_scan), {}, {}, {});\n",
+            quote(&relative(tree, &entry.path).to_string_lossy()),
+            entry.lines,
+            entry.bytes
+        ));
+        for child in &entry.children {
+            rows(tree, child, script);
+        }
+    }
+
+    let mut script = format!("{SCHEMA}BEGIN;\n");
+    script.push_str(&format!(
+        "INSERT INTO scans (timestamp, root, lines, bytes, files, directories) \
+         VALUES ({}, {}, {}, {}, {}, {});\n",
+        quote(timestamp),
+        quote(&tree.path.to_string_lossy()),
+        tree.lines,
+        tree.bytes,
+        tree.files(),
+        tree.directories()
+    ));
+    script.push_str("CREATE TEMP TABLE current_scan AS SELECT last_insert_rowid() AS id;\n");
+    rows(tree, tree, &mut script);
+    script.push_str("COMMIT;\n");
+    script
+}
+
+// there is no sqlite crate to lean on, so the sqlite3 shell does the writing
+pub fn sqlite(database: &Path, tree: &Entry, timestamp: &str) -> io::Result<()> {
+    let mut child = Command::new("sqlite3")
+        .arg("-bail")
+        .arg(database)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => io::Error::new(
+                io::ErrorKind::NotFound,
+                "--export sqlite needs the sqlite3 command on PATH",
+            ),
+            _ => err,
+        })?;
+    let script = sqlite_script(tree, timestamp);
+    child.stdin.take().unwrap().write_all(script.as_bytes())?;
+    let status = child.wait()?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!(
+            "sqlite3 {} failed ({status})",
+            database.display()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+
+    fn tree() -> io::Result<Entry> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/it's.rs", "1\n2\n");
+        memory.insert("r/sub/b.rs", "1\n");
+        Ok(Walker::new(&memory, Options::default()).walk(Path::new("r"))?)
+    }
+
+    #[test]
+    fn script() -> io::Result<()> {
+        let script = sqlite_script(&tree()?, "2024-01-01T00:00:00Z");
+        assert!(script.contains("VALUES ('2024-01-01T00:00:00Z', 'r', 3, 6, 2, 2);\n"));
+        assert!(script.contains(
+            "INSERT INTO files VALUES ((SELECT id FROM current_scan), 'it''s.rs', 2, 4);\n"
+        ));
+        assert!(script
+            .contains("INSERT INTO dirs VALUES ((SELECT id FROM current_scan), 'sub', 1, 2);\n"));
+        assert!(script.ends_with("COMMIT;\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> io::Result<()> {
+        if Command::new("sqlite3").arg("-version").output().is_err() {
+            return Ok(());
+        }
+        let dir = std::env::temp_dir().join(format!("lc-test-{}-sqlite", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let database = dir.join("scan.db");
+        let _ = std::fs::remove_file(&database);
+
+        let tree = tree()?;
+        sqlite(&database, &tree, "2024-01-01T00:00:00Z")?;
+        sqlite(&database, &tree, "2024-01-02T00:00:00Z")?;
+        let output = Command::new("sqlite3")
+            .arg(&database)
+            .arg("SELECT count(*), sum(lines) FROM files WHERE scan_id = 2;")
+            .output()?;
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "2|3\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+// `--assert` expressions: numbers, report fields, arithmetic, comparisons, `!`, `&&`, `||`.
+// Everything evaluates to f64; comparisons and logic produce 1.0 or 0.0.
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Field(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+const OPERATORS: [&str; 14] = [
+    "&&", "||", "<=", ">=", "==", "!=", "<", ">", "+", "-", "*", "/", "%", "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+        let length = if c == '(' || c == ')' {
+            tokens.push(ternary!(c == '(' => Token::Open; Token::Close));
+            1
+        } else if c.is_ascii_digit() || c == '.' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+                .unwrap_or(rest.len());
+            let number = rest[..end].replace('_', "");
+            let value = number
+                .parse()
+                .map_err(|_| format!("invalid number '{}'", &rest[..end]))?;
+            tokens.push(Token::Number(value));
+            end
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Field(rest[..end].to_string()));
+            end
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| format!("unexpected '{c}'"))?;
+            tokens.push(Token::Op(op));
+            op.len()
+        };
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a, F> {
+    tokens: Vec<Token>,
+    position: usize,
+    lookup: &'a F,
+}
+
+impl<'a, F: Fn(&str) -> Option<f64>> Parser<'a, F> {
+    fn peek_op(&self, ops: &[&str]) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[&str],
+        next: fn(&mut Self) -> Result<f64, String>,
+    ) -> Result<f64, String> {
+        let mut left = next(self)?;
+        while let Some(op) = self.peek_op(ops) {
+            self.position += 1;
+            let right = next(self)?;
+            let truth = |value: bool| ternary!(value => 1.0; 0.0);
+            left = match op {
+                "||" => truth(left != 0.0 || right != 0.0),
+                "&&" => truth(left != 0.0 && right != 0.0),
+                "<" => truth(left < right),
+                ">" => truth(left > right),
+                "<=" => truth(left <= right),
+                ">=" => truth(left >= right),
+                "==" => truth(left == right),
+                "!=" => truth(left != right),
+                "+" => left + right,
+                "-" => left - right,
+                "*" => left * right,
+                "/" => left / right,
+                _ => left % right,
+            };
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<f64, String> {
+        self.binary(&["||"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<f64, String> {
+        self.binary(&["&&"], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<f64, String> {
+        self.binary(&["<=", ">=", "==", "!=", "<", ">"], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        self.binary(&["+", "-"], Self::product)
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        self.binary(&["*", "/", "%"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Number(value)) => Ok(value),
+            Some(Token::Field(name)) => {
+                (self.lookup)(&name).ok_or_else(|| format!("unknown field '{name}'"))
+            }
+            Some(Token::Op("!")) => Ok(ternary!(self.unary()? == 0.0 => 1.0; 0.0)),
+            Some(Token::Op("-")) => Ok(-self.unary()?),
+            Some(Token::Open) => {
+                let value = self.or()?;
+                match self.tokens.get(self.position) {
+                    Some(Token::Close) => {
+                        self.position += 1;
+                        Ok(value)
+                    }
+                    _ => Err("missing ')'".to_string()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {token:?}")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+pub fn evaluate<F: Fn(&str) -> Option<f64>>(source: &str, lookup: &F) -> Result<f64, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        lookup,
+    };
+    let value = parser.or()?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(value),
+        Some(token) => Err(format!("unexpected {token:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(name: &str) -> Option<f64> {
+        match name {
+            "lines" => Some(1200.0),
+            "langs.rust.lines" => Some(1000.0),
+            "langs.python.lines" => Some(200.0),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expressions() {
+        let check = |source| evaluate(source, &fields);
+        assert_eq!(check("lines < 500_000"), Ok(1.0));
+        assert_eq!(
+            check("lines < 500000 && langs.rust.lines > langs.python.lines"),
+            Ok(1.0)
+        );
+        assert_eq!(check("!(lines >= 1200) || 1 == 2"), Ok(0.0));
+        assert_eq!(check("langs.rust.lines / lines * 100 > 80"), Ok(1.0));
+        assert_eq!(check("1 + 2 * 3 - -1"), Ok(8.0));
+        assert!(check("bogus > 1").is_err());
+        assert!(check("(lines > 1").is_err());
+        assert!(check("lines >").is_err());
+    }
+}
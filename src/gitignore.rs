@@ -0,0 +1,210 @@
+use crate::error::LcError;
+use crate::glob::Glob;
+use crate::vfs::FileSystem;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// read in this order in every directory, so a .lcignore overrules its sibling .gitignore;
+// .lcignore holds what lc should skip but git should still track
+pub const FILES: [&str; 2] = [".gitignore", ".lcignore"];
+
+// dependency and build directories skipped wherever they turn up, manifest or not
+pub const DEFAULT_DIRS: &[&str] = &["node_modules", "target", ".venv", "vendor", "dist", "build"];
+
+// third-party code checked into the tree; counted apart from first-party code, and left out
+// altogether with --no-vendored
+pub const VENDORED_DIRS: &[&str] = &["vendor", "third_party", "extern"];
+
+// whether anything between `root` and `path` is a vendored directory
+pub fn is_vendored(root: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|component| {
+            VENDORED_DIRS
+                .iter()
+                .any(|dir| component.as_os_str() == *dir)
+        })
+}
+
+// version control metadata, skipped unless asked for even though the names are hidden anyway,
+// so a count of them can be reported
+pub const VCS_DIRS: &[&str] = &[".git", ".hg", ".svn"];
+
+pub fn is_vcs(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| VCS_DIRS.iter().any(|vcs| name == *vcs))
+}
+
+struct Rule {
+    glob: Glob,
+    negated: bool,
+    // 1-based, and the line as written, for `lc explain`
+    line: usize,
+    text: String,
+}
+
+// one ignore file and the directory it sits in; its patterns are relative to that directory
+pub struct Gitignore {
+    base: PathBuf,
+    // where the rules came from; empty for rules that weren't read from a file
+    file: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl Gitignore {
+    pub fn parse(base: &Path, contents: &str) -> Gitignore {
+        let mut rules = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            // trailing spaces are dropped unless escaped
+            let mut pattern = line.trim_end_matches([' ', '\t']);
+            if line[pattern.len()..].starts_with(' ') && pattern.ends_with('\\') {
+                pattern = &line[..pattern.len() + 1];
+            }
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+            let (negated, pattern) = match pattern.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern),
+            };
+            // `\#` and `\!` are literal; Glob already reads other escapes itself
+            let pattern = pattern
+                .strip_prefix('\\')
+                .filter(|rest| rest.starts_with(['#', '!']))
+                .unwrap_or(pattern);
+            // Glob already anchors a pattern with a slash anywhere but the end to this
+            // directory, and lets one without match a name at any depth
+            rules.push(Rule {
+                glob: Glob::new(pattern),
+                negated,
+                line: number + 1,
+                text: line.to_string(),
+            });
+        }
+        Gitignore {
+            base: base.to_path_buf(),
+            file: PathBuf::new(),
+            rules,
+        }
+    }
+
+    // Some(true) when the last matching pattern ignores the path, Some(false) when it
+    // re-includes it with `!`, None when no pattern here mentions it
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.glob.is_match(relative, is_dir))
+            .map(|rule| !rule.negated)
+    }
+}
+
+// $XDG_CONFIG_HOME/lc/ignore, falling back to ~/.config/lc/ignore; its patterns are
+// relative to whichever root is being scanned
+pub fn global_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("lc").join("ignore"))
+}
+
+// None when the directory has no such file at all
+pub fn load(
+    fs: &dyn FileSystem,
+    directory_path: &Path,
+    name: &str,
+) -> Result<Option<Gitignore>, LcError> {
+    read(fs, directory_path.join(name), directory_path)
+}
+
+pub fn read(fs: &dyn FileSystem, path: PathBuf, base: &Path) -> Result<Option<Gitignore>, LcError> {
+    match fs.read(&path) {
+        Ok(contents) => {
+            let mut gitignore = Gitignore::parse(base, &String::from_utf8_lossy(&contents));
+            gitignore.file = path;
+            Ok(Some(gitignore))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(LcError::Ignore { path, source }),
+    }
+}
+
+// later files are deeper in the tree, and a deeper file overrules a shallower one
+pub fn is_ignored(gitignores: &[&Gitignore], path: &Path, is_dir: bool) -> bool {
+    gitignores
+        .iter()
+        .rev()
+        .find_map(|gitignore| gitignore.matched(path, is_dir))
+        .unwrap_or(false)
+}
+
+// the line that decides `path` as `file:line: pattern`, and whether it ignores the path
+// rather than re-including it
+pub fn deciding_rule(
+    gitignores: &[&Gitignore],
+    path: &Path,
+    is_dir: bool,
+) -> Option<(String, bool)> {
+    gitignores.iter().rev().find_map(|gitignore| {
+        let relative = path.strip_prefix(&gitignore.base).ok()?;
+        let rule =
+            (gitignore.rules.iter().rev()).find(|rule| rule.glob.is_match(relative, is_dir))?;
+        let file = match gitignore.file.as_os_str().is_empty() {
+            true => &gitignore.base,
+            false => &gitignore.file,
+        };
+        Some((
+            format!("{}:{}: {}", file.display(), rule.line, rule.text),
+            !rule.negated,
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignored(contents: &str, path: &str, is_dir: bool) -> bool {
+        let gitignore = Gitignore::parse(Path::new("repo"), contents);
+        is_ignored(&[&gitignore], &Path::new("repo").join(path), is_dir)
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(ignored("*.log\n", "a/b/debug.log", false));
+        assert!(ignored("target\n", "crates/x/target", true));
+        assert!(!ignored("# *.log\n\n", "debug.log", false));
+        assert!(ignored("\\#notes\n", "#notes", false));
+        assert!(ignored("docs/**/*.tmp\n", "docs/a/b/x.tmp", false));
+        assert!(ignored("**/cache\n", "a/cache", true));
+        assert!(ignored("trailing   \n", "trailing", false));
+    }
+
+    #[test]
+    fn anchoring() {
+        assert!(ignored("/build\n", "build", true));
+        assert!(!ignored("/build\n", "src/build", true));
+        assert!(ignored("src/gen\n", "src/gen", true));
+        assert!(!ignored("src/gen\n", "lib/src/gen", true));
+    }
+
+    #[test]
+    fn directory_only() {
+        assert!(ignored("out/\n", "a/out", true));
+        assert!(!ignored("out/\n", "a/out", false));
+    }
+
+    #[test]
+    fn negation() {
+        let rules = "*.log\n!keep.log\n";
+        assert!(ignored(rules, "debug.log", false));
+        assert!(!ignored(rules, "keep.log", false));
+        // order matters: the last matching pattern wins
+        assert!(ignored("!keep.log\n*.log\n", "keep.log", false));
+    }
+}
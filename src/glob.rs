@@ -0,0 +1,148 @@
+use std::path::Path;
+
+// shell-style globs over `/`-separated relative paths: `*`, `?`, `[a-z]`, `[!x]`
+// and `**` for any number of directories. Patterns without a `/` match the
+// basename at any depth, like .gitignore does.
+#[derive(Clone, Debug)]
+pub struct Glob {
+    pattern: String,
+    basename: bool,
+    dir_only: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Glob {
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        let basename = !pattern.contains('/');
+        Glob {
+            pattern: pattern
+                .trim_start_matches("./")
+                .trim_start_matches('/')
+                .to_string(),
+            basename,
+            dir_only,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, relative: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let path = relative.to_string_lossy().replace('\\', "/");
+        let subject = match self.basename {
+            true => path.rsplit('/').next().unwrap_or(&path),
+            false => &path,
+        };
+        matches(self.pattern.as_bytes(), subject.as_bytes())
+    }
+}
+
+fn matches(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => match rest {
+            // `**/` also matches no directories at all
+            [b'/', after @ ..] => {
+                matches(after, path)
+                    || path
+                        .iter()
+                        .enumerate()
+                        .any(|(i, byte)| *byte == b'/' && matches(after, &path[i + 1..]))
+            }
+            _ => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        },
+        [b'*', rest @ ..] => {
+            for i in 0..=path.len() {
+                if matches(rest, &path[i..]) {
+                    return true;
+                }
+                if path.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        [b'?', rest @ ..] => {
+            matches!(path.first(), Some(byte) if *byte != b'/') && matches(rest, &path[1..])
+        }
+        [b'[', rest @ ..] => match (class(rest), path.first()) {
+            (Some((matcher, after)), Some(byte)) if *byte != b'/' => {
+                matcher(*byte) && matches(after, &path[1..])
+            }
+            // an unterminated class is just a literal bracket
+            (None, Some(b'[')) => matches(rest, &path[1..]),
+            _ => false,
+        },
+        [b'\\', literal, rest @ ..] => path.first() == Some(literal) && matches(rest, &path[1..]),
+        [literal, rest @ ..] => path.first() == Some(literal) && matches(rest, &path[1..]),
+    }
+}
+
+// parses the body of a `[...]` class, returning a matcher and what follows the `]`
+fn class(pattern: &[u8]) -> Option<(impl Fn(u8) -> bool + '_, &[u8])> {
+    let (negated, body) = match pattern {
+        [b'!' | b'^', body @ ..] => (true, body),
+        body => (false, body),
+    };
+    // a `]` right after the opening bracket is a member, not the end
+    let end = body.iter().skip(1).position(|byte| *byte == b']')? + 1;
+    let members = &body[..end];
+    let matcher = move |byte: u8| {
+        let mut i = 0;
+        let mut found = false;
+        while i < members.len() {
+            if i + 2 < members.len() && members[i + 1] == b'-' {
+                found |= (members[i]..=members[i + 2]).contains(&byte);
+                i += 3;
+            } else {
+                found |= members[i] == byte;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matcher, &body[end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).is_match(Path::new(path), false)
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(is_match("*.rs", "src/main.rs"));
+        assert!(!is_match("*.rs", "src/main.rsx"));
+        assert!(is_match("src/*.rs", "src/main.rs"));
+        assert!(!is_match("src/*.rs", "src/walk/mod.rs"));
+        assert!(is_match("ma?n.rs", "main.rs"));
+        assert!(is_match("[a-m]ain.rs", "main.rs"));
+        assert!(!is_match("[!m]ain.rs", "main.rs"));
+        assert!(is_match("\\*.rs", "*.rs"));
+    }
+
+    #[test]
+    fn double_star() {
+        assert!(is_match("src/**/*.rs", "src/main.rs"));
+        assert!(is_match("src/**/*.rs", "src/a/b/c.rs"));
+        assert!(is_match("**/tests/*.rs", "tests/a.rs"));
+        assert!(is_match("**/tests/*.rs", "crates/x/tests/a.rs"));
+        assert!(is_match("target/**", "target/debug/lc"));
+        assert!(!is_match("target/**", "src/target.rs"));
+    }
+
+    #[test]
+    fn directories_only() {
+        let glob = Glob::new("build/");
+        assert!(glob.is_match(Path::new("a/build"), true));
+        assert!(!glob.is_match(Path::new("a/build"), false));
+    }
+}
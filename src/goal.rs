@@ -0,0 +1,193 @@
+use crate::report::relative;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::path::Path;
+
+pub const BUCKETS: [&str; 2] = ["tests", "docs"];
+
+const TEST_DIRS: [&str; 5] = ["test", "tests", "spec", "specs", "__tests__"];
+const DOC_DIRS: [&str; 3] = ["doc", "docs", "documentation"];
+const DOC_EXTENSIONS: [&str; 4] = ["md", "markdown", "rst", "adoc"];
+
+// by convention only: a test directory, `_test`/`test_`/`.test.`/`.spec.` names, or prose
+pub fn in_bucket(bucket: &str, relative: &Path) -> bool {
+    let dirs = relative
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|component| component.as_os_str().to_str());
+    let name = relative
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    match bucket {
+        "tests" => {
+            dirs.into_iter().any(|dir| TEST_DIRS.contains(&dir))
+                || stem.ends_with("_test")
+                || stem.starts_with("test_")
+                || name.contains(".test.")
+                || name.contains(".spec.")
+        }
+        "docs" => {
+            let extension = relative
+                .extension()
+                .and_then(|extension| extension.to_str());
+            dirs.into_iter().any(|dir| DOC_DIRS.contains(&dir))
+                || extension.is_some_and(|extension| DOC_EXTENSIONS.contains(&extension))
+        }
+        _ => false,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Goal {
+    pub bucket: String,
+    pub operator: &'static str,
+    // a fraction of all lines, 0.2 for 20%
+    pub target: f64,
+}
+
+impl std::fmt::Display for Goal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}%",
+            self.bucket,
+            self.operator,
+            self.target * 100.0
+        )
+    }
+}
+
+// `tests>=20%`; the percent sign is optional
+pub fn parse(source: &str) -> Result<Goal, String> {
+    let (at, operator) = [">=", "<=", ">", "<"]
+        .iter()
+        .find_map(|operator| source.find(operator).map(|at| (at, *operator)))
+        .ok_or("expected <bucket><op><percent>, e.g. tests>=20%")?;
+    let bucket = source[..at].trim();
+    if !BUCKETS.contains(&bucket) {
+        return Err(format!(
+            "unknown bucket '{bucket}', expected one of {}",
+            BUCKETS.join(", ")
+        ));
+    }
+    let percent = source[at + operator.len()..].trim().trim_end_matches('%');
+    let target: f64 = percent
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid percentage '{percent}'"))?;
+    if !(0.0..=100.0).contains(&target) {
+        return Err(format!("{target}% is not a percentage"));
+    }
+    Ok(Goal {
+        bucket: bucket.to_string(),
+        operator,
+        target: target / 100.0,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress {
+    pub goal: Goal,
+    pub lines: u128,
+    pub total: u128,
+}
+
+impl Progress {
+    pub fn ratio(&self) -> f64 {
+        ternary!(self.total == 0 => 0.0; self.lines as f64 / self.total as f64)
+    }
+
+    pub fn met(&self) -> bool {
+        let ratio = self.ratio();
+        match self.goal.operator {
+            ">=" => ratio >= self.goal.target,
+            "<=" => ratio <= self.goal.target,
+            ">" => ratio > self.goal.target,
+            _ => ratio < self.goal.target,
+        }
+    }
+
+    // the bucket's share of all lines, with the target marked by `|`
+    pub fn bar(&self, width: usize) -> String {
+        let filled = (self.ratio() * width as f64).round() as usize;
+        let marker = ((self.goal.target * width as f64).round() as usize).min(width - 1);
+        (0..width)
+            .map(|cell| match cell {
+                cell if cell == marker => '|',
+                cell if cell < filled => '#',
+                _ => '-',
+            })
+            .collect()
+    }
+}
+
+pub fn progress(tree: &Entry, goal: &Goal) -> Progress {
+    fn visit(tree: &Entry, entry: &Entry, bucket: &str, lines: &mut u128) {
+        match entry.kind {
+            Kind::Dir => {
+                for child in &entry.children {
+                    visit(tree, child, bucket, lines);
+                }
+            }
+            _ if in_bucket(bucket, relative(tree, &entry.path)) => *lines += entry.lines,
+            _ => {}
+        }
+    }
+
+    let mut lines = 0;
+    visit(tree, tree, &goal.bucket, &mut lines);
+    Progress {
+        goal: goal.clone(),
+        lines,
+        total: tree.lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+
+    #[test]
+    fn buckets() {
+        assert!(in_bucket("tests", Path::new("tests/cli.rs")));
+        assert!(in_bucket("tests", Path::new("pkg/parser_test.go")));
+        assert!(in_bucket("tests", Path::new("test_parser.py")));
+        assert!(in_bucket("tests", Path::new("src/app.spec.ts")));
+        assert!(!in_bucket("tests", Path::new("src/testing.rs")));
+        assert!(in_bucket("docs", Path::new("README.md")));
+        assert!(in_bucket("docs", Path::new("docs/conf.py")));
+        assert!(!in_bucket("docs", Path::new("src/main.rs")));
+    }
+
+    #[test]
+    fn parsing() {
+        let goal = parse("tests>=20%").unwrap();
+        assert_eq!((goal.bucket.as_str(), goal.operator), ("tests", ">="));
+        assert_eq!(goal.target, 0.2);
+        assert_eq!(goal.to_string(), "tests >= 20%");
+        assert_eq!(parse(" docs < 5 ").unwrap().operator, "<");
+        assert!(parse("bench>=1%").is_err());
+        assert!(parse("tests=20%").is_err());
+        assert!(parse("tests>=120%").is_err());
+    }
+
+    #[test]
+    fn tracking() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/src/lib.rs", "1\n2\n3\n");
+        memory.insert("r/tests/lib.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        let progress = progress(&tree, &parse("tests>=20%").unwrap());
+        assert_eq!((progress.lines, progress.total), (1, 4));
+        assert!(progress.met());
+        assert_eq!(progress.bar(10), "##|-------");
+        assert!(!super::progress(&tree, &parse("tests>=30%").unwrap()).met());
+        Ok(())
+    }
+}
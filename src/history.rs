@@ -0,0 +1,332 @@
+use crate::atomic;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const LAST_RUNS: &str = "last-runs";
+const SNAPSHOTS: &str = "snapshots";
+// directories deeper than this below a root are folded into their parents' snapshot rows
+const SNAPSHOT_DEPTH: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Run {
+    pub timestamp: u64,
+    pub lines: u128,
+    pub bytes: u64,
+}
+
+// $XDG_CACHE_HOME/lc, falling back to ~/.cache/lc
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("lc"))
+}
+
+// `timestamp<TAB>lines<TAB>bytes<TAB>root`, one line per scanned root
+fn parse_run(line: &str) -> Option<(&str, Run)> {
+    let fields: Vec<&str> = line.splitn(4, '\t').collect();
+    let [timestamp, lines, bytes, root] = fields[..] else {
+        return None;
+    };
+    let run = Run {
+        timestamp: timestamp.parse().ok()?,
+        lines: lines.parse().ok()?,
+        bytes: bytes.parse().ok()?,
+    };
+    Some((root, run))
+}
+
+// the latest run recorded for every root, oldest first
+pub fn runs(dir: &Path) -> Vec<(PathBuf, Run)> {
+    let contents = fs::read_to_string(dir.join(LAST_RUNS)).unwrap_or_default();
+    let mut runs: Vec<(PathBuf, Run)> = contents
+        .lines()
+        .filter_map(parse_run)
+        .map(|(root, run)| (PathBuf::from(root), run))
+        .collect();
+    runs.sort_by_key(|(_, run)| run.timestamp);
+    runs
+}
+
+// stores this run's totals for `root` and hands back the previous ones
+pub fn record(dir: &Path, root: &Path, run: Run) -> io::Result<Option<Run>> {
+    let file = dir.join(LAST_RUNS);
+    let root = root.to_string_lossy();
+    let contents = fs::read_to_string(&file).unwrap_or_default();
+
+    let mut previous = None;
+    let mut kept = String::new();
+    for line in contents.lines() {
+        match parse_run(line) {
+            Some((path, run)) if path == root => previous = Some(run),
+            Some(_) => {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+            None => {}
+        }
+    }
+    kept.push_str(&format!(
+        "{}\t{}\t{}\t{root}\n",
+        run.timestamp, run.lines, run.bytes
+    ));
+
+    fs::create_dir_all(dir)?;
+    atomic::write(&file, kept.as_bytes())?;
+    Ok(previous)
+}
+
+fn snapshot_rows(entry: &Entry, root: &Path, depth: usize, rows: &mut Vec<(String, u128)>) {
+    let directory = match entry.path.strip_prefix(root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative.to_string_lossy(),
+        _ => ".".into(),
+    };
+    rows.push((directory.replace('\\', "/"), entry.lines));
+    if depth < SNAPSHOT_DEPTH {
+        for child in entry
+            .children
+            .iter()
+            .filter(|child| child.kind == Kind::Dir)
+        {
+            snapshot_rows(child, root, depth + 1, rows);
+        }
+    }
+}
+
+// appends the lines under every directory of `tree` near its root, one
+// `timestamp<TAB>lines<TAB>directory<TAB>root` line each, `.` being the root itself
+pub fn snapshot(dir: &Path, timestamp: u64, tree: &Entry) -> io::Result<()> {
+    let mut rows = Vec::new();
+    snapshot_rows(tree, &tree.path, 0, &mut rows);
+    // `lc growth` may run from elsewhere, so the root is kept absolute when it exists
+    let root = tree
+        .path
+        .canonicalize()
+        .unwrap_or_else(|_| tree.path.clone());
+    let root = root.to_string_lossy();
+    let mut appended = String::new();
+    for (directory, lines) in rows {
+        appended.push_str(&format!("{timestamp}\t{lines}\t{directory}\t{root}\n"));
+    }
+
+    fs::create_dir_all(dir)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(SNAPSHOTS))?;
+    // one write, so a scan running alongside can't interleave its rows with ours
+    file.write_all(appended.as_bytes())
+}
+
+pub struct Growth {
+    pub directory: String,
+    pub before: u128,
+    pub after: u128,
+}
+
+impl Growth {
+    pub fn change(&self) -> i128 {
+        self.after as i128 - self.before as i128
+    }
+}
+
+// line counts per directory between the latest snapshot taken by `from` and the latest
+// taken by `to`, fastest growing first; a directory missing from one end had no lines then.
+// When no snapshot is as old as `from`, the first one after it stands in. None when there
+// is nothing to compare, otherwise the timestamps of the two snapshots used
+pub fn growth(
+    dir: &Path,
+    is_root: &dyn Fn(&Path) -> bool,
+    from: u64,
+    to: u64,
+) -> Option<(u64, u64, Vec<Growth>)> {
+    let contents = fs::read_to_string(dir.join(SNAPSHOTS)).ok()?;
+    let mut snapshots: BTreeMap<u64, BTreeMap<&str, u128>> = BTreeMap::new();
+    for line in contents.lines() {
+        let fields: Vec<&str> = line.splitn(4, '\t').collect();
+        let [timestamp, lines, directory, root] = fields[..] else {
+            continue;
+        };
+        let (Ok(timestamp), Ok(lines)) = (timestamp.parse(), lines.parse()) else {
+            continue;
+        };
+        if timestamp <= to && is_root(Path::new(root)) {
+            snapshots
+                .entry(timestamp)
+                .or_default()
+                .insert(directory, lines);
+        }
+    }
+
+    let (&end, after) = snapshots.last_key_value()?;
+    let (&start, before) = match snapshots.range(..=from).next_back() {
+        Some(start) => start,
+        None => snapshots.first_key_value()?,
+    };
+    let mut directories: Vec<&str> = before.keys().chain(after.keys()).copied().collect();
+    directories.sort_unstable();
+    directories.dedup();
+    let mut growth: Vec<Growth> = directories
+        .into_iter()
+        .map(|directory| Growth {
+            directory: directory.to_string(),
+            before: before.get(directory).copied().unwrap_or(0),
+            after: after.get(directory).copied().unwrap_or(0),
+        })
+        .collect();
+    growth.sort_by_key(|row| std::cmp::Reverse(row.change()));
+    Some((start, end, growth))
+}
+
+pub fn thousands(value: i128) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    ternary!(value < 0 => format!("-{grouped}"); grouped)
+}
+
+pub fn ago(seconds: u64) -> String {
+    let plural = |count: u64, unit: &str| match count {
+        1 => format!("1 {unit} ago"),
+        count => format!("{count} {unit}s ago"),
+    };
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => plural(seconds / 60, "minute"),
+        3600..=86_399 => plural(seconds / 3600, "hour"),
+        _ => plural(seconds / 86_400, "day"),
+    }
+}
+
+pub fn delta(previous: Run, current: Run) -> String {
+    let change = current.lines as i128 - previous.lines as i128;
+    let sign = ternary!(change > 0 => "+"; "");
+    let elapsed = current.timestamp.saturating_sub(previous.timestamp);
+    format!(
+        "{sign}{} lines since last run, {}",
+        thousands(change),
+        ago(elapsed)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(2314), "2,314");
+        assert_eq!(thousands(-1_234_567), "-1,234,567");
+        assert_eq!(ago(30), "just now");
+        assert_eq!(ago(3 * 86_400 + 5), "3 days ago");
+        assert_eq!(ago(3600), "1 hour ago");
+
+        let before = Run {
+            timestamp: 0,
+            lines: 1000,
+            bytes: 0,
+        };
+        let after = Run {
+            timestamp: 3 * 86_400,
+            lines: 3314,
+            bytes: 0,
+        };
+        assert_eq!(
+            delta(before, after),
+            "+2,314 lines since last run, 3 days ago"
+        );
+    }
+
+    #[test]
+    fn growth_between_snapshots() -> io::Result<()> {
+        use crate::vfs::MemoryFs;
+        use crate::walk::{Options, Walker};
+
+        let dir = std::env::temp_dir().join(format!("lc-test-{}-growth", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut memory = MemoryFs::new();
+        memory.insert("r/src/a.rs", "1\n");
+        memory.insert("r/docs/a.md", "1\n2\n3\n");
+        memory.insert("r/src/deep/er/b.rs", "1\n");
+        let scan = |memory: &MemoryFs| Walker::new(memory, Options::default()).walk(Path::new("r"));
+        snapshot(&dir, 100, &scan(&memory)?)?;
+        memory.insert("r/src/deep/er/b.rs", "1\n2\n3\n4\n5\n");
+        memory.insert("r/docs/a.md", "1\n");
+        memory.insert("r/tests/t.rs", "1\n");
+        snapshot(&dir, 200, &scan(&memory)?)?;
+        let mut emptied = MemoryFs::new();
+        emptied.insert("r/README", "");
+        snapshot(&dir, 300, &scan(&emptied)?)?;
+
+        let is_root = |root: &Path| root == Path::new("r");
+        let rows = |from, to| {
+            let (start, end, growth) = growth(&dir, &is_root, from, to)?;
+            let rows: Vec<String> = (growth.iter())
+                .map(|row| format!("{} {}->{}", row.directory, row.before, row.after))
+                .collect();
+            Some((start, end, rows))
+        };
+        assert_eq!(
+            rows(150, 250),
+            Some((
+                100,
+                200,
+                [
+                    "src 2->6",
+                    "src/deep 1->5",
+                    ". 5->8",
+                    "tests 0->1",
+                    "docs 3->1"
+                ]
+                .map(str::to_string)
+                .to_vec()
+            ))
+        );
+        // nothing as old as the start, so the first snapshot stands in
+        assert_eq!(
+            rows(0, 250).map(|(start, end, _)| (start, end)),
+            Some((100, 200))
+        );
+        let (_, end, emptied) = rows(150, 300).unwrap();
+        assert_eq!((end, emptied.last().unwrap().as_str()), (300, ". 5->0"));
+        assert_eq!(rows(0, 50), None);
+        assert!(growth(&dir, &|_: &Path| false, 0, 500).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn record_round_trip() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("lc-test-{}-history", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let run = |lines| Run {
+            timestamp: 1,
+            lines,
+            bytes: 2,
+        };
+
+        assert_eq!(record(&dir, Path::new("/a"), run(10))?, None);
+        assert_eq!(record(&dir, Path::new("/b"), run(20))?, None);
+        assert_eq!(record(&dir, Path::new("/a"), run(11))?, Some(run(10)));
+        assert_eq!(record(&dir, Path::new("/a"), run(12))?, Some(run(11)));
+        assert_eq!(record(&dir, Path::new("/b"), run(21))?, Some(run(20)));
+        assert_eq!(
+            runs(&dir),
+            [
+                (PathBuf::from("/a"), run(12)),
+                (PathBuf::from("/b"), run(21))
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use crate::vfs::Kind;
+use crate::walk::Entry;
+
+const STYLE: &str = "body{font:14px/1.4 system-ui,sans-serif;margin:2em;color:#222}\
+h1{font-size:1.3em}table.totals td{text-align:right;padding:0 1em}\
+ul{list-style:none;padding-left:1.2em;margin:0}ul.tree{padding-left:0}\
+summary{cursor:pointer}.row{display:inline-flex;width:calc(100% - 2em)}\
+.name{flex:1}.num{width:8em;text-align:right;font-variant-numeric:tabular-nums}\
+.sort button{margin-right:.5em}";
+
+// re-sorts every list in place; directories keep their open/closed state
+const SCRIPT: &str = "document.querySelectorAll('.sort button').forEach(function (button) {\n\
+  button.addEventListener('click', function () {\n\
+    var key = button.dataset.key;\n\
+    document.querySelectorAll('ul').forEach(function (list) {\n\
+      Array.from(list.children).sort(function (a, b) {\n\
+        return key === 'name' ? a.dataset.name.localeCompare(b.dataset.name)\n\
+          : b.dataset[key] - a.dataset[key];\n\
+      }).forEach(function (item) { list.appendChild(item); });\n\
+    });\n\
+  });\n\
+});\n";
+
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn node(entry: &Entry, name: &str, out: &mut String) {
+    let row = format!(
+        "<span class=\"name\">{}</span><span class=\"num\">{}</span><span class=\"num\">{}</span>",
+        escape(name),
+        entry.lines,
+        entry.bytes
+    );
+    out.push_str(&format!(
+        "<li data-name=\"{}\" data-lines=\"{}\" data-bytes=\"{}\">",
+        escape(name),
+        entry.lines,
+        entry.bytes
+    ));
+    match entry.kind {
+        Kind::Dir => {
+            out.push_str(&format!(
+                "<details open><summary><span class=\"row\">{row}</span></summary><ul>"
+            ));
+            for child in &entry.children {
+                let name = ternary!(child.kind == Kind::Dir => format!("{}/", child.name()); child.name().to_string());
+                node(child, &name, out);
+            }
+            out.push_str("</ul></details>");
+        }
+        _ => out.push_str(&format!("<span class=\"row\">{row}</span>")),
+    }
+    out.push_str("</li>\n");
+}
+
+// one self-contained page: no external scripts or styles, so it can be mailed around
+pub fn render(tree: &Entry) -> String {
+    let root = tree.path.to_string_lossy();
+    let mut out = format!(
+        "<!doctype html>\n<html><head><meta charset=\"utf-8\"><title>lc: {0}</title>\
+         <style>{STYLE}</style></head><body>\n<h1>{0}</h1>\n<table class=\"totals\">\
+         <tr><th>lines</th><td>{1}</td></tr><tr><th>bytes</th><td>{2}</td></tr>\
+         <tr><th>files</th><td>{3}</td></tr><tr><th>directories</th><td>{4}</td></tr></table>\n\
+         <p class=\"sort\">sort by <button data-key=\"name\">name</button>\
+         <button data-key=\"lines\">lines</button><button data-key=\"bytes\">bytes</button></p>\n\
+         <ul class=\"tree\">\n",
+        escape(&root),
+        tree.lines,
+        tree.bytes,
+        tree.files(),
+        tree.directories()
+    );
+    node(tree, &format!("{root}/"), &mut out);
+    out.push_str("</ul>\n<script>\n");
+    out.push_str(SCRIPT);
+    out.push_str("</script>\n</body></html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+    use std::path::Path;
+
+    #[test]
+    fn standalone_page() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/<b>.rs", "1\n2\n");
+        memory.insert("r/sub/c.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        let page = render(&tree);
+        assert!(page.starts_with("<!doctype html>"));
+        assert!(page.contains("<tr><th>lines</th><td>3</td></tr>"));
+        assert!(page.contains("<li data-name=\"&lt;b&gt;.rs\" data-lines=\"2\" data-bytes=\"4\">"));
+        assert!(page
+            .contains("<li data-name=\"sub/\" data-lines=\"1\" data-bytes=\"2\"><details open>"));
+        assert!(!page.contains("src="));
+        assert_eq!(escape("a&'\""), "a&amp;&#39;&quot;");
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+use crate::atomic;
+use crate::encoding::Encoding;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+// a whole scan, so `--load-index` can render it again without touching the tree; bump the
+// version whenever Entry gains a field
+const MAGIC: &[u8] = b"LCIX\x02";
+
+const KINDS: [Kind; 3] = [Kind::File, Kind::Dir, Kind::Other];
+const ENCODINGS: [Encoding; 5] = [
+    Encoding::Utf8,
+    Encoding::Utf8Bom,
+    Encoding::Utf16Le,
+    Encoding::Utf16Be,
+    Encoding::Latin1,
+];
+
+// flag bits after the two of the kind: which optional fields follow, and whether the
+// path is stored relative to the parent's
+const ENCODING: u8 = 1 << 2;
+const ENTROPY: u8 = 1 << 3;
+const HASH: u8 = 1 << 4;
+const MODIFIED: u8 = 1 << 5;
+const LANGUAGE: u8 = 1 << 6;
+const RELATIVE: u8 = 1 << 7;
+
+fn varint(out: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn string(out: &mut Vec<u8>, value: &str) {
+    varint(out, value.len() as u128);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn entry(out: &mut Vec<u8>, parent: &Path, entry: &Entry) {
+    // most paths are their parent's plus a name, so only the name is kept
+    let relative = match entry.path.parent() == Some(parent) {
+        true => entry.path.file_name().map(Path::new),
+        false => None,
+    };
+    let kind = KINDS
+        .iter()
+        .position(|kind| *kind == entry.kind)
+        .unwrap_or(0) as u8;
+    let flags = kind
+        | ternary!(entry.encoding.is_some() => ENCODING; 0)
+        | ternary!(entry.entropy.is_some() => ENTROPY; 0)
+        | ternary!(entry.hash.is_some() => HASH; 0)
+        | ternary!(entry.modified.is_some() => MODIFIED; 0)
+        | ternary!(entry.declared_language.is_some() => LANGUAGE; 0)
+        | ternary!(relative.is_some() => RELATIVE; 0);
+    out.push(flags);
+    string(out, &relative.unwrap_or(&entry.path).to_string_lossy());
+    varint(out, entry.lines);
+    varint(out, entry.bytes as u128);
+    varint(out, entry.dir_bytes as u128);
+    varint(out, entry.chars);
+    varint(out, entry.visual);
+    varint(out, entry.blank);
+    varint(out, entry.comment);
+    varint(out, entry.unscanned as u128);
+    if let Some(encoding) = entry.encoding {
+        out.push(
+            ENCODINGS
+                .iter()
+                .position(|known| *known == encoding)
+                .unwrap_or(0) as u8,
+        );
+    }
+    if let Some(entropy) = entry.entropy {
+        out.extend_from_slice(&entropy.to_le_bytes());
+    }
+    if let Some(hash) = entry.hash {
+        out.extend_from_slice(&hash.to_le_bytes());
+    }
+    if let Some(modified) = entry.modified {
+        // files dated before 1970 come back as the epoch itself
+        let since = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+        varint(out, since.as_secs() as u128);
+        varint(out, since.subsec_nanos() as u128);
+    }
+    if let Some(language) = &entry.declared_language {
+        string(out, language);
+    }
+    varint(out, entry.children.len() as u128);
+    for child in &entry.children {
+        self::entry(out, &entry.path, child);
+    }
+}
+
+pub fn encode(tree: &Entry) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    entry(&mut out, Path::new(""), tree);
+    out
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("lc index: {message}"))
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> io::Result<&[u8]> {
+        if self.data.len() < len {
+            return Err(invalid("truncated"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> io::Result<u128> {
+        let mut value = 0u128;
+        for shift in (0..128).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            value |= ((byte & 0x7f) as u128) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("number out of range"))
+    }
+
+    fn number<T: TryFrom<u128>>(&mut self) -> io::Result<T> {
+        T::try_from(self.varint()?).map_err(|_| invalid("number out of range"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.number()?;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| invalid("path is not utf-8"))
+    }
+
+    fn entry(&mut self, parent: &Path) -> io::Result<Entry> {
+        let flags = self.bytes(1)?[0];
+        let kind = *KINDS
+            .get((flags & 0b11) as usize)
+            .ok_or_else(|| invalid("unknown entry kind"))?;
+        let path = PathBuf::from(self.string()?);
+        let path = ternary!(flags & RELATIVE != 0 => parent.join(path); path);
+        let lines = self.varint()?;
+        let bytes = self.number()?;
+        let dir_bytes = self.number()?;
+        let chars = self.varint()?;
+        let visual = self.varint()?;
+        let blank = self.varint()?;
+        let comment = self.varint()?;
+        let unscanned = self.number()?;
+        let encoding = match flags & ENCODING {
+            0 => None,
+            _ => Some(
+                *ENCODINGS
+                    .get(self.bytes(1)?[0] as usize)
+                    .ok_or_else(|| invalid("unknown encoding"))?,
+            ),
+        };
+        let entropy = match flags & ENTROPY {
+            0 => None,
+            _ => Some(f64::from_le_bytes(self.bytes(8)?.try_into().unwrap())),
+        };
+        let hash = match flags & HASH {
+            0 => None,
+            _ => Some(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap())),
+        };
+        let modified = match flags & MODIFIED {
+            0 => None,
+            _ => {
+                let since = Duration::new(self.number()?, self.number()?);
+                Some(UNIX_EPOCH + since)
+            }
+        };
+        let declared_language = match flags & LANGUAGE {
+            0 => None,
+            _ => Some(self.string()?),
+        };
+        let count: usize = self.number()?;
+        // every entry takes at least eight bytes, so a corrupt count can't reserve much
+        let mut children = Vec::with_capacity(count.min(self.data.len() / 8));
+        for _ in 0..count {
+            children.push(self.entry(&path)?);
+        }
+        Ok(Entry {
+            path,
+            kind,
+            lines,
+            bytes,
+            dir_bytes,
+            encoding,
+            chars,
+            visual,
+            blank,
+            comment,
+            entropy,
+            hash,
+            modified,
+            declared_language,
+            unscanned,
+            children,
+        })
+    }
+}
+
+pub fn decode(data: &[u8]) -> io::Result<Entry> {
+    let data = data
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| invalid("not an index written by this version of lc"))?;
+    let mut reader = Reader { data };
+    let tree = reader.entry(Path::new(""))?;
+    match reader.data.is_empty() {
+        true => Ok(tree),
+        false => Err(invalid("trailing data")),
+    }
+}
+
+pub fn write(path: &Path, tree: &Entry) -> io::Result<()> {
+    atomic::write(path, &encode(tree))
+}
+
+pub fn read(path: &Path) -> io::Result<Entry> {
+    decode(&std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+
+    fn flatten(entry: &Entry, rows: &mut Vec<String>) {
+        rows.push(format!(
+            "{} {:?} {} {} {} {} {} {} {:?} {:?} {:?} {:?} {:?}",
+            entry.path.display(),
+            entry.kind,
+            entry.lines,
+            entry.bytes,
+            entry.dir_bytes,
+            entry.chars,
+            entry.visual,
+            entry.unscanned,
+            entry.encoding,
+            entry.entropy,
+            entry.hash,
+            entry.modified,
+            entry.declared_language,
+        ));
+        entry.children.iter().for_each(|child| flatten(child, rows));
+    }
+
+    #[test]
+    fn round_trip() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "// lc:language=sql\n1\n");
+        memory.insert("r/sub/b.txt", "caf\u{e9}\n");
+        let options = Options {
+            encodings: true,
+            entropy: true,
+            hashes: true,
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        let mut tree = Entry::combine(vec![walker.walk(Path::new("r"))?]);
+        tree.children[0].children[0].modified = Some(UNIX_EPOCH + Duration::new(1, 5));
+
+        let (mut before, mut after) = (Vec::new(), Vec::new());
+        flatten(&tree, &mut before);
+        flatten(&decode(&encode(&tree))?, &mut after);
+        assert_eq!(before, after);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_damage() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let data = encode(&tree);
+        assert!(decode(&data[..data.len() - 1]).is_err());
+        assert!(decode(b"LCIX\x00").is_err());
+        assert!(decode(&[&data[..], b"x"].concat()).is_err());
+        Ok(())
+    }
+}
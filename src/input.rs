@@ -0,0 +1,73 @@
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+// `--files-from FILE`, or standard input for `-`, so `fd -e rs | lc --files-from -` works
+pub fn read_file_list(source: &str) -> io::Result<Vec<u8>> {
+    match source {
+        "-" => {
+            let mut list = Vec::new();
+            io::stdin().lock().read_to_end(&mut list)?;
+            Ok(list)
+        }
+        _ => std::fs::read(source),
+    }
+}
+
+// `find -print0` / `git ls-files -z` output; a NUL can never appear in a real path,
+// so its presence alone is enough to switch modes
+pub fn is_nul_separated(list: &[u8]) -> bool {
+    list.contains(&0)
+}
+
+pub fn parse_file_list(list: &[u8], null: bool) -> Vec<PathBuf> {
+    let separator = match null || is_nul_separated(list) {
+        true => 0,
+        false => b'\n',
+    };
+    list.split(|byte| *byte == separator)
+        .map(|path| match separator {
+            b'\n' => path.strip_suffix(b"\r").unwrap_or(path),
+            _ => path,
+        })
+        .filter(|path| !path.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newline_list() {
+        let paths = parse_file_list(b"src/main.rs\r\nsrc/lock.rs\n\n", false);
+        assert_eq!(
+            paths,
+            [PathBuf::from("src/main.rs"), PathBuf::from("src/lock.rs")]
+        );
+    }
+
+    #[test]
+    fn nul_list() {
+        let list = b"with space.rs\0with\nnewline.rs\0";
+        let expected = [
+            PathBuf::from("with space.rs"),
+            PathBuf::from("with\nnewline.rs"),
+        ];
+        assert!(is_nul_separated(list));
+        assert_eq!(parse_file_list(list, false), expected);
+        // forced, even without a NUL in sight the whole input is one path
+        assert_eq!(parse_file_list(b"a b\nc", true), [PathBuf::from("a b\nc")]);
+    }
+}
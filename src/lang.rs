@@ -0,0 +1,453 @@
+use crate::glob::Glob;
+use crate::report::relative_to;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::io;
+use std::path::Path;
+
+// extension -> language, for breakdowns; anything else has no language
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["rs"]),
+    ("python", &["py", "pyi"]),
+    ("javascript", &["js", "mjs", "cjs", "jsx"]),
+    ("typescript", &["ts", "tsx", "mts", "cts"]),
+    ("go", &["go"]),
+    ("c", &["c", "h"]),
+    ("cpp", &["cc", "cpp", "cxx", "hpp", "hh", "hxx"]),
+    ("csharp", &["cs"]),
+    ("java", &["java"]),
+    ("kotlin", &["kt", "kts"]),
+    ("swift", &["swift"]),
+    ("ruby", &["rb"]),
+    ("php", &["php"]),
+    ("shell", &["sh", "bash", "zsh"]),
+    ("lua", &["lua"]),
+    ("haskell", &["hs"]),
+    ("sql", &["sql"]),
+    ("html", &["html", "htm"]),
+    ("css", &["css", "scss", "sass", "less"]),
+    ("markdown", &["md", "markdown"]),
+    ("toml", &["toml"]),
+    ("yaml", &["yaml", "yml"]),
+    ("json", &["json"]),
+    ("xml", &["xml"]),
+];
+
+const FILENAMES: &[(&str, &str)] = &[
+    ("Makefile", "make"),
+    ("makefile", "make"),
+    ("GNUmakefile", "make"),
+    ("Dockerfile", "dockerfile"),
+    ("CMakeLists.txt", "cmake"),
+];
+
+pub fn language(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    if let Some((_, language)) = FILENAMES.iter().find(|(filename, _)| *filename == name) {
+        return Some(language);
+    }
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(language, _)| *language)
+}
+
+// each language's line comment and block comment delimiters, for `--format cloc`
+type Comments = (Option<&'static str>, Option<(&'static str, &'static str)>);
+const COMMENTS: &[(&[&str], Comments)] = &[
+    (
+        &[
+            "rust",
+            "javascript",
+            "typescript",
+            "go",
+            "c",
+            "cpp",
+            "csharp",
+            "java",
+            "kotlin",
+            "swift",
+            "php",
+        ],
+        (Some("//"), Some(("/*", "*/"))),
+    ),
+    (
+        &[
+            "python",
+            "shell",
+            "ruby",
+            "toml",
+            "yaml",
+            "make",
+            "dockerfile",
+            "cmake",
+        ],
+        (Some("#"), None),
+    ),
+    (&["lua"], (Some("--"), Some(("--[[", "]]")))),
+    (&["haskell"], (Some("--"), Some(("{-", "-}")))),
+    (&["sql"], (Some("--"), Some(("/*", "*/")))),
+    (&["css"], (None, Some(("/*", "*/")))),
+    (&["html", "xml", "markdown"], (None, Some(("<!--", "-->")))),
+];
+
+// languages lc has no syntax for, json among them, have no comments at all
+pub fn comments(language: &str) -> Comments {
+    (COMMENTS.iter())
+        .find(|(languages, _)| languages.contains(&language))
+        .map_or((None, None), |(_, comments)| *comments)
+}
+
+// GitHub's names where lc's differ; the rest are just lowercased
+const LINGUIST: &[(&str, &str)] = &[("c++", "cpp"), ("c#", "csharp"), ("makefile", "make")];
+
+// a `linguist-language=` value as lc names the language
+pub fn from_linguist(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match LINGUIST.iter().find(|(linguist, _)| *linguist == name) {
+        Some((_, language)) => language.to_string(),
+        None => name,
+    }
+}
+
+// `lc:language=sql` near the top of a file, for DSLs and templates with misleading names
+const MARKER: &[u8] = b"lc:language=";
+const MARKER_LINES: usize = 5;
+
+pub fn marker(content: &[u8]) -> Option<String> {
+    for line in content.split(|byte| *byte == b'\n').take(MARKER_LINES) {
+        let Some(at) = line
+            .windows(MARKER.len())
+            .position(|window| window == MARKER)
+        else {
+            continue;
+        };
+        let name: String = line[at + MARKER.len()..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_alphanumeric() || b"_+#-".contains(byte))
+            .map(|byte| byte.to_ascii_lowercase() as char)
+            .collect();
+        if !name.is_empty() {
+            return Some(name);
+        }
+    }
+    None
+}
+
+// lockfiles and codegen output, for --no-generated
+const GENERATED_NAMES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "Pipfile.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+];
+const GENERATED_SUFFIXES: &[&str] = &[".pb.go", "_pb2.py", "_generated.rs", ".g.dart"];
+
+pub fn is_generated_name(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    GENERATED_NAMES.contains(&name)
+        || (GENERATED_SUFFIXES.iter()).any(|suffix| name.ends_with(suffix))
+}
+
+// `@generated` anywhere near the top, or Go's `Code generated ... DO NOT EDIT.` line
+pub fn has_generated_marker(content: &[u8]) -> bool {
+    let contains =
+        |line: &[u8], needle: &[u8]| line.windows(needle.len()).any(|window| window == needle);
+    (content.split(|byte| *byte == b'\n').take(MARKER_LINES)).any(|line| {
+        contains(line, b"@generated")
+            || (contains(line, b"Code generated ") && contains(line, b"DO NOT EDIT"))
+    })
+}
+
+// the spec caps pointer files at 1024 bytes
+pub const LFS_POINTER_MAX: u64 = 1024;
+
+// the `size` line of a git LFS pointer file, which has to open with the spec's version line
+pub fn lfs_size(content: &[u8]) -> Option<u64> {
+    let content = std::str::from_utf8(content).ok()?;
+    let mut lines = content.lines();
+    if !lines
+        .next()?
+        .starts_with("version https://git-lfs.github.com/spec/")
+    {
+        return None;
+    }
+    lines.find_map(|line| line.strip_prefix("size ")?.trim().parse().ok())
+}
+
+// `<glob> <language>` per line, globs relative to the scanned root
+pub fn parse_overrides(contents: &str) -> io::Result<Vec<(Glob, String)>> {
+    let mut overrides = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.rsplit_once(char::is_whitespace) {
+            Some((pattern, language)) => {
+                overrides.push((Glob::new(pattern.trim()), language.to_ascii_lowercase()))
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "language overrides line {}: expected `<glob> <language>`",
+                        number + 1
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(overrides)
+}
+
+// the last matching line wins, but a marker inside the file beats them all
+pub fn apply_overrides(tree: &mut Entry, overrides: &[(Glob, String)]) {
+    fn visit(root: &Path, entry: &mut Entry, overrides: &[(Glob, String)]) {
+        if entry.kind == Kind::Dir {
+            for child in &mut entry.children {
+                visit(root, child, overrides);
+            }
+        } else if entry.declared_language.is_none() {
+            let relative = relative_to(root, &entry.path);
+            entry.declared_language = overrides
+                .iter()
+                .rev()
+                .find(|(glob, _)| glob.is_match(relative, false))
+                .map(|(_, language)| language.clone());
+        }
+    }
+
+    let root = tree.path.clone();
+    visit(&root, tree, overrides);
+}
+
+// images, audio and video
+pub const MEDIA: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "tif", "tiff", "psd", "heic", "mp3", "wav",
+    "flac", "ogg", "aac", "m4a", "mp4", "m4v", "mov", "avi", "mkv", "webm", "wmv",
+];
+
+// compiled programs, libraries and objects
+const EXECUTABLE: &[&str] = &[
+    "o", "obj", "a", "lib", "so", "dylib", "dll", "exe", "class", "pyc", "wasm", "rlib",
+];
+
+// never text, so never worth opening
+const BINARY: &[&str] = &[
+    "zip", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "tar", "jar", "war", "whl", "ttf", "otf",
+    "woff", "woff2", "eot",
+];
+
+// languages that are prose rather than code
+const PROSE: &[&str] = &["markdown"];
+
+fn extension(path: &Path) -> Option<String> {
+    Some(path.extension()?.to_str()?.to_ascii_lowercase())
+}
+
+pub fn is_binary(path: &Path) -> bool {
+    !matches!(
+        content_type(path, None),
+        ContentType::Code | ContentType::Text
+    )
+}
+
+// what `--type` picks files by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ContentType {
+    Code,
+    // javascript and css written by a minifier, not a person
+    Minified,
+    Text,
+    Media,
+    Executable,
+    // archives and fonts
+    Binary,
+}
+
+impl ContentType {
+    pub const ALL: [ContentType; 6] = [
+        ContentType::Code,
+        ContentType::Minified,
+        ContentType::Text,
+        ContentType::Media,
+        ContentType::Executable,
+        ContentType::Binary,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentType::Code => "code",
+            ContentType::Minified => "minified",
+            ContentType::Text => "text",
+            ContentType::Media => "media",
+            ContentType::Executable => "executable",
+            ContentType::Binary => "binary",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ContentType> {
+        ContentType::ALL
+            .into_iter()
+            .find(|content_type| content_type.name() == name)
+    }
+}
+
+// `language` is the file's own, declared or guessed; files with none are text unless their
+// extension says otherwise
+pub fn content_type(path: &Path, language: Option<&str>) -> ContentType {
+    let extension = extension(path).unwrap_or_default();
+    let extension = extension.as_str();
+    match language {
+        _ if MEDIA.contains(&extension) => ContentType::Media,
+        _ if EXECUTABLE.contains(&extension) => ContentType::Executable,
+        _ if BINARY.contains(&extension) => ContentType::Binary,
+        Some(language) if !PROSE.contains(&language) => ContentType::Code,
+        _ => ContentType::Text,
+    }
+}
+
+// web assets averaging longer lines than this came out of a minifier
+const MINIFIED_LINE: u128 = 500;
+const MINIFIABLE: &[&str] = &["javascript", "css"];
+
+// by a `.min.js`-style name, or a handful of enormous lines
+pub fn is_minified(path: &Path, language: Option<&str>, lines: u128, bytes: u64) -> bool {
+    if !language.is_some_and(|language| MINIFIABLE.contains(&language)) {
+        return false;
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = name.rsplit_once('.').map_or("", |(stem, _)| stem);
+    stem.ends_with(".min") || (lines > 0 && u128::from(bytes) / lines > MINIFIED_LINE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages() {
+        assert_eq!(language(Path::new("src/main.rs")), Some("rust"));
+        assert_eq!(language(Path::new("App.TSX")), Some("typescript"));
+        assert_eq!(language(Path::new("a/Makefile")), Some("make"));
+        assert_eq!(language(Path::new("LICENSE")), None);
+    }
+
+    #[test]
+    fn content_types() {
+        let at = |path: &str| content_type(Path::new(path), language(Path::new(path)));
+        assert_eq!(at("src/main.rs"), ContentType::Code);
+        assert_eq!(at("README.md"), ContentType::Text);
+        assert_eq!(at("LICENSE"), ContentType::Text);
+        assert_eq!(at("logo.PNG"), ContentType::Media);
+        assert_eq!(at("target/lc.exe"), ContentType::Executable);
+        assert_eq!(at("fonts/a.woff2"), ContentType::Binary);
+        assert_eq!(
+            content_type(Path::new("query"), Some("sql")),
+            ContentType::Code
+        );
+        assert_eq!(ContentType::from_name("media"), Some(ContentType::Media));
+        assert!(is_binary(Path::new("a.so")) && !is_binary(Path::new("a.txt")));
+    }
+
+    #[test]
+    fn markers() {
+        assert_eq!(
+            marker(b"-- lc:language=SQL\nselect 1;\n"),
+            Some("sql".to_string())
+        );
+        assert_eq!(
+            marker(b"\n\n\n\n# lc:language=c++ template\n"),
+            Some("c++".to_string())
+        );
+        assert_eq!(marker(b"\n\n\n\n\nlc:language=sql\n"), None);
+        assert_eq!(marker(b"lc:language=\n"), None);
+    }
+
+    #[test]
+    fn minified() {
+        let js = Some("javascript");
+        assert!(is_minified(Path::new("vendor/jquery.min.js"), js, 2, 900));
+        assert!(is_minified(Path::new("dist/app.js"), js, 3, 90_000));
+        assert!(!is_minified(Path::new("src/app.js"), js, 300, 9_000));
+        assert!(!is_minified(
+            Path::new("data.json"),
+            Some("json"),
+            1,
+            90_000
+        ));
+        assert!(!is_minified(Path::new("empty.js"), js, 0, 0));
+    }
+
+    #[test]
+    fn generated() {
+        assert!(is_generated_name(Path::new("Cargo.lock")));
+        assert!(is_generated_name(Path::new("api/v1/service.pb.go")));
+        assert!(!is_generated_name(Path::new("src/lock.rs")));
+        assert!(has_generated_marker(
+            b"// @generated by build.rs\nfn x() {}\n"
+        ));
+        assert!(has_generated_marker(
+            b"// Code generated by protoc-gen-go. DO NOT EDIT.\n"
+        ));
+        assert!(!has_generated_marker(b"\n\n\n\n\n// @generated\n"));
+        assert!(!has_generated_marker(b"// Code generated by hand\n"));
+    }
+
+    #[test]
+    fn lfs_pointers() {
+        let pointer = b"version https://git-lfs.github.com/spec/v1\n\
+            oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+            size 12345\n";
+        assert_eq!(lfs_size(pointer), Some(12345));
+        assert_eq!(lfs_size(b"size 12345\n"), None);
+        assert_eq!(
+            lfs_size(b"version https://git-lfs.github.com/spec/v1\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn overrides() -> io::Result<()> {
+        use crate::vfs::MemoryFs;
+        use crate::walk::{Options, Walker};
+
+        let mut memory = MemoryFs::new();
+        memory.insert("r/queries/a.txt", "select 1;\n");
+        memory.insert("r/queries/b.txt", "lc:language=jinja\n");
+        memory.insert("r/notes.txt", "");
+        let mut tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let overrides = parse_overrides("# sql lives here\nqueries/*.txt sql\n")?;
+        apply_overrides(&mut tree, &overrides);
+
+        let languages: Vec<Option<&str>> = tree
+            .children
+            .iter()
+            .flat_map(|child| match child.kind {
+                Kind::Dir => child.children.iter().map(Entry::language).collect(),
+                _ => vec![child.language()],
+            })
+            .collect();
+        assert_eq!(languages, [None, Some("sql"), Some("jinja")]);
+        assert!(parse_overrides("lonely").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn binaries() {
+        assert!(is_binary(Path::new("assets/intro.MP4")));
+        assert!(is_binary(Path::new("fonts/Inter.woff2")));
+        assert!(is_binary(Path::new("target/main.o")));
+        assert!(!is_binary(Path::new("src/main.rs")));
+        assert!(!is_binary(Path::new("Makefile")));
+    }
+}
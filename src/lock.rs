@@ -0,0 +1,160 @@
+use crate::report::stable_id;
+use crate::vfs::Kind;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// lockfiles this process holds, for `release_all`
+static HELD: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+// how long a lockfile may sit without a pid before its holder is taken for dead
+const UNWRITTEN_GRACE: Duration = Duration::from_secs(5);
+
+// held for the whole scan; dropping it lets the next queued invocation through
+pub struct ScanLock {
+    path: PathBuf,
+}
+
+impl ScanLock {
+    // one lockfile per scanned root, kept in the temp dir so the tree stays untouched. the
+    // name is a fixed hash of the path, so lc builds from any toolchain agree on it
+    pub fn lockfile(root: &Path) -> PathBuf {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        std::env::temp_dir().join(format!("lc-{}.lock", stable_id(Kind::Dir, &root)))
+    }
+
+    pub fn acquire(root: &Path) -> std::io::Result<ScanLock> {
+        let path = ScanLock::lockfile(root);
+        let pid = std::process::id();
+        // the pid goes into a file of our own first and is linked into place whole, so the
+        // lockfile is never seen without it
+        let pending = path.with_extension(format!("{pid}.lock"));
+        fs::write(&pending, format!("{pid}\n"))?;
+        let mut announced = false;
+
+        let locked = loop {
+            match fs::hard_link(&pending, &path) {
+                Ok(()) => {
+                    HELD.lock().unwrap().push(path.clone());
+                    break Ok(ScanLock { path });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    let holder = fs::read_to_string(&path).unwrap_or_default();
+                    if is_stale(&path, holder.trim()) {
+                        // another waiter may have cleared it and taken the lock meanwhile
+                        if fs::read_to_string(&path).is_ok_and(|again| again == holder) {
+                            let _ = fs::remove_file(&path);
+                        }
+                        continue;
+                    }
+                    if !announced {
+                        let holder = holder.trim();
+                        eprintln!("[lock]   waiting for lc (pid {holder}) scanning the same tree");
+                        announced = true;
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(err) => break Err(err),
+            }
+        };
+        let _ = fs::remove_file(&pending);
+        locked
+    }
+}
+
+impl Drop for ScanLock {
+    fn drop(&mut self) {
+        HELD.lock().unwrap().retain(|held| *held != self.path);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// for an exit that won't run `Drop`
+pub fn release_all() {
+    for path in HELD.lock().unwrap().drain(..) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// a holder that crashed leaves its lockfile behind. One with no pid in it comes from a
+// holder that died while writing it, once it is older than any write could take
+fn is_stale(path: &Path, holder: &str) -> bool {
+    match holder.parse::<u32>() {
+        Ok(pid) => !is_running(pid),
+        Err(_) => fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > UNWRITTEN_GRACE),
+    }
+}
+
+fn is_running(pid: u32) -> bool {
+    if Path::new("/proc/self").exists() {
+        return Path::new(&format!("/proc/{pid}")).exists();
+    }
+    // no procfs: ask the system, and assume the holder is alive when it cannot say
+    let probe = if cfg!(windows) {
+        Command::new("tasklist")
+            .args(["/NH", "/FI", &format!("PID eq {pid}")])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+    } else {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+    };
+    probe.unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_on_drop() -> std::io::Result<()> {
+        let root = Path::new("src");
+        let lockfile = ScanLock::lockfile(root);
+        {
+            let _lock = ScanLock::acquire(root)?;
+            assert!(lockfile.exists());
+        }
+        assert!(!lockfile.exists());
+
+        // what an exit does instead of dropping it
+        let lock = ScanLock::acquire(root)?;
+        release_all();
+        assert!(!lockfile.exists());
+        drop(lock);
+        Ok(())
+    }
+
+    #[test]
+    fn stale_locks() -> std::io::Result<()> {
+        let root = Path::new("src/bin");
+        let lockfile = ScanLock::lockfile(root);
+        // a holder that died before writing its pid, and one that died after
+        fs::write(&lockfile, "")?;
+        let old = std::time::SystemTime::now() - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&lockfile)?
+            .set_modified(old)?;
+        assert!(is_stale(&lockfile, ""));
+        drop(ScanLock::acquire(root)?);
+        fs::write(&lockfile, format!("{}\n", u32::MAX))?;
+        assert!(is_stale(&lockfile, &u32::MAX.to_string()));
+        drop(ScanLock::acquire(root)?);
+        assert!(!lockfile.exists());
+
+        fs::write(&lockfile, "")?;
+        assert!(!is_stale(&lockfile, ""));
+        assert!(!is_stale(&lockfile, &std::process::id().to_string()));
+        fs::remove_file(&lockfile)
+    }
+}
@@ -1,9 +1,3 @@
-use clap::{App, Arg};
-use std::io::BufRead;
-use std::path::Path;
-use std::time::Instant;
-use std::{fs, process};
-
 macro_rules! ternary {
     ($test:expr => $true_expr:expr; $false_expr:expr) => {
         if $test {
@@ -14,311 +8,2960 @@ macro_rules! ternary {
     };
 }
 
+mod atomic;
+mod attributes;
+mod badge;
+mod bench;
+mod cache;
+mod diff;
+mod document;
+mod encoding;
+mod error;
+mod exec;
+mod export;
+mod expr;
+mod gitignore;
+mod glob;
+mod goal;
+mod history;
+mod html;
+mod index;
+mod input;
+mod json;
+mod lang;
+mod lock;
+mod manifest;
+mod multi;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod progress;
+mod regex;
+mod report;
+mod rules;
+mod services;
+mod sign;
+mod stats;
+mod time;
+mod verify;
+mod vfs;
+mod walk;
+mod xxh3;
+mod yaml;
+mod zstd;
+
+use cache::{Cache, CacheStats};
+use clap::{App, Arg, ArgGroup};
+use error::LcError;
+use json::Json;
+use lock::ScanLock;
+use stats::{EntropyReport, LanguageTotals, PathLengths, DEFAULT_PATH_LIMIT};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use vfs::{Kind, RealFs};
+use walk::{Entry, Options, Verdict, Walker};
+
 trait Visible {
     fn is_visible(&self) -> bool;
 }
 
 impl Visible for Path {
     fn is_visible(&self) -> bool {
-        let filename = self.file_name().unwrap_or_default().to_str().unwrap_or_default();
-        ternary!(filename.starts_with(".") => return false; return true);
+        let filename = self
+            .file_name()
+            .unwrap_or_default()
+            .to_str()
+            .unwrap_or_default();
+        ternary!(filename.starts_with('.') => false; true)
     }
 }
 
-trait Ignore {
-    fn ignore(&self, gitignore: Vec<String>) -> bool;
+// editor temp and backup files: `notes.txt~`, `main.rs.swp`, `#draft.md#`, `.DS_Store`
+const JUNK_SUFFIXES: &[&str] = &["~", ".swp", ".swo", ".tmp"];
+const JUNK_NAMES: &[&str] = &[".DS_Store", "Thumbs.db"];
+
+trait Junk {
+    fn is_junk(&self) -> bool;
+}
+
+impl Junk for Path {
+    fn is_junk(&self) -> bool {
+        let filename = self.file_name().unwrap_or_default().to_string_lossy();
+        JUNK_NAMES.contains(&&*filename)
+            || JUNK_SUFFIXES
+                .iter()
+                .any(|suffix| filename.ends_with(suffix))
+            || (filename.len() > 1 && filename.starts_with('#') && filename.ends_with('#'))
+    }
 }
 
-impl Ignore for Path {
-    fn ignore(&self, gitignore: Vec<String>) -> bool {
-        for i in gitignore {
-            if self.file_name().unwrap().to_str().unwrap() == i {
-                return true;
-            }
+// files lc writes itself; these are never counted, even inside the scanned tree
+const CACHE_FILE: &str = ".lc-cache";
+const CHECKPOINT_FILE: &str = ".lc-checkpoint";
+
+// report paths registered by writers before they open their output
+static OUTPUTS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+// set once --max-time runs out; the walk notices at its next directory or file
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    // the file may not exist yet, so resolve its directory instead
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn reserve_output(path: &Path) {
+    OUTPUTS.lock().unwrap().push(absolute(path));
+}
+
+trait Reserved {
+    fn is_reserved(&self) -> bool;
+}
+
+impl Reserved for Path {
+    fn is_reserved(&self) -> bool {
+        let filename = self.file_name().unwrap_or_default();
+        if filename == CACHE_FILE || filename == CHECKPOINT_FILE {
+            return true;
+        }
+        if filename.to_string_lossy().contains(atomic::TEMP_MARKER) {
+            return true;
+        }
+        let outputs = OUTPUTS.lock().unwrap();
+        if !outputs
+            .iter()
+            .any(|output| output.file_name() == Some(filename))
+        {
+            return false;
         }
-        false
+        // only resolve when the name matches; this runs for every entry
+        outputs.contains(&absolute(self))
     }
 }
 
 const WIDTH: usize = 50;
+// bytes of rendered rows held before a write to stdout
+const RENDER_BUFFER: usize = 64 * 1024;
 
 fn fetch_directory() -> std::io::Result<String> {
     let output = process::Command::new("pwd").output()?;
     let mut current_dir = String::from_utf8_lossy(&output.stdout).into_owned();
     current_dir.pop();
 
-    return Ok(current_dir);
+    Ok(current_dir)
+}
+
+fn newest(entry: &Entry) -> String {
+    entry.modified.map_or("-".to_string(), time::iso8601_utc)
+}
+
+// the path that stands for standard input, as in `cat app.log | lc -`
+const STDIN: &str = "-";
+
+// set by `--no-tty`; see `plain`
+static NO_TTY: AtomicBool = AtomicBool::new(false);
+
+// CI services set these, whether or not they give the job a terminal
+const CI_VARS: [&str; 6] = [
+    "CI",
+    "GITHUB_ACTIONS",
+    "JENKINS_URL",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "TF_BUILD",
+];
+
+// output meant for a log rather than a person at a terminal: no color, and nothing but
+// ASCII in the bars and sparklines
+fn plain() -> bool {
+    static PLAIN: OnceLock<bool> = OnceLock::new();
+    *PLAIN.get_or_init(|| {
+        NO_TTY.load(Ordering::Relaxed)
+            || !io::stdout().is_terminal()
+            || CI_VARS.iter().any(|var| std::env::var_os(var).is_some())
+    })
+}
+
+// a row's name and how many columns it takes: control characters are escaped so a file
+// name can't move the cursor or color a log, and dotfiles are dimmed outside plain output,
+// so what --hidden let in stands out
+fn shown<'a>(text: &'a str, path: &Path) -> (Cow<'a, str>, usize) {
+    let mut text = match text.contains(char::is_control) {
+        true => Cow::Owned(text.chars().flat_map(char::escape_default).collect()),
+        false => Cow::Borrowed(text),
+    };
+    let width = text.chars().count();
+    if !plain() && std::env::var_os("NO_COLOR").is_none() && !path.is_visible() {
+        text = Cow::Owned(format!("\x1b[2m{text}\x1b[0m"));
+    }
+    (text, width)
+}
+
+// a line after the totals: below the tree when there is one, otherwise on stderr, so a bare
+// total stays the only thing on stdout and scripts can keep parsing it
+fn summary(tree_display: bool, line: &str) {
+    ternary!(tree_display => println!("{line}"); eprintln!("{line}"));
+}
+
+// `mtime` adds the newest modification time below each directory to its row. rows are
+// written straight into `out`, so a huge tree costs no allocation per line
+fn print_tree(out: &mut impl Write, entry: &Entry, indent: usize, mtime: bool) -> io::Result<()> {
+    let (path, width) = shown(entry.path.to_str().unwrap_or("???"), &entry.path);
+    let pad = WIDTH.saturating_sub(width);
+    // a file given as a root is just its own row
+    if entry.kind != Kind::Dir {
+        return writeln!(out, "{:indent$}{path}{:pad$} {}", "", "", entry.lines);
+    }
+    if entry.unreadable() {
+        let unscanned = human_bytes(entry.unscanned);
+        return writeln!(out, "{:indent$}{path}/   unscanned: ~{unscanned}", "");
+    }
+    match mtime {
+        true => writeln!(out, "{:indent$}{path}/   {}", "", newest(entry))?,
+        false => writeln!(out, "{:indent$}{path}/", "")?,
+    }
+
+    for child in &entry.children {
+        if child.kind == Kind::Dir {
+            print_tree(out, child, indent + 2, mtime)?;
+            continue;
+        }
+        let (name, width) = shown(child.name(), &child.path);
+        let pad = WIDTH.saturating_sub(width);
+        writeln!(
+            out,
+            "{:indent$}{name}{:pad$} {}",
+            "",
+            "",
+            child.lines,
+            indent = indent + 2
+        )?;
+    }
+    Ok(())
+}
+
+// `--max-entries`: lets the first `remaining` rows through and counts the rest instead of
+// writing them. rows are counted by their newlines, however the writes split them
+struct RowLimit<W: Write> {
+    inner: W,
+    remaining: usize,
+    omitted: usize,
+}
+
+impl<W: Write> Write for RowLimit<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut shown = 0;
+        for (at, byte) in buf.iter().enumerate() {
+            if self.remaining == 0 {
+                break;
+            }
+            if *byte == b'\n' {
+                self.remaining -= 1;
+            }
+            shown = at + 1;
+        }
+        self.inner.write_all(&buf[..shown])?;
+        self.omitted += buf[shown..].iter().filter(|byte| **byte == b'\n').count();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// one row per top-level entry; subdirectories are summed as a single row
+fn print_shallow(out: &mut impl Write, tree: &Entry, mtime: bool) -> io::Result<()> {
+    if tree.kind != Kind::Dir {
+        return print_tree(out, tree, 0, mtime);
+    }
+    writeln!(out, "{}/", tree.path.to_str().unwrap_or("???"))?;
+
+    for child in &tree.children {
+        let (name, slash) = (child.name(), ternary!(child.kind == Kind::Dir => "/"; ""));
+        let (name, width) = shown(name, &child.path);
+        let pad = WIDTH.saturating_sub(width + slash.len());
+        write!(out, "  {name}{slash}{:pad$} {}", "", child.lines)?;
+        match mtime && child.kind == Kind::Dir {
+            true => writeln!(out, "   {}", newest(child))?,
+            false => writeln!(out)?,
+        }
+    }
+    Ok(())
+}
+
+// every matching directory, nested matches included, rendered independently
+fn print_root_at(
+    out: &mut impl Write,
+    tree: &Entry,
+    glob: &glob::Glob,
+    mtime: bool,
+) -> io::Result<()> {
+    fn find<'a>(tree: &Entry, entry: &'a Entry, glob: &glob::Glob, found: &mut Vec<&'a Entry>) {
+        for child in entry
+            .children
+            .iter()
+            .filter(|child| child.kind == Kind::Dir)
+        {
+            if glob.is_match(report::relative(tree, &child.path), true) {
+                found.push(child);
+            }
+            find(tree, child, glob, found);
+        }
+    }
+
+    let mut found = Vec::new();
+    find(tree, tree, glob, &mut found);
+    if found.is_empty() {
+        writeln!(out, "no directory matches {}", glob.as_str())?;
+    }
+    for (i, root) in found.iter().enumerate() {
+        ternary!(i > 0 => writeln!(out)?; ());
+        print_tree(out, root, 0, mtime)?;
+        writeln!(out, "  {:width$} {}", "[total]", root.lines, width = WIDTH)?;
+    }
+    Ok(())
+}
+
+fn print_path_lengths(tree: &Entry, limit: usize) {
+    let lengths = PathLengths::collect(tree, limit);
+    println!("\n[paths]");
+    println!(
+        "  {:width$} {} {}",
+        "deepest",
+        lengths.deepest,
+        lengths.deepest_path.display(),
+        width = WIDTH
+    );
+    println!(
+        "  {:width$} {} {}",
+        "longest",
+        lengths.longest,
+        lengths.longest_path.display(),
+        width = WIDTH
+    );
+    println!(
+        "  {:width$} {}",
+        format!("over {limit}"),
+        lengths.over_limit.len(),
+        width = WIDTH
+    );
+    for (path, length) in &lengths.over_limit {
+        println!("    {length} {}", path.display());
+    }
+}
+
+fn print_depth_profile(tree: &Entry) {
+    let profile = stats::depth_profile(tree);
+    println!("\n[depths]   {}", stats::sparkline(&profile, plain()));
+    let total = tree.lines.max(1) as f64;
+    for (depth, lines) in profile.iter().enumerate() {
+        println!(
+            "  {:width$} {:>5.1}% {}",
+            format!("depth {}", depth + 1),
+            *lines as f64 / total * 100.0,
+            history::thousands(*lines as i128),
+            width = WIDTH
+        );
+    }
+}
+
+fn print_entropy(tree: &Entry) {
+    let report = EntropyReport::collect(tree);
+    println!("\n[entropy]");
+    println!(
+        "  {:width$} {:.2} bits/byte over {} files",
+        "average",
+        report.average,
+        report.files,
+        width = WIDTH
+    );
+    println!(
+        "  {:width$} {}",
+        "near-random",
+        report.random.len(),
+        width = WIDTH
+    );
+    for (path, entropy) in &report.random {
+        println!("    {entropy:.2} {}", path.display());
+    }
+    println!(
+        "  {:width$} {}",
+        "repetitive",
+        report.repetitive.len(),
+        width = WIDTH
+    );
+    for (path, entropy) in &report.repetitive {
+        println!("    {entropy:.2} {}", path.display());
+    }
+}
+
+fn print_services(tree: &Entry, services: &[(String, PathBuf)]) {
+    println!("\n[services]");
+    for total in services::totals(tree, services) {
+        println!(
+            "  {:width$} {} ({} files)",
+            total.name,
+            total.lines,
+            total.files,
+            width = WIDTH
+        );
+    }
+}
+
+fn services_json(tree: &Entry, services: &[(String, PathBuf)]) -> Json {
+    let totals = services::totals(tree, services)
+        .into_iter()
+        .map(|total| {
+            Json::object(vec![
+                ("name", Json::str(total.name)),
+                ("files", Json::from(total.files)),
+                ("lines", Json::from(total.lines)),
+                ("bytes", Json::from(total.bytes)),
+            ])
+        })
+        .collect();
+    Json::Array(totals)
+}
+
+fn print_encodings(tree: &Entry) {
+    println!("\n[encodings]");
+    for (encoding, totals) in stats::encodings(tree) {
+        println!(
+            "  {:width$} {} lines, {} chars ({} files)",
+            encoding.name(),
+            totals.lines,
+            totals.chars,
+            totals.files,
+            width = WIDTH
+        );
+    }
+}
+
+fn encodings_json(tree: &Entry) -> Json {
+    let breakdown = stats::encodings(tree)
+        .into_iter()
+        .map(|(encoding, totals)| {
+            Json::object(vec![
+                ("encoding", Json::str(encoding.name())),
+                ("files", Json::from(totals.files)),
+                ("lines", Json::from(totals.lines)),
+                ("chars", Json::from(totals.chars)),
+                ("bytes", Json::from(totals.bytes)),
+            ])
+        })
+        .collect();
+    Json::Array(breakdown)
+}
+
+// what the last recorded run of this root came to, for a progress ETA or an estimate;
+// several roots are recorded together, so they have none
+fn previous_run(paths: &[PathBuf]) -> Option<history::Run> {
+    let [path] = paths else {
+        return None;
+    };
+    let runs = history::runs(&history::cache_dir()?);
+    let previous = runs.into_iter().find(|(root, _)| root == path);
+    previous.map(|(_, run)| run)
+}
+
+// `--progressive`: every file sized from metadata alone, none opened, and lines put at the
+// last run's lines per byte or, failing that, `stats::BYTES_PER_LINE` of text
+fn print_estimate(options: &walk::Options, paths: &[PathBuf]) -> Result<(), LcError> {
+    let started = Instant::now();
+    let options = walk::Options {
+        skip_larger_than: Some(0),
+        ..options.clone()
+    };
+    let walker = Walker::new(&RealFs, options);
+    let mut roots = Vec::with_capacity(paths.len());
+    for path in paths {
+        roots.push(walker.walk(path)?);
+    }
+    let tree = ternary!(roots.len() > 1 => Entry::combine(roots); roots.pop().unwrap());
+    let lines = match previous_run(paths) {
+        Some(run) if run.bytes > 0 => {
+            (tree.bytes as f64 * run.lines as f64 / run.bytes as f64) as u128
+        }
+        _ => stats::estimated_lines(&tree, stats::BYTES_PER_LINE),
+    };
+    eprintln!(
+        "[estimate]   ~{lines} lines in {} files, {} ({:.2?}, from metadata; exact totals follow)",
+        tree.files(),
+        human_bytes(tree.bytes),
+        started.elapsed()
+    );
+    Ok(())
+}
+
+// where --cache keeps its files: each scanned directory, a file's own directory, and the
+// working directory for a file list, whose paths are relative to it
+fn cache_roots(calls: &clap::ArgMatches, paths: &[PathBuf], cwd: &str) -> Vec<PathBuf> {
+    if calls.is_present("files-from") {
+        return vec![PathBuf::from(cwd)];
+    }
+    let mut roots: Vec<PathBuf> = (paths.iter())
+        .filter(|path| *path != Path::new(STDIN))
+        .map(|path| match path.is_dir() {
+            true => path.clone(),
+            false => match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            },
+        })
+        .collect();
+    roots.dedup();
+    ternary!(roots.is_empty() => vec![PathBuf::from(cwd)]; roots)
+}
+
+// history is a nicety, so failing to read or write it never fails the scan
+fn record_run(tree: &Entry) -> Option<(history::Run, history::Run)> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    let current = history::Run {
+        timestamp,
+        lines: tree.lines,
+        bytes: tree.bytes,
+    };
+    let dir = history::cache_dir()?;
+    // a lost snapshot only leaves a gap in what `lc growth` can look back on
+    let _ = history::snapshot(&dir, timestamp, tree);
+    let previous = history::record(&dir, &tree.path, current).ok()??;
+    Some((previous, current))
+}
+
+// fields: lines, bytes, files, directories and langs.<language>.{lines,bytes,files}
+fn report_field(
+    tree: &Entry,
+    languages: &BTreeMap<String, LanguageTotals>,
+    types: &BTreeMap<lang::ContentType, LanguageTotals>,
+    vendored: LanguageTotals,
+    name: &str,
+) -> Option<f64> {
+    let total = |totals: LanguageTotals, field| match field {
+        "lines" => Some(totals.lines as f64),
+        "bytes" => Some(totals.bytes as f64),
+        "files" => Some(totals.files as f64),
+        _ => None,
+    };
+    let fields: Vec<&str> = name.split('.').collect();
+    match fields[..] {
+        ["lines"] => Some(tree.lines as f64),
+        ["bytes"] => Some(tree.bytes as f64),
+        ["files"] => Some(tree.files() as f64),
+        ["directories"] => Some(tree.directories() as f64),
+        ["langs", language, field] => {
+            total(languages.get(language).copied().unwrap_or_default(), field)
+        }
+        ["vendored", field] => total(vendored, field),
+        ["types", content_type, field] => {
+            let content_type = lang::ContentType::from_name(content_type)?;
+            total(types.get(&content_type).copied().unwrap_or_default(), field)
+        }
+        _ => None,
+    }
+}
+
+// the names an --assert or .lcrules expression can use, over one scan
+fn report_fields(tree: &Entry) -> impl Fn(&str) -> Option<f64> + '_ {
+    let languages = stats::languages(tree);
+    let types = stats::content_types(tree);
+    let vendored = stats::vendored(tree);
+    move |name| report_field(tree, &languages, &types, vendored, name)
 }
 
-fn detect_gitignore(pathstr: &str) -> Vec<String> {
-    let mut path = pathstr.to_string();
-    path.push_str("/.gitignore");
-    let contents: Vec<u8> = fs::read(path).unwrap_or(Vec::new());
+// true when any assertion failed; a malformed one is a usage error and exits right away
+fn check_assertions(tree: &Entry, assertions: &[&str]) -> bool {
+    let lookup = report_fields(tree);
+    let mut failed = false;
+    for assertion in assertions {
+        match expr::evaluate(assertion, &lookup) {
+            Ok(value) if value != 0.0 => {}
+            Ok(_) => {
+                eprintln!("lc: assertion failed: {assertion}");
+                failed = true;
+            }
+            Err(err) => {
+                eprintln!("lc: invalid --assert '{assertion}': {err}");
+                exit(2);
+            }
+        }
+    }
+    failed
+}
+
+fn scan_repositories(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let options = walk_options(calls);
+    let repos = multi::parse_repos(&std::fs::read_to_string(calls.value_of("repos").unwrap())?);
+    let workdir = match calls.value_of("workdir") {
+        Some(dir) => PathBuf::from(dir),
+        None => history::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("repos"),
+    };
+
+    println!(
+        "{:width$} {:>12} {:>8} {:>12}",
+        "[repository]",
+        "lines",
+        "files",
+        "bytes",
+        width = WIDTH
+    );
+    let (mut lines, mut files, mut bytes, mut failed) = (0u128, 0usize, 0u64, false);
+    for source in &repos {
+        // one unreachable repository should not sink an org-wide inventory
+        let tree = multi::checkout(source, &workdir)
+            .and_then(|path| Walker::new(&RealFs, options.clone()).walk(&path));
+        match tree {
+            Ok(tree) => {
+                println!(
+                    "{:width$} {:>12} {:>8} {:>12}",
+                    source.name(),
+                    tree.lines,
+                    tree.files(),
+                    tree.bytes,
+                    width = WIDTH
+                );
+                lines += tree.lines;
+                files += tree.files();
+                bytes += tree.bytes;
+            }
+            Err(err) => {
+                eprintln!("lc: {}: {err}", source.name());
+                failed = true;
+            }
+        }
+    }
+    println!(
+        "{:width$} {:>12} {:>8} {:>12}",
+        "[total]",
+        lines,
+        files,
+        bytes,
+        width = WIDTH
+    );
+
+    if failed {
+        exit(1);
+    }
+    Ok(())
+}
+
+fn explain_path(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let root = absolute(Path::new(&match calls.value_of("root") {
+        Some(root) => root.to_string(),
+        None => fetch_directory()?,
+    }));
+    let target = absolute(&root.join(calls.value_of("path").unwrap()));
+    let shown = |path: &Path| {
+        let relative = path.strip_prefix(&root).unwrap_or(path);
+        ternary!(relative.as_os_str().is_empty() => ".".to_string(); relative.display().to_string())
+    };
+    match Walker::new(&RealFs, walk_options(calls)).explain(&root, &target)? {
+        Verdict::Skipped(path, why) => {
+            println!("{}: skipped", shown(&target));
+            println!("  {}: {why}", shown(&path));
+        }
+        Verdict::Counted(file, rule) => {
+            match file {
+                Some(file) => println!(
+                    "{}: counted, {} lines ({})",
+                    shown(&target),
+                    history::thousands(file.lines as i128),
+                    file.language().unwrap_or("no language")
+                ),
+                None => println!("{}: counted", shown(&target)),
+            }
+            if let Some(rule) = rule {
+                println!("  re-included by {rule}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn verify_counts(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let verdicts = verify::verify(&RealFs, Path::new(calls.value_of("path").unwrap()))?;
+    let disagreements: Vec<&verify::Verdict> = verdicts
+        .iter()
+        .filter(|verdict| !verdict.agrees())
+        .collect();
+
+    for verdict in &disagreements {
+        println!("{}", verdict.path.display());
+        println!(
+            "  {:width$} {}",
+            "lines()",
+            verdict.lines_iterator,
+            width = WIDTH
+        );
+        println!(
+            "  {:width$} {}",
+            "newline scan",
+            verdict.newline_scan,
+            width = WIDTH
+        );
+        println!("  {:width$} {}", "chunked", verdict.chunked, width = WIDTH);
+    }
+    println!(
+        "[verified]   {} files, {} disagreements",
+        verdicts.len(),
+        disagreements.len()
+    );
+
+    if !disagreements.is_empty() {
+        exit(1);
+    }
+    Ok(())
+}
+
+fn bench_throughput(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let size = size(calls, "size").unwrap_or(64 * 1024 * 1024);
+    let iterations = number::<usize>(calls, "iterations").unwrap_or(5).max(1);
+    let floor = calls.value_of("assert-throughput").map(|value| {
+        parse_size(value.trim_end_matches("/s")).unwrap_or_else(|| {
+            eprintln!("lc: --assert-throughput expects a rate like 200MB/s, got '{value}'");
+            exit(2);
+        })
+    });
+
+    let files = bench::synthetic(size);
+    let root = std::env::temp_dir().join(format!("lc-bench-{}", process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let timed = bench::write(&root, &files)
+        .map_err(LcError::from)
+        .and_then(|()| {
+            let walker = Walker::new(&RealFs, Options::default());
+            // the first walk only warms the page cache
+            let tree = walker.walk(&root)?;
+            let mut best = Duration::MAX;
+            for _ in 0..iterations {
+                let start = Instant::now();
+                walker.walk(&root)?;
+                best = best.min(start.elapsed());
+            }
+            Ok((tree, best))
+        });
+    let _ = std::fs::remove_dir_all(&root);
+    let (tree, best) = timed?;
+
+    let rate = bench::throughput(tree.bytes, best);
+    println!(
+        "[bench]   synthetic, {} in {} files",
+        human_bytes(tree.bytes),
+        tree.files()
+    );
+    println!(
+        "[throughput]   {}/s (best of {iterations}, {best:?})",
+        human_bytes(rate as u64)
+    );
+    if let Some(floor) = floor.filter(|floor| rate < *floor as f64) {
+        eprintln!(
+            "lc: throughput below --assert-throughput {}/s",
+            human_bytes(floor)
+        );
+        exit(1);
+    }
+    Ok(())
+}
+
+// `lc cache estimate`: the cache's size from a metadata-only walk, its paths and a record
+// each, and the time it saves from how fast a sample of the files reads, as a hit skips
+// reading the file but not its metadata
+fn cache_estimate(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    const SAMPLE: usize = 32;
+    let root = PathBuf::from(match calls.value_of("path") {
+        Some(path) => path.to_string(),
+        None => fetch_directory()?,
+    });
+    let options = walk_options(calls);
+    let sized = walk::Options {
+        skip_larger_than: Some(0),
+        ..options.clone()
+    };
+    let tree = Walker::new(&RealFs, sized).walk(&root)?;
+    let mut files = Vec::new();
+    let mut pending = vec![&tree];
+    while let Some(entry) = pending.pop() {
+        match entry.kind {
+            Kind::Dir => pending.extend(&entry.children),
+            _ => files.push(entry),
+        }
+    }
+    let path_bytes = files
+        .iter()
+        .map(|file| file.path.as_os_str().len() as u64)
+        .sum();
+    println!(
+        "[cache]   ~{} for {} files",
+        human_bytes(cache::estimated_size(files.len(), path_bytes)),
+        files.len()
+    );
 
-    let mut ignored: Vec<String> = Vec::new();
+    // entropy, encodings, hashes and wrapping read every file with or without it
+    if options.entropy || options.encodings || options.hashes || options.wrap_width.is_some() {
+        if !files.is_empty() {
+            eprintln!("lc: nothing would be cached; --entropy, --encodings, --hash and --wrap-width read every file anyway");
+        }
+        return Ok(());
+    }
+    let walker = Walker::new(&RealFs, options);
+    let (mut sampled, mut read) = (0, Duration::ZERO);
+    let step = (files.len() / SAMPLE).max(1);
+    for file in files.iter().step_by(step).take(SAMPLE) {
+        let start = Instant::now();
+        sampled += walker.walk(&file.path)?.bytes;
+        read += start.elapsed();
+    }
+    let rate = ternary!(read.is_zero() => 0.0; sampled as f64 / read.as_secs_f64());
+    let saved =
+        ternary!(sampled == 0 => Duration::ZERO; read.mul_f64(tree.bytes as f64 / sampled as f64));
+    println!(
+        "[scan]   ~{saved:?} saved per run, reading {}/s over {} sampled files",
+        human_bytes(rate as u64),
+        files.len().min(SAMPLE)
+    );
+    Ok(())
+}
 
-    for line in contents.lines() {
-        let mut ignore_value = Some(line).unwrap().unwrap();
-        let prefix = ignore_value.chars().nth(0);
-        if prefix.unwrap() == '/' {
-            ignore_value.remove(0);
+fn verify_signature(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let path = Path::new(calls.value_of("path").unwrap());
+    let key = calls.value_of("key").unwrap();
+    let key = sign::read_key(Path::new(key)).unwrap_or_else(|err| {
+        eprintln!("lc: --key {key}: {err}");
+        exit(2);
+    });
+    let signature_path = sign::signature_path(path);
+    let signature = std::fs::read_to_string(&signature_path).unwrap_or_else(|err| {
+        eprintln!("lc: {}: {err}", signature_path.display());
+        exit(1);
+    });
+    match sign::verify(&key, &std::fs::read(path)?, &signature) {
+        true => println!("[signature]   {}: good", path.display()),
+        false => {
+            eprintln!("lc: {}: signature does not match", path.display());
+            exit(1);
         }
-        ignored.push(ignore_value);
     }
+    Ok(())
+}
 
-    ignored
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    ternary!(unit == 0 => format!("{bytes} B"); format!("{size:.1} {}", UNITS[unit]))
 }
 
-fn linecount_abridged<P>(directory_path: P) -> std::io::Result<u128>
-where
-    P: AsRef<Path>,
-{
-    let mut total_linecount: u128 = 0;
+// `10MB`, `1.5k`, `512`: a number and an optional unit, in the same powers of 1024 that
+// human_bytes prints
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let power = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 1,
+        "M" | "MB" | "MIB" => 2,
+        "G" | "GB" | "GIB" => 3,
+        "T" | "TB" | "TIB" => 4,
+        _ => return None,
+    };
+    Some((number * 1024f64.powi(power)).round() as u64)
+}
 
-    for entry in fs::read_dir(directory_path)? {
-        let entry = entry?.path();
-        let path = entry.as_path();
-        let metadata = fs::metadata(path)?.file_type();
+fn size(calls: &clap::ArgMatches, name: &str) -> Option<u64> {
+    let value = calls.value_of(name)?;
+    Some(parse_size(value).unwrap_or_else(|| {
+        eprintln!("lc: --{name} expects a size like 512, 64KB or 10MB, got '{value}'");
+        exit(2);
+    }))
+}
 
-        if metadata.is_file() && path.is_visible() {
-            let content = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
-            total_linecount = content.lines().count() as u128;
-        } else if metadata.is_dir() && path.is_visible() {
-            let _linecount_result = linecount_abridged(Path::new(&path));
-            let linecount = match _linecount_result {
-                Ok(success) => success,
-                Err(err) => panic!("shit!{err}"),
-            };
-            total_linecount += linecount;
+fn goals_json(progress: &[goal::Progress]) -> Json {
+    let goals = progress
+        .iter()
+        .map(|progress| {
+            Json::object(vec![
+                ("goal", Json::str(progress.goal.to_string())),
+                ("lines", Json::from(progress.lines)),
+                ("total", Json::from(progress.total)),
+                ("ratio", Json::from(progress.ratio())),
+                ("met", Json::Bool(progress.met())),
+            ])
+        })
+        .collect();
+    Json::Array(goals)
+}
+
+fn cache_json(stats: CacheStats) -> Json {
+    Json::object(vec![
+        ("hits", Json::from(stats.hits)),
+        ("misses", Json::from(stats.misses)),
+        ("bytes_saved", Json::from(stats.bytes_saved)),
+        ("bytes_read", Json::from(stats.bytes_read)),
+        (
+            "time_saved_ms",
+            Json::from(stats.time_saved().as_secs_f64() * 1000.0),
+        ),
+    ])
+}
+
+// flags that shape the walk itself, shared by every subcommand that scans
+fn walk_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("ignore")
+            .short('i')
+            .long("ignore")
+            .help("Skip what .gitignore, .lcignore and ~/.config/lc/ignore list, the default; kept for older scripts"),
+        Arg::new("no-ignore")
+            .long("no-ignore")
+            .conflicts_with("ignore")
+            .help("Count files the ignore files list too"),
+        Arg::new("exclude")
+            .long("exclude")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("PATTERN")
+            .help("Skip files and directories matching a glob like 'target/**' or '*.min.js'"),
+        Arg::new("include")
+            .long("include")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("PATTERN")
+            .help("Count only files matching a glob like 'src/**/*.rs'; --exclude still applies"),
+        Arg::new("ext")
+            .long("ext")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .use_value_delimiter(true)
+            .value_name("EXTENSIONS")
+            .help("Count only files with these extensions, e.g. rs,toml,md; empty directories are left out"),
+        Arg::new("no-ext")
+            .long("no-ext")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .use_value_delimiter(true)
+            .value_name("EXTENSIONS")
+            .help("Skip files with these extensions, e.g. png,lock,svg"),
+        Arg::new("type")
+            .long("type")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .use_value_delimiter(true)
+            .possible_values(lang::ContentType::ALL.map(lang::ContentType::name))
+            .value_name("TYPES")
+            .help("Count only files of these content types; empty directories are left out"),
+        Arg::new("match-content")
+            .long("match-content")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("REGEX")
+            .help("Count only files whose start matches a regular expression, like '^#!/usr/bin/env bash'"),
+        Arg::new("match-content-kb")
+            .long("match-content-kb")
+            .takes_value(true)
+            .value_name("N")
+            .requires("match-content")
+            .help("How much of each file --match-content looks at [default: 64]"),
+        Arg::new("since")
+            .long("since")
+            .takes_value(true)
+            .value_name("WHEN")
+            .help("Count only files modified after 2024-01-01, 2024-01-01T12:00 or a span ago like 7d; empty directories are left out"),
+        Arg::new("no-generated")
+            .long("no-generated")
+            .help("Leave lockfiles and generated code (*.pb.go, @generated, ...) out of the totals"),
+        Arg::new("tests-only")
+            .long("tests-only")
+            .conflicts_with("no-tests")
+            .help("Count only test code: tests/, test/, spec/ and *_test.*, *.spec.* files"),
+        Arg::new("no-tests")
+            .long("no-tests")
+            .help("Leave test code out of the totals, see --tests-only"),
+        Arg::new("no-vendored")
+            .long("no-vendored")
+            .help("Leave vendor, third_party and extern directories out of the totals"),
+        Arg::new("lfs")
+            .long("lfs")
+            .help("Count git LFS pointer files at the size of the object they stand for"),
+        Arg::new("skip-larger-than")
+            .long("skip-larger-than")
+            .takes_value(true)
+            .value_name("SIZE")
+            .help("Don't read files larger than this, like 100MB; their bytes still count, their lines don't"),
+        Arg::new("min-size")
+            .long("min-size")
+            .takes_value(true)
+            .value_name("SIZE")
+            .help("Count only files of at least this size, like 100KB; empty directories are left out"),
+        Arg::new("max-size")
+            .long("max-size")
+            .takes_value(true)
+            .value_name("SIZE")
+            .help("Skip files larger than this, like 10MB; empty directories are left out"),
+        Arg::new("linguist")
+            .long("linguist")
+            .help("Skip vendored and generated files and take languages from .gitattributes, like GitHub"),
+        Arg::new("regex")
+            .long("regex")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("REGEX")
+            .help("Count only files whose relative path matches a regular expression"),
+        Arg::new("regex-exclude")
+            .long("regex-exclude")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("REGEX")
+            .help("Skip paths matching a regular expression like '(^|/)__snapshots__/'; directories end in /"),
+        Arg::new("no-recurse")
+            .long("no-recurse")
+            .help("Count only the files directly inside the directory"),
+        Arg::new("entropy")
+            .long("entropy")
+            .help("[experimental] Estimate compressed bits per byte and flag outliers"),
+        Arg::new("pipe-timeout")
+            .long("pipe-timeout")
+            .takes_value(true)
+            .value_name("SECS")
+            .help("How long a FIFO or device given as a path may take to deliver [default: 5]"),
+        Arg::new("no-manifest-rule")
+            .long("no-manifest-rule")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .use_value_delimiter(true)
+            .value_name("RULE")
+            .possible_values(manifest::rule_names())
+            .help("Count a build directory lc would skip because of a sibling manifest"),
+        Arg::new("no-manifest-rules")
+            .long("no-manifest-rules")
+            .help("Disable every manifest-based exclusion"),
+        Arg::new("no-default-ignores")
+            .long("no-default-ignores")
+            .help("Count node_modules, target, .venv, vendor, dist and build directories too"),
+        Arg::new("include-dir-bytes")
+            .long("include-dir-bytes")
+            .help("Add directory entry sizes to byte totals, like `du -sb`"),
+        Arg::new("read-binary").long("read-binary").help(
+            "Read media, archives, fonts and objects too instead of sizing them from metadata",
+        ),
+        Arg::new("wrap-width")
+            .long("wrap-width")
+            .takes_value(true)
+            .value_name("COLUMNS")
+            .help("Also count visual lines, wrapping long lines at COLUMNS characters"),
+        Arg::new("encodings")
+            .long("encodings")
+            .help("Break totals down by detected text encoding, with character counts"),
+        Arg::new("extract-documents")
+            .long("extract-documents")
+            .help("Count the text lines inside PDF, DOCX and ODT files instead of none"),
+        Arg::new("include-junk")
+            .long("include-junk")
+            .help("Count editor temp and backup files (*~, *.swp, #*#, .DS_Store) too"),
+        Arg::new("hidden")
+            .long("hidden")
+            .help("Count dotfiles and dot-directories too; .git, .hg and .svn still need --include-vcs"),
+        Arg::new("include-vcs")
+            .long("include-vcs")
+            .help("Count what is inside .git, .hg and .svn too"),
+        Arg::new("hash")
+            .long("hash")
+            .takes_value(true)
+            .possible_values(["xxh3"])
+            .help("Add each file's content hash to json, yaml, ndjson, csv and tsv output"),
+    ]
+}
+
+fn scan_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("paths")
+            .multiple_values(true)
+            .value_name("PATH")
+            .conflicts_with("files-from")
+            .help("Directories to count, - for what's piped in [default: the current directory]"),
+        Arg::new("verbose").short('v').long("verbose"),
+        Arg::new("shallow")
+            .long("shallow")
+            .help("List top-level entries only, summing each subdirectory as one row"),
+        Arg::new("max-depth")
+            .long("max-depth")
+            .takes_value(true)
+            .value_name("N")
+            .help("List at most N levels below the root, summing deeper entries into one row per directory"),
+        Arg::new("mtime-col")
+            .long("mtime-col")
+            .help("Show the newest modification time below each directory in the tree"),
+        Arg::new("path-lengths")
+            .long("path-lengths")
+            .help("Report the deepest and longest paths and those over --path-limit"),
+        Arg::new("depth-profile")
+            .long("depth-profile")
+            .help("Show how lines spread over directory depths, to tell a flat tree from a deep one"),
+        Arg::new("path-limit")
+            .long("path-limit")
+            .takes_value(true)
+            .value_name("CHARS")
+            .help("Path length considered a violation [default: 260]"),
+        Arg::new("files-from")
+            .long("files-from")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Count the paths listed in FILE (- for stdin) instead of walking a directory"),
+        Arg::new("null")
+            .short('0')
+            .long("null")
+            .requires("files-from")
+            .help("Paths in --files-from are NUL-separated (detected automatically)"),
+        Arg::new("language-overrides")
+            .long("language-overrides")
+            .takes_value(true)
+            .value_name("FILE")
+            .help(
+                "Attribute files to languages by path, FILE mapping `<glob> <language>` per line",
+            ),
+        Arg::new("services-file")
+            .long("services-file")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Report totals per service, FILE mapping `<name> <path prefix>` per line"),
+        Arg::new("rules")
+            .long("rules")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Warn after the summary per `<expr>: <message>` line that holds [default: .lcrules]"),
+        Arg::new("root-at")
+            .long("root-at")
+            .takes_value(true)
+            .value_name("GLOB")
+            .help("Render every directory matching GLOB as its own tree with its own total"),
+        Arg::new("no-delta")
+            .long("no-delta")
+            .help("Neither show nor record the change since the previous run"),
+        Arg::new("assert")
+            .long("assert")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("EXPR")
+            .help("Exit non-zero unless EXPR holds, e.g. 'lines < 500000 && langs.rust.lines > 0'"),
+        Arg::new("goal")
+            .long("goal")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("GOAL")
+            .help("Track the share of test or doc lines, e.g. 'tests>=20%'"),
+        Arg::new("goal-strict")
+            .long("goal-strict")
+            .requires("goal")
+            .help("Exit non-zero when a --goal is not met"),
+        Arg::new("output")
+            .short('o')
+            .long("output")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Write the report to FILE instead of stdout; summaries stay on the terminal"),
+        Arg::new("trim-prefix")
+            .long("trim-prefix")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("PATH")
+            .help("Leave PATH off the front of paths in the output, e.g. /home/ci/builds/workspace/"),
+        Arg::new("alias")
+            .long("alias")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("FROM=TO")
+            .help("Show paths starting with FROM as starting with TO instead; the longest match wins"),
+        Arg::new("compress")
+            .long("compress")
+            .takes_value(true)
+            .possible_values(["zstd"])
+            .requires("output")
+            .help("Compress the --output file, adding .zst to its name"),
+        Arg::new("sign")
+            .long("sign")
+            .takes_value(true)
+            .value_name("KEY")
+            .requires("output")
+            .help("Write an HMAC-SHA256 of the --output file, keyed by the KEY file, to FILE.sig"),
+        Arg::new("max-time")
+            .long("max-time")
+            .takes_value(true)
+            .value_name("SECS")
+            .help("Give up on a scan still running after this long, exiting 124 like timeout(1)"),
+        Arg::new("exec-per-file")
+            .long("exec-per-file")
+            .takes_value(true)
+            .value_name("CMD")
+            .help("Run CMD for every counted file, {} being its path; LC_PATH, LC_LINES and LC_BYTES are set too"),
+        Arg::new("exec-min-lines")
+            .long("exec-min-lines")
+            .takes_value(true)
+            .value_name("N")
+            .requires("exec-per-file")
+            .help("Only run --exec-per-file for files with at least N lines"),
+        Arg::new("exec-min-bytes")
+            .long("exec-min-bytes")
+            .takes_value(true)
+            .value_name("N")
+            .requires("exec-per-file")
+            .help("Only run --exec-per-file for files of at least N bytes"),
+        Arg::new("exec-jobs")
+            .long("exec-jobs")
+            .takes_value(true)
+            .value_name("N")
+            .requires("exec-per-file")
+            .help("How many --exec-per-file commands run at once [default: one per CPU]"),
+        Arg::new("save-index")
+            .long("save-index")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Keep the whole scan in FILE for --load-index"),
+        Arg::new("load-index")
+            .long("load-index")
+            .takes_value(true)
+            .value_name("FILE")
+            .conflicts_with_all(&["paths", "files-from", "save-index"])
+            .help("Render a scan kept by --save-index instead of walking again"),
+        Arg::new("utc")
+            .long("utc")
+            .help("Give the scan's start and end in UTC rather than local time"),
+        Arg::new("format")
+            .long("format")
+            .takes_value(true)
+            .possible_values(["json", "yaml", "ndjson", "csv", "tsv", "cloc", "cloc-csv"])
+            .help("Print the report in a machine-readable format"),
+        Arg::new("porcelain")
+            .long("porcelain")
+            .takes_value(true)
+            .possible_values(["wc"])
+            .conflicts_with("format")
+            .help("Print `lines bytes path` per file and a total, laid out like `wc -l -c`"),
+        Arg::new("max-entries")
+            .long("max-entries")
+            .takes_value(true)
+            .value_name("N")
+            .help("Stop the tree listing after N rows; everything is still counted"),
+        Arg::new("no-tty")
+            .long("no-tty")
+            .help("Plain output for logs: no color, ASCII sparklines (the default off a terminal or under CI)"),
+        Arg::new("progressive")
+            .long("progressive")
+            .help("Print a quick estimate from file sizes first, then the exact totals"),
+        Arg::new("progress")
+            .long("progress")
+            .takes_value(true)
+            .possible_values(["json"])
+            .help("Print progress to stderr as it goes, one JSON object per line"),
+        Arg::new("report-budget")
+            .long("report-budget")
+            .takes_value(true)
+            .value_name("DURATION")
+            .help("Fold small files into per-directory rows to render reports within e.g. 5s"),
+        Arg::new("export")
+            .long("export")
+            .takes_value(true)
+            .value_name("KIND:PATH")
+            .help(
+                "Also write the scan to sqlite:PATH, or parquet:PATH in builds with that feature",
+            ),
+        Arg::new("append")
+            .long("append")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Append a timestamped totals record to a .csv or .jsonl file"),
+        Arg::new("cache")
+            .long("cache")
+            .help("Reuse line counts of files unchanged since the last --cache run"),
+        Arg::new("fsync")
+            .long("fsync")
+            .help("Flush reports and caches to disk before renaming them into place"),
+        Arg::new("lock")
+            .long("lock")
+            .help("Wait for other lc runs scanning the same tree to finish first"),
+    ]
+}
+
+fn walk_options(calls: &clap::ArgMatches) -> Options {
+    Options {
+        ignore: !calls.is_present("no-ignore"),
+        recurse: !calls.is_present("no-recurse"),
+        entropy: calls.is_present("entropy"),
+        disabled_rules: calls
+            .values_of("no-manifest-rule")
+            .map(|rules| rules.map(String::from).collect())
+            .unwrap_or_default(),
+        manifest_rules: !calls.is_present("no-manifest-rules"),
+        default_ignores: !calls.is_present("no-default-ignores"),
+        include_dir_bytes: calls.is_present("include-dir-bytes"),
+        encodings: calls.is_present("encodings"),
+        read_binary: calls.is_present("read-binary"),
+        include_junk: calls.is_present("include-junk"),
+        include_vcs: calls.is_present("include-vcs"),
+        hidden: calls.is_present("hidden"),
+        extract_documents: calls.is_present("extract-documents"),
+        global_ignore: gitignore::global_path(),
+        exclude: calls
+            .values_of("exclude")
+            .into_iter()
+            .flatten()
+            .map(glob::Glob::new)
+            .collect(),
+        include: calls
+            .values_of("include")
+            .into_iter()
+            .flatten()
+            .map(glob::Glob::new)
+            .collect(),
+        extensions: calls
+            .values_of("ext")
+            .into_iter()
+            .flatten()
+            .map(|extension| extension.trim_start_matches('.').to_string())
+            .collect(),
+        skip_extensions: calls
+            .values_of("no-ext")
+            .into_iter()
+            .flatten()
+            .map(|extension| extension.trim_start_matches('.').to_string())
+            .collect(),
+        types: calls
+            .values_of("type")
+            .into_iter()
+            .flatten()
+            .filter_map(lang::ContentType::from_name)
+            .collect(),
+        linguist: calls.is_present("linguist"),
+        regex: regexes(calls, "regex"),
+        regex_exclude: regexes(calls, "regex-exclude"),
+        match_content: regexes(calls, "match-content"),
+        min_size: size(calls, "min-size"),
+        modified_since: calls.value_of("since").map(|value| {
+            time::parse_time(value, SystemTime::now()).unwrap_or_else(|| {
+                eprintln!(
+                    "lc: --since expects a date like 2024-01-01 or a span like 7d, got '{value}'"
+                );
+                exit(2);
+            })
+        }),
+        max_size: size(calls, "max-size"),
+        skip_larger_than: size(calls, "skip-larger-than"),
+        no_generated: calls.is_present("no-generated"),
+        tests: match (calls.is_present("tests-only"), calls.is_present("no-tests")) {
+            (false, false) => None,
+            (tests, _) => Some(tests),
+        },
+        no_vendored: calls.is_present("no-vendored"),
+        lfs: calls.is_present("lfs"),
+        match_content_bytes: number::<u64>(calls, "match-content-kb")
+            .map_or(64 * 1024, |kb| kb.saturating_mul(1024)),
+        hashes: calls.is_present("hash"),
+        // only --format cloc reports it, and that isn't a walk arg
+        line_kinds: false,
+        wrap_width: calls
+            .value_of("wrap-width")
+            .map(|width| match width.parse() {
+                Ok(width) if width > 0 => width,
+                _ => {
+                    eprintln!(
+                        "lc: --wrap-width expects a positive number of columns, got '{width}'"
+                    );
+                    exit(2);
+                }
+            }),
+        special_timeout: seconds(calls, "pipe-timeout")
+            .unwrap_or(Options::default().special_timeout),
+    }
+}
+
+// what --compress and --sign do to a report file on its way to disk
+#[derive(Default)]
+struct Seal {
+    compress: bool,
+    key: Option<Vec<u8>>,
+}
+
+impl Seal {
+    // compressed output gets `.zst` on its name, as zstd itself would, unless it has it already
+    fn path(&self, path: &Path) -> PathBuf {
+        if !self.compress || path.extension() == Some("zst".as_ref()) {
+            return path.to_path_buf();
+        }
+        let mut name = path.as_os_str().to_owned();
+        name.push(".zst");
+        PathBuf::from(name)
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        let contents = match self.compress {
+            true => zstd::compress(&contents),
+            false => contents,
         };
+        let path = self.path(path);
+        atomic::write(&path, &contents)?;
+        match &self.key {
+            Some(key) => atomic::write(
+                &sign::signature_path(&path),
+                sign::signature(key, &contents).as_bytes(),
+            ),
+            None => Ok(()),
+        }
     }
-    Ok(total_linecount)
 }
 
-fn linecount_abridged_ignore(directory_path: &Path) -> std::io::Result<u128> {
-    let mut total_linecount: u128 = 0;
-    let dir = directory_path.as_os_str().to_str().unwrap();
-    let gitignore = detect_gitignore(dir);
+// read up front, so a missing key fails before the scan rather than after it
+fn seal(calls: &clap::ArgMatches) -> Seal {
+    let key = calls.value_of("sign").map(|key| {
+        sign::read_key(Path::new(key)).unwrap_or_else(|err| {
+            eprintln!("lc: --sign {key}: {err}");
+            exit(2);
+        })
+    });
+    Seal {
+        compress: calls.value_of("compress") == Some("zstd"),
+        key,
+    }
+}
 
-    for entry in fs::read_dir(directory_path)? {
-        let entry = entry?.path();
-        let path = entry.as_path();
-        let metadata = fs::metadata(path)?.file_type();
+// where the report itself goes: stdout as it is produced, or kept and swapped into the
+// --output file in one piece once the scan is done
+struct Output {
+    file: Option<PathBuf>,
+    buffer: Vec<u8>,
+    seal: Seal,
+}
 
-        if path.ignore(gitignore.clone()) {
-            continue;
-        } else if metadata.is_file() && path.is_visible() {
-            let content = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
-            for _ in content.lines() {
-                total_linecount += 1;
+impl Output {
+    fn new(file: Option<&str>) -> Output {
+        if let Some(file) = file {
+            reserve_output(Path::new(file));
+        }
+        Output {
+            file: file.map(PathBuf::from),
+            buffer: Vec::new(),
+            seal: Seal::default(),
+        }
+    }
+
+    fn sealed(mut self, seal: Seal) -> Output {
+        if let Some(file) = &self.file {
+            let file = seal.path(file);
+            if seal.key.is_some() {
+                reserve_output(&sign::signature_path(&file));
+            }
+            reserve_output(&file);
+        }
+        self.seal = seal;
+        self
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match &self.file {
+            Some(file) => self.seal.write(file, std::mem::take(&mut self.buffer)),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        match self.file {
+            Some(_) => self.buffer.write(bytes),
+            None => io::stdout().write(bytes),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file {
+            Some(_) => Ok(()),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+fn scan(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    atomic::FSYNC.store(
+        calls.is_present("fsync"),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    NO_TTY.store(calls.is_present("no-tty"), Ordering::Relaxed);
+    let root = fetch_directory()?;
+    let paths: Vec<PathBuf> = match calls.values_of("paths") {
+        Some(paths) => paths.map(PathBuf::from).collect(),
+        None => vec![PathBuf::from(&root)],
+    };
+    let loaded = match calls.value_of("load-index") {
+        Some(file) => Some(index::read(Path::new(file))?),
+        None => None,
+    };
+    // several roots are combined under an unnamed parent, which an index keeps
+    let multi_root = match &loaded {
+        Some(tree) => tree.path.as_os_str().is_empty(),
+        None => paths.len() > 1,
+    };
+    // always taken in the same order so two runs over overlapping roots cannot deadlock
+    let mut _locks = Vec::new();
+    if calls.is_present("lock") {
+        let mut lock_order = paths.clone();
+        lock_order.sort();
+        lock_order.dedup();
+        for path in lock_order.iter().filter(|path| *path != Path::new(STDIN)) {
+            _locks.push(ScanLock::acquire(path)?);
+        }
+    }
+    let path_limit = match calls.value_of("path-limit") {
+        Some(limit) => limit.parse::<usize>().unwrap_or_else(|_| {
+            eprintln!("lc: --path-limit expects a number, got '{limit}'");
+            exit(2);
+        }),
+        None => DEFAULT_PATH_LIMIT,
+    };
+    let hook = calls.value_of("exec-per-file").map(|command| exec::Hook {
+        command: command.to_string(),
+        min_lines: number(calls, "exec-min-lines").unwrap_or(0),
+        min_bytes: number(calls, "exec-min-bytes").unwrap_or(0),
+        jobs: number(calls, "exec-jobs")
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from)),
+    });
+    let options = Options {
+        line_kinds: matches!(calls.value_of("format"), Some("cloc" | "cloc-csv")),
+        ..walk_options(calls)
+    };
+    let overrides = match calls.value_of("language-overrides") {
+        Some(file) => Some(lang::parse_overrides(&std::fs::read_to_string(file)?)?),
+        None => None,
+    };
+    let services = match calls.value_of("services-file") {
+        Some(file) => Some(services::parse_services(&std::fs::read_to_string(file)?)?),
+        None => None,
+    };
+    let rules = match calls.value_of("rules") {
+        Some(file) => rules::parse_rules(&std::fs::read_to_string(file)?)?,
+        None => match std::fs::read_to_string(paths[0].join(rules::FILE)) {
+            Ok(contents) => rules::parse_rules(&contents)?,
+            Err(_) => Vec::new(),
+        },
+    };
+
+    let format = calls.value_of("format");
+    let tree_display = format.is_none()
+        && (calls.is_present("verbose")
+            || calls.is_present("shallow")
+            || calls.is_present("root-at"));
+    let cache = calls
+        .is_present("cache")
+        .then(|| Cache::load(&cache_roots(calls, &paths, &root)));
+
+    if let Some(series) = calls.value_of("append") {
+        reserve_output(Path::new(series));
+    }
+    if let Some(file) = calls.value_of("save-index") {
+        reserve_output(Path::new(file));
+    }
+    let export = calls
+        .value_of("export")
+        .map(|export| match export.split_once(':') {
+            Some((kind @ ("sqlite" | "parquet"), path)) if !path.is_empty() => {
+                if kind == "parquet" && !cfg!(feature = "parquet") {
+                    eprintln!("lc: this lc was built without parquet support (--features parquet)");
+                    exit(2);
+                }
+                reserve_output(Path::new(path));
+                (kind, Path::new(path))
+            }
+            _ => {
+                eprintln!("lc: --export expects sqlite:<path> or parquet:<path>, got '{export}'");
+                exit(2);
+            }
+        });
+
+    let output = std::cell::RefCell::new(Output::new(calls.value_of("output")).sealed(seal(calls)));
+
+    if tree_display {
+        writeln!(output.borrow_mut(), "[tree]")?;
+    }
+    let start_execution = Instant::now();
+    let started = SystemTime::now();
+    let stream_root = std::cell::RefCell::new(PathBuf::new());
+    let rewrites = rewrites(calls);
+    let stream = |entry: &Entry| {
+        let record = match rewrites.is_empty() {
+            true => report::ndjson_file(&stream_root.borrow(), entry, multi_root),
+            false => {
+                let root = report::rewrite(&stream_root.borrow(), &rewrites);
+                let entry = Entry {
+                    path: report::rewrite(&entry.path, &rewrites),
+                    ..entry.childless()
+                };
+                report::ndjson_file(&root, &entry, multi_root)
             }
-        } else if metadata.is_dir() && path.is_visible() {
-            let _linecount_result = linecount_abridged_ignore(Path::new(&path));
-            let linecount = match _linecount_result {
-                Ok(success) => success,
-                Err(err) => panic!("shit!{err}"),
-            };
-            total_linecount += linecount;
         };
+        // the callback can't fail the walk; stdout errors end the run at the totals anyway
+        let _ = writeln!(output.borrow_mut(), "{record}");
+    };
+    // an index, a file list or a pipe has nothing quicker to estimate from
+    if calls.is_present("progressive")
+        && loaded.is_none()
+        && !calls.is_present("files-from")
+        && !paths.iter().any(|path| path == Path::new(STDIN))
+    {
+        print_estimate(&options, &paths)?;
+    }
+    let progress = calls.is_present("progress").then(|| {
+        let expected = match calls.value_of("files-from") {
+            Some(_) => None,
+            None => previous_run(&paths).map(|run| run.bytes),
+        };
+        progress::Progress::new(expected, progress::INTERVAL)
+    });
+    let on_file = |entry: &Entry| {
+        if format == Some("ndjson") {
+            stream(entry);
+        }
+        if let Some(record) = progress.as_ref().and_then(|progress| progress.file(entry)) {
+            eprintln!("{record}");
+        }
+    };
+    let mut walker = Walker::new(&RealFs, options);
+    if let Some(cache) = &cache {
+        walker = walker.cache(cache);
+    }
+    if format == Some("ndjson") || progress.is_some() {
+        walker = walker.on_file(&on_file);
+    }
+    if let Some(limit) = seconds(calls, "max-time") {
+        walker = walker.cancel(&CANCEL);
+        std::thread::spawn(move || {
+            std::thread::sleep(limit);
+            CANCEL.store(true, Ordering::Relaxed);
+        });
+    }
+    let mut overlaps = Vec::new();
+    let mut tree = match calls.value_of("files-from") {
+        _ if loaded.is_some() => loaded.unwrap(),
+        Some(list) => {
+            let paths =
+                input::parse_file_list(&input::read_file_list(list)?, calls.is_present("null"));
+            *stream_root.borrow_mut() = PathBuf::from(list);
+            walker.walk_files(Path::new(list), paths)?
+        }
+        None => {
+            let mut roots = Vec::with_capacity(paths.len());
+            for path in &paths {
+                *stream_root.borrow_mut() = path.clone();
+                roots.push(match path == Path::new(STDIN) {
+                    true => walker.walk_reader(path, &mut io::stdin().lock())?,
+                    false => walker.walk(path)?,
+                });
+            }
+            let canonical = |path: &Path| path.canonicalize().unwrap_or(path.to_path_buf());
+            overlaps = walk::remove_overlap(&mut roots, &canonical);
+            ternary!(multi_root => Entry::combine(roots); roots.pop().unwrap())
+        }
+    };
+    if let Some(progress) = &progress {
+        eprintln!("{}", progress.finish());
+    }
+    if let Some(file) = calls.value_of("save-index") {
+        index::write(Path::new(file), &tree)?;
+    }
+    if let Some(overrides) = &overrides {
+        lang::apply_overrides(&mut tree, overrides);
+    }
+    let end_execution = Instant::now();
+    let finished = SystemTime::now();
+    let utc = calls.is_present("utc");
+    let iso8601 = ternary!(utc => time::iso8601_utc; time::iso8601_local);
+    let cache_stats = cache.as_ref().map(Cache::stats);
+    if let Some(cache) = &cache {
+        cache.save()?;
+    }
+
+    // a file list has no stable root to compare runs against, and a loaded index is no run
+    let previous_run = match calls.is_present("no-delta")
+        || calls.is_present("files-from")
+        || calls.is_present("load-index")
+        || multi_root
+    {
+        true => None,
+        false => record_run(&tree),
+    };
+    // commands get the real paths; everything from here on is output
+    let executed = hook.as_ref().map(|hook| exec::run(hook, &tree));
+    report::rewrite_paths(&mut tree, &rewrites);
+
+    let timestamp = time::iso8601_utc(std::time::SystemTime::now());
+    if let Some(series) = calls.value_of("append") {
+        report::append_totals(Path::new(series), &tree, &timestamp)?;
+    }
+    match export {
+        Some(("sqlite", database)) => export::sqlite(database, &tree, &timestamp)?,
+        #[cfg(feature = "parquet")]
+        Some(("parquet", file)) => parquet::write(file, &tree)?,
+        _ => {}
+    }
+
+    let assertions: Vec<&str> = calls.values_of("assert").into_iter().flatten().collect();
+    let mut failed = check_assertions(&tree, &assertions);
+    if executed.is_some_and(|(_, failures)| failures > 0) {
+        failed = true;
+    }
+    let progress: Vec<goal::Progress> = calls
+        .values_of("goal")
+        .into_iter()
+        .flatten()
+        .map(|source| match goal::parse(source) {
+            Ok(goal) => goal::progress(&tree, &goal),
+            Err(err) => {
+                eprintln!("lc: invalid --goal '{source}': {err}");
+                exit(2);
+            }
+        })
+        .collect();
+    if calls.is_present("goal-strict") && progress.iter().any(|progress| !progress.met()) {
+        failed = true;
+    }
+
+    let mut output = output.borrow_mut();
+    if format == Some("ndjson") {
+        writeln!(output, "{}", report::ndjson_totals(&tree))?;
+        output.finish()?;
+        ternary!(failed => exit(1); return Ok(()));
+    }
+
+    // totals, summaries and statistics below still see the whole tree
+    let listed: Cow<Entry> = match number(calls, "max-depth") {
+        Some(depth) if multi_root => Cow::Owned(Entry {
+            children: (tree.children.iter())
+                .map(|root| report::truncate(root, depth))
+                .collect(),
+            ..tree.childless()
+        }),
+        Some(depth) => Cow::Owned(report::truncate(&tree, depth)),
+        None => Cow::Borrowed(&tree),
+    };
+    if matches!(format, Some("json") | Some("yaml")) {
+        let cache = cache_stats.map(cache_json).unwrap_or(Json::Null);
+        let mut sections = vec![
+            ("started", Json::str(iso8601(started))),
+            ("finished", Json::str(iso8601(finished))),
+            ("cache", cache),
+        ];
+        if let Some(services) = &services {
+            sections.push(("services", services_json(&tree, services)));
+        }
+        if calls.is_present("encodings") {
+            sections.push(("encodings", encodings_json(&tree)));
+        }
+        if calls.is_present("include-dir-bytes") {
+            sections.push(("dir_bytes", Json::from(tree.dir_bytes)));
+        }
+        if calls.is_present("wrap-width") {
+            sections.push(("visual_lines", Json::from(tree.visual)));
+        }
+        if !progress.is_empty() {
+            sections.push(("goals", goals_json(&progress)));
+        }
+        let rendered = render_within(&listed, report_budget(calls), |tree| {
+            let report = report::json(tree, sections.clone());
+            match format {
+                Some("yaml") => yaml::render(&report),
+                _ => format!("{report}\n"),
+            }
+        });
+        output.write_all(rendered.as_bytes())?;
+        output.finish()?;
+        ternary!(failed => exit(1); return Ok(()));
+    }
+
+    if let Some(csv) = match format {
+        Some("cloc") => Some(false),
+        Some("cloc-csv") => Some(true),
+        _ => None,
+    } {
+        let elapsed = end_execution - start_execution;
+        output.write_all(report::cloc(&tree, elapsed, csv).as_bytes())?;
+        output.finish()?;
+        ternary!(failed => exit(1); return Ok(()));
+    }
+
+    if calls.value_of("porcelain") == Some("wc") {
+        let base = std::env::current_dir().unwrap_or_default();
+        output.write_all(report::wc(&tree, &base).as_bytes())?;
+        output.finish()?;
+        ternary!(failed => exit(1); return Ok(()));
+    }
+    if let Some(separator) = match format {
+        Some("csv") => Some(','),
+        Some("tsv") => Some('\t'),
+        _ => None,
+    } {
+        let budget = report_budget(calls);
+        let rendered = render_within(&listed, budget, |tree| report::table(tree, separator));
+        output.write_all(rendered.as_bytes())?;
+        output.finish()?;
+        ternary!(failed => exit(1); return Ok(()));
+    }
+
+    let mtime = calls.is_present("mtime-col");
+    let roots = ternary!(multi_root => listed.children.iter().collect(); vec![&*listed]);
+    // one buffer for the whole listing, flushed to the output a chunk at a time
+    let mut out = RowLimit {
+        inner: io::BufWriter::with_capacity(RENDER_BUFFER, &mut *output),
+        remaining: number(calls, "max-entries").unwrap_or(usize::MAX),
+        omitted: 0,
+    };
+    for root in &roots {
+        if let Some(pattern) = calls.value_of("root-at") {
+            print_root_at(&mut out, root, &glob::Glob::new(pattern), mtime)?;
+        } else if calls.is_present("shallow") {
+            print_shallow(&mut out, root, mtime)?;
+        } else if calls.is_present("verbose") {
+            print_tree(&mut out, root, 0, mtime)?;
+        }
+    }
+    if out.omitted > 0 {
+        let omitted = out.omitted;
+        writeln!(
+            out.inner,
+            "... {omitted} more rows not shown (--max-entries)"
+        )?;
+    }
+    out.flush()?;
+    drop(out);
+    // the listing and bare totals are the report; everything after is summary
+    if tree_display {
+        println!();
+        if multi_root {
+            for root in &roots {
+                println!(
+                    "[subtotal]   {:width$} {}",
+                    root.path.display(),
+                    root.lines,
+                    width = WIDTH
+                );
+            }
+        }
+        println!("[sum]   {}", tree.lines);
+        let execution = format!("{:?}", end_execution - start_execution);
+        match plain() {
+            true => println!("[execution]   {}", execution.replace('µ', "u")),
+            false => println!("[execution]   {execution}"),
+        }
+        println!("[started]   {}", time::readable(started, utc));
+        println!("[finished]   {}", time::readable(finished, utc));
+        if let Some((previous, current)) = previous_run {
+            println!("[delta]   {}", history::delta(previous, current));
+        }
+    } else if multi_root {
+        for root in &roots {
+            writeln!(output, "{} {}", root.lines, root.path.display())?;
+        }
+        writeln!(output, "{} total", tree.lines)?;
+    } else {
+        writeln!(output, "{}", tree.lines)?;
+    }
+    output.finish()?;
+    if calls.is_present("include-dir-bytes") {
+        let line = format!(
+            "[bytes]   {} ({} files + {} directory entries)",
+            tree.bytes,
+            tree.bytes - tree.dir_bytes,
+            tree.dir_bytes
+        );
+        summary(tree_display, &line);
+    }
+    if tree.unscanned > 0 {
+        fn unreadable(entry: &Entry) -> usize {
+            match entry.unreadable() {
+                true => 1,
+                false => entry.children.iter().map(unreadable).sum(),
+            }
+        }
+        let line = format!(
+            "[unscanned]   ~{} in {} unreadable directories, not counted",
+            human_bytes(tree.unscanned),
+            unreadable(&tree)
+        );
+        summary(tree_display, &line);
+    }
+    for (path, removed) in &overlaps {
+        let line = format!(
+            "[overlap]   {}: {} files, {} lines already counted under another root",
+            path.display(),
+            removed.files(),
+            removed.lines
+        );
+        summary(tree_display, &line);
+    }
+    if walker.linguist_excluded() > 0 {
+        let line = format!(
+            "[linguist]   {} vendored or generated files skipped (.gitattributes)",
+            walker.linguist_excluded()
+        );
+        summary(tree_display, &line);
+    }
+    if walker.junk() > 0 {
+        let line = format!(
+            "[junk]   {} editor temp and backup files skipped (--include-junk counts them)",
+            walker.junk()
+        );
+        summary(tree_display, &line);
+    }
+    if walker.vcs_skipped() > 0 {
+        let line = format!(
+            "[vcs]   {} .git, .hg and .svn directories skipped (--include-vcs counts them)",
+            walker.vcs_skipped()
+        );
+        summary(tree_display, &line);
+    }
+    let (generated, generated_lines) = walker.generated();
+    if generated > 0 {
+        let line = format!(
+            "[generated]   {generated} lockfiles and generated files, {generated_lines} lines, not counted"
+        );
+        summary(tree_display, &line);
+    }
+    let (minified, minified_bytes) = stats::minified(&tree);
+    if minified > 0 {
+        let line = format!(
+            "[minified]   {minified} minified JS/CSS files, {}, classed as minified rather than code",
+            human_bytes(minified_bytes)
+        );
+        summary(tree_display, &line);
+    }
+    let tests = stats::tests(&tree);
+    if tests.files > 0 {
+        let line = format!(
+            "[tests]   {} lines in {} files of tests, {} lines of source ({:.1}% tests)",
+            tests.lines,
+            tests.files,
+            tree.lines - tests.lines,
+            tests.lines as f64 * 100.0 / tree.lines.max(1) as f64
+        );
+        summary(tree_display, &line);
+    }
+    let vendored = stats::vendored(&tree);
+    if vendored.files > 0 {
+        let line = format!(
+            "[vendored]   {} lines in {} files third-party, {} lines first-party",
+            vendored.lines,
+            vendored.files,
+            tree.lines - vendored.lines
+        );
+        summary(tree_display, &line);
+    }
+    let (lfs, lfs_bytes) = walker.lfs();
+    if lfs > 0 {
+        let line = format!(
+            "[lfs]   {lfs} files stored in git LFS, {} in all (counted at object size, no lines)",
+            human_bytes(lfs_bytes)
+        );
+        summary(tree_display, &line);
+    }
+    let (large, large_bytes) = walker.large_skipped();
+    if large > 0 {
+        let line = format!(
+            "[large]   {large} files over --skip-larger-than not read, {} in all (no lines counted)",
+            human_bytes(large_bytes)
+        );
+        summary(tree_display, &line);
+    }
+    let (documents, pages) = walker.documents();
+    if documents > 0 {
+        let counted = ternary!(calls.is_present("extract-documents") => "text lines counted"; "no lines counted, see --extract-documents");
+        let line = format!("[documents]   {documents} pdf/docx/odt, {pages} pages ({counted})");
+        summary(tree_display, &line);
+    }
+    if let Some((ran, failures)) = executed {
+        let line = format!("[exec]   {ran} files, {failures} failed");
+        summary(tree_display, &line);
+    }
+    if let Some(stats) = cache_stats {
+        let line = format!(
+            "[cache]   {} hits, {} misses, {} saved (~{:?})",
+            stats.hits,
+            stats.misses,
+            human_bytes(stats.bytes_saved),
+            stats.time_saved()
+        );
+        summary(tree_display, &line);
+    }
+    if let Some(width) = calls.value_of("wrap-width") {
+        let line = format!("[visual]   {} lines at {width} columns", tree.visual);
+        summary(tree_display, &line);
+    }
+    for progress in &progress {
+        let line = format!(
+            "[goal]   {:width$} {:.1}% [{}] {}",
+            progress.goal.to_string(),
+            progress.ratio() * 100.0,
+            progress.bar(20),
+            ternary!(progress.met() => "met"; "not met"),
+            width = WIDTH
+        );
+        summary(tree_display, &line);
+    }
+    if calls.is_present("path-lengths") {
+        print_path_lengths(&tree, path_limit);
+    }
+    if calls.is_present("depth-profile") {
+        print_depth_profile(&tree);
+    }
+    if calls.is_present("entropy") {
+        print_entropy(&tree);
+    }
+    if let Some(services) = &services {
+        print_services(&tree, services);
+    }
+    if calls.is_present("encodings") {
+        print_encodings(&tree);
+    }
+    match rules::triggered(&rules, &report_fields(&tree)) {
+        Ok(warnings) => {
+            for warning in warnings {
+                summary(tree_display, &format!("[warning]   {warning}"));
+            }
+        }
+        Err(err) => {
+            eprintln!("lc: invalid rule: {err}");
+            exit(2);
+        }
+    }
+
+    if failed {
+        exit(1);
+    }
+    Ok(())
+}
+
+fn seconds(calls: &clap::ArgMatches, name: &str) -> Option<Duration> {
+    let secs = calls.value_of(name)?;
+    let duration = secs
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+    Some(duration.unwrap_or_else(|| {
+        eprintln!("lc: --{name} expects a number of seconds, got '{secs}'");
+        exit(2);
+    }))
+}
+
+fn rewrites(calls: &clap::ArgMatches) -> Vec<report::Rewrite> {
+    let trims =
+        (calls.values_of("trim-prefix").into_iter().flatten()).map(|from| report::Rewrite {
+            from: PathBuf::from(from),
+            to: PathBuf::new(),
+        });
+    let aliases =
+        (calls.values_of("alias").into_iter().flatten()).map(|alias| match alias.split_once('=') {
+            Some((from, to)) if !from.is_empty() => report::Rewrite {
+                from: PathBuf::from(from),
+                to: PathBuf::from(to),
+            },
+            _ => {
+                eprintln!("lc: --alias expects FROM=TO, got '{alias}'");
+                exit(2);
+            }
+        });
+    trims.chain(aliases).collect()
+}
+
+fn regexes(calls: &clap::ArgMatches, name: &str) -> Vec<regex::Regex> {
+    let patterns = calls.values_of(name).into_iter().flatten();
+    patterns
+        .map(|pattern| {
+            regex::Regex::new(pattern).unwrap_or_else(|err| {
+                eprintln!("lc: invalid --{name} '{pattern}': {err}");
+                exit(2);
+            })
+        })
+        .collect()
+}
+
+fn number<T: std::str::FromStr>(calls: &clap::ArgMatches, name: &str) -> Option<T> {
+    let value = calls.value_of(name)?;
+    Some(value.parse().unwrap_or_else(|_| {
+        eprintln!("lc: --{name} expects a number, got '{value}'");
+        exit(2);
+    }))
+}
+
+fn report_budget(calls: &clap::ArgMatches) -> Option<Duration> {
+    let budget = calls.value_of("report-budget")?;
+    Some(time::parse_duration(budget).unwrap_or_else(|| {
+        eprintln!("lc: --report-budget expects a duration like 5s or 500ms, got '{budget}'");
+        exit(2);
+    }))
+}
+
+// files rendered to calibrate how long a row takes
+const CALIBRATION_FILES: usize = 1000;
+
+// renders a sample first to measure the cost per file, then folds as many small files
+// as it takes for the whole report to fit in what is left of the budget
+fn render_within(
+    tree: &Entry,
+    budget: Option<Duration>,
+    render: impl Fn(&Entry) -> String,
+) -> String {
+    let budget = match budget {
+        Some(budget) if tree.files() > CALIBRATION_FILES => budget,
+        _ => return render(tree),
+    };
+    let (sample, _) = report::collapse(tree, CALIBRATION_FILES);
+    let started = Instant::now();
+    render(&sample);
+    let elapsed = started.elapsed();
+
+    let per_file = elapsed.as_secs_f64() / sample.files() as f64;
+    let remaining = budget.saturating_sub(elapsed).as_secs_f64();
+    let keep = match per_file > 0.0 {
+        true => (remaining / per_file) as usize,
+        false => usize::MAX,
+    };
+    let (reduced, folded) = report::collapse(tree, keep.max(CALIBRATION_FILES));
+    if folded > 0 {
+        eprintln!("lc: report budget: folded {folded} smaller files into per-directory rows");
+    }
+    render(&reduced)
+}
+
+fn signed(value: i128) -> String {
+    ternary!(value > 0 => format!("+{}", history::thousands(value)); history::thousands(value))
+}
+
+fn diff_trees(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let walker = Walker::new(&RealFs, walk_options(calls));
+    let before = walker.walk(Path::new(calls.value_of("before").unwrap()))?;
+    let after = walker.walk(Path::new(calls.value_of("after").unwrap()))?;
+
+    let changes = diff::diff(&before, &after);
+    let count = |lines: Option<u128>| lines.map_or("-".to_string(), |lines| lines.to_string());
+    for change in &changes {
+        println!(
+            "  {:width$} {} -> {} ({})",
+            change.path.display(),
+            count(change.before),
+            count(change.after),
+            signed(change.delta()),
+            width = WIDTH
+        );
     }
-    Ok(total_linecount)
-}
-
-//these functions should also return the bytes or something
-fn linecount_verbose<P>(
-    directory_path: P,
-    mut indent_amount: Option<usize>,
-) -> std::io::Result<u128>
-where
-    P: AsRef<Path>,
-{
-    let dir_path = directory_path
-        .as_ref()
-        .as_os_str()
-        .to_str()
-        .unwrap_or("???");
-
-    ternary!(indent_amount.is_none() => indent_amount = Some(0); indent_amount = indent_amount);
-    let (dir_indent, file_indent) = (
-        " ".repeat(indent_amount.unwrap_or_default()),
-        " ".repeat(indent_amount.unwrap_or_default() + 2),
+    println!(
+        "[delta]   {} lines across {} files",
+        signed(after.lines as i128 - before.lines as i128),
+        changes.len()
     );
-    println!("{dir_indent}{dir_path}/");
+    Ok(())
+}
 
-    let entries = fs::read_dir(directory_path)
-        .expect("Failed to read directory")
-        .map(|entry| entry.unwrap().path())
-        .collect::<Vec<_>>();
-    let (mut files, mut dirs) = (Vec::new(), Vec::new());
+fn tree_diff(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let options = Options {
+        hashes: true,
+        ..walk_options(calls)
+    };
+    let walker = Walker::new(&RealFs, options);
+    let before = walker.walk(Path::new(calls.value_of("before").unwrap()))?;
+    let after = walker.walk(Path::new(calls.value_of("after").unwrap()))?;
 
-    for entry in entries {
-        if entry.is_file() {
-            files.push(entry);
-        } else {
-            dirs.push(entry);
-        }
-    }
-    files.sort();
-    dirs.sort();
-    let sorted_entries = files.iter().chain(dirs.iter());
-
-    let mut total_linecount: u128 = 0;
-    for entry in sorted_entries {
-        let path = entry.as_path();
-        let filetype = fs::metadata(path)?.file_type();
-        let filename = entry.file_name().unwrap().to_str().unwrap_or("?");
-
-        let mut file_linecount: u128 = 0;
-
-        if filetype.is_file() && path.is_visible() {
-            let content = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
-            total_linecount += content.lines().count() as u128;
-            file_linecount += content.lines().count() as u128;
-            println!(
-                "{file_indent}{}",
-                format!("{:width$} {}", filename, file_linecount, width = WIDTH)
-            );
-        } else if filetype.is_dir() && path.is_visible() {
-            total_linecount +=
-                linecount_verbose(Path::new(&path), Some(indent_amount.unwrap() + 2))
-                    .expect("shit!");
+    let changes = diff::tree_diff(&before, &after);
+    let mut counts = [0usize; 4];
+    for change in &changes {
+        let (index, kind, detail) = match change {
+            diff::TreeChange::Moved { from, .. } => {
+                (0, "moved", format!("from {}", from.display()))
+            }
+            diff::TreeChange::Modified { before, after, .. } => (
+                1,
+                "modified",
+                format!("{before} -> {after} ({})", signed(change.delta())),
+            ),
+            diff::TreeChange::Added { .. } => (2, "added", signed(change.delta())),
+            diff::TreeChange::Removed { .. } => (3, "removed", signed(change.delta())),
         };
+        counts[index] += 1;
+        println!(
+            "  {kind:9} {:width$} {detail}",
+            change.path().display(),
+            width = WIDTH
+        );
     }
-    Ok(total_linecount)
-}
-
-fn linecount_verbose_ignore<P>(
-    directory_path: P,
-    mut indent_amount: Option<usize>,
-) -> std::io::Result<u128>
-where
-    P: AsRef<Path>,
-{
-    let dir_path = directory_path
-        .as_ref()
-        .as_os_str()
-        .to_str()
-        .unwrap_or("???");
-
-    ternary!(indent_amount.is_none() => indent_amount = Some(0); indent_amount = indent_amount);
-    let (dir_indent, file_indent) = (
-        " ".repeat(indent_amount.unwrap_or_default()),
-        " ".repeat(indent_amount.unwrap_or_default() + 2),
+    println!(
+        "[tree-diff]   {} moved, {} modified, {} added, {} removed, {} lines",
+        counts[0],
+        counts[1],
+        counts[2],
+        counts[3],
+        signed(after.lines as i128 - before.lines as i128)
     );
-    println!("{dir_indent}{dir_path}/");
-    let gitignore = detect_gitignore(dir_path);
-
-    let entries = fs::read_dir(directory_path)
-        .expect("Failed to read directory")
-        .map(|entry| entry.unwrap().path())
-        .collect::<Vec<_>>();
-    let (mut files, mut dirs) = (Vec::new(), Vec::new());
-
-    for entry in entries {
-        if entry.is_file() {
-            files.push(entry);
-        } else {
-            dirs.push(entry);
+    Ok(())
+}
+
+fn report_runs(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    if calls.is_present("markdown") || calls.is_present("html") {
+        let path = match calls.value_of("path") {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(fetch_directory()?),
+        };
+        let seal = seal(calls);
+        if let Some(page) = calls.value_of("html") {
+            let page = seal.path(Path::new(page));
+            reserve_output(&page);
+            if seal.key.is_some() {
+                reserve_output(&sign::signature_path(&page));
+            }
         }
+        let tree = Walker::new(&RealFs, walk_options(calls)).walk(&path)?;
+        return match calls.value_of("html") {
+            Some(page) => {
+                let rendered = render_within(&tree, report_budget(calls), html::render);
+                seal.write(Path::new(page), rendered.into_bytes())?;
+                Ok(())
+            }
+            None => {
+                print!("{}", report::markdown(&tree));
+                Ok(())
+            }
+        };
     }
-    files.sort();
-    dirs.sort();
-    let sorted_entries = files.iter().chain(dirs.iter());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let only = calls.value_of("path").map(|path| absolute(Path::new(path)));
+    let runs: Vec<(PathBuf, history::Run)> = history::cache_dir()
+        .map(|dir| history::runs(&dir))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(root, _)| only.is_none() || only == Some(absolute(root)))
+        .collect();
 
-    let mut total_linecount: u128 = 0;
-    for entry in sorted_entries {
-        let path = entry.as_path();
-        let filetype = fs::metadata(path)?.file_type();
-        let filename = entry.file_name().unwrap().to_str().unwrap_or("?");
+    if runs.is_empty() {
+        println!("no recorded runs");
+    }
+    for (root, run) in &runs {
+        println!(
+            "  {:width$} {} lines, {}, {}",
+            root.display(),
+            history::thousands(run.lines as i128),
+            human_bytes(run.bytes),
+            history::ago(now.saturating_sub(run.timestamp)),
+            width = WIDTH
+        );
+    }
+    Ok(())
+}
 
-        let mut file_linecount: u128 = 0;
+fn growth_report(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let now = SystemTime::now();
+    let moment = |name: &str| {
+        let value = calls.value_of(name).unwrap_or("now");
+        let time = time::parse_time(value, now).unwrap_or_else(|| {
+            eprintln!("lc: --{name} expects now, 7d, a date like 2024-01-01 or 2024-01-01T12:00, got '{value}'");
+            exit(2);
+        });
+        time.duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    };
+    let (from, to) = (moment("from"), moment("to"));
+    if from > to {
+        eprintln!("lc: --from is later than --to");
+        exit(2);
+    }
+    let root = absolute(Path::new(&match calls.value_of("path") {
+        Some(path) => path.to_string(),
+        None => fetch_directory()?,
+    }));
+    let is_root = |recorded: &Path| absolute(recorded) == root;
+    let Some((start, end, growth)) =
+        history::cache_dir().and_then(|dir| history::growth(&dir, &is_root, from, to))
+    else {
+        println!("no snapshots of {} up to --to", root.display());
+        return Ok(());
+    };
 
-        if path.ignore(gitignore.clone()){
-            continue;
-        } else if filetype.is_file() && path.is_visible() {
-            let content = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
-            total_linecount += content.lines().count() as u128;
-            file_linecount += content.lines().count() as u128;
-            println!(
-                "{file_indent}{}",
-                format!("{:width$} {}", filename, file_linecount, width = WIDTH)
-            );
-        } else if filetype.is_dir() && path.is_visible() {
-            total_linecount +=
-                linecount_verbose(Path::new(&path), Some(indent_amount.unwrap() + 2))
-                    .expect("shit!");
+    let utc = calls.is_present("utc");
+    let at =
+        |seconds: u64| time::readable(std::time::UNIX_EPOCH + Duration::from_secs(seconds), utc);
+    println!("[growth]   {} to {}", at(start), at(end));
+    let days = (end - start) as f64 / 86_400.0;
+    for row in growth
+        .iter()
+        .take(number(calls, "top").unwrap_or(usize::MAX))
+    {
+        // a rate over less than a day says more about when the scans ran than about the code
+        let rate = match days >= 1.0 {
+            true => format!(" {:>+9.1}/day", row.change() as f64 / days),
+            false => String::new(),
         };
+        println!(
+            "  {:width$} {:>12} {:>12} {:>12}{rate}",
+            row.directory,
+            history::thousands(row.before as i128),
+            history::thousands(row.after as i128),
+            signed(row.change()),
+            width = WIDTH
+        );
     }
-    Ok(total_linecount)
-}
-
-fn main() -> std::io::Result<()> {
-    //let calls = App::new("lc")
-    //    .version("1.0")
-    //    .author("Ethan Water")
-    //    .about("Line counting program")
-    //    .arg(Arg::new("verbose").short('v').long("verbose"))
-    //    .arg(Arg::new("ignore").short('i').long("ignore"))
-    //    .get_matches();
-
-    //if calls.is_present("verbose") && calls.is_present("ignore") {
-    //    println!("[tree]");
-    //    let start_execution = Instant::now();
-    //    let result = linecount_verbose_ignore(Path::new(&fetch_directory().unwrap()), None)?;
-    //    let end_execution = Instant::now();
-    //    println!("\n[sum]   {result}");
-    //    println!("[execution]   {:?}", end_execution - start_execution);
-    //} else if calls.is_present("verbose") {
-    //    println!("[tree]");
-    //    let start_execution = Instant::now();
-    //    let result = linecount_verbose(Path::new(&fetch_directory().unwrap()), None)?;
-    //    let end_execution = Instant::now();
-    //    println!("\n[sum]   {result}");
-    //    println!("[execution]   {:?}", end_execution - start_execution);
-    //} else if calls.is_present("ignore") {
-    //    let result = linecount_abridged_ignore(Path::new(&fetch_directory().unwrap()))?;
-    //    println!("{result}");
-    //} else {
-    //    let result = linecount_abridged(Path::new(&fetch_directory().unwrap()))?;
-    //    println!("{result}");
-    //}
+    Ok(())
+}
+
+fn badge_files(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let path = match calls.value_of("path") {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(fetch_directory()?),
+    };
+    let color = calls.value_of("color").unwrap_or(badge::COLOR);
+    let hex = badge::hex(color).unwrap_or_else(|| {
+        eprintln!("lc: --color expects a shields.io color name or hex, got '{color}'");
+        exit(2);
+    });
+    if let Some(image) = calls.value_of("svg") {
+        reserve_output(Path::new(image));
+    }
+    let label = calls.value_of("label").unwrap_or(badge::LABEL);
+    let message = match calls.value_of("value") {
+        Some(value) => value.to_string(),
+        None => {
+            let tree = Walker::new(&RealFs, walk_options(calls)).walk(&path)?;
+            badge::compact(tree.lines)
+        }
+    };
 
+    if let Some(image) = calls.value_of("svg") {
+        atomic::write(
+            Path::new(image),
+            badge::svg(label, &message, &hex).as_bytes(),
+        )?;
+    }
+    if calls.is_present("json") {
+        println!("{}", badge::endpoint(label, &message, color));
+    }
     Ok(())
 }
+
+// polls rather than subscribing to change events, which keeps it portable and dependency-free
+fn watch(calls: &clap::ArgMatches) -> Result<(), LcError> {
+    let path = match calls.value_of("path") {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(fetch_directory()?),
+    };
+    let interval = seconds(calls, "interval").unwrap_or(Duration::from_secs(2));
+    let walker = Walker::new(&RealFs, walk_options(calls));
+
+    let mut previous: Option<u128> = None;
+    loop {
+        let tree = walker.walk(&path)?;
+        if previous != Some(tree.lines) {
+            let timestamp = time::iso8601_utc(std::time::SystemTime::now());
+            match previous {
+                Some(lines) => println!(
+                    "[{timestamp}]   {} ({})",
+                    tree.lines,
+                    signed(tree.lines as i128 - lines as i128)
+                ),
+                None => println!("[{timestamp}]   {}", tree.lines),
+            }
+            previous = Some(tree.lines);
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+// usage mistakes exit 2 like a bad flag, a scan stopped by --max-time 124 like
+// timeout(1), anything else 1
+// process::exit skips destructors, so every exit goes through here to release --lock first
+fn exit(code: i32) -> ! {
+    lock::release_all();
+    process::exit(code)
+}
+
+fn fail(err: LcError) -> ! {
+    let code = match err {
+        LcError::InvalidOptions(_) => 2,
+        LcError::Cancelled => 124,
+        _ => 1,
+    };
+    match err {
+        LcError::Cancelled => eprintln!("lc: scan still running at --max-time, gave up"),
+        err => eprintln!("lc: {err}"),
+    }
+    exit(code)
+}
+
+fn main() {
+    let calls = App::new("lc")
+        .version("1.0")
+        .author("Ethan Water")
+        .about("Line counting program")
+        // `lc [flags]` is `lc scan [flags]`, so the root takes the scan flags as well
+        .args_conflicts_with_subcommands(true)
+        .args(walk_args())
+        .args(scan_args())
+        .subcommand(
+            App::new("scan")
+                .about("Count lines, the default when no subcommand is given")
+                .args(walk_args())
+                .args(scan_args()),
+        )
+        .subcommand(
+            App::new("diff")
+                .about("Compare two trees file by file")
+                .args(walk_args())
+                .arg(Arg::new("before").required(true).value_name("BEFORE"))
+                .arg(Arg::new("after").required(true).value_name("AFTER")),
+        )
+        .subcommand(
+            App::new("tree-diff")
+                .about("Compare two trees by content, detecting moved and renamed files")
+                .args(walk_args())
+                .arg(Arg::new("before").required(true).value_name("BEFORE"))
+                .arg(Arg::new("after").required(true).value_name("AFTER")),
+        )
+        .subcommand(
+            App::new("report")
+                .about("List the totals recorded by previous scans")
+                .args(walk_args())
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Only the run recorded for this root, or the tree to render"),
+                )
+                .arg(
+                    Arg::new("markdown")
+                        .long("markdown")
+                        .help("Scan PATH and render markdown tables for PR descriptions or wikis"),
+                )
+                .arg(
+                    Arg::new("report-budget")
+                        .long("report-budget")
+                        .takes_value(true)
+                        .value_name("DURATION")
+                        .help("Fold small files into per-directory rows to render within e.g. 5s"),
+                )
+                .arg(
+                    Arg::new("html")
+                        .long("html")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .conflicts_with("markdown")
+                        .help("Scan PATH and write a standalone HTML page with a collapsible tree"),
+                )
+                .arg(
+                    Arg::new("compress")
+                        .long("compress")
+                        .takes_value(true)
+                        .possible_values(["zstd"])
+                        .requires("html")
+                        .help("Compress the --html page, adding .zst to its name"),
+                )
+                .arg(
+                    Arg::new("sign")
+                        .long("sign")
+                        .takes_value(true)
+                        .value_name("KEY")
+                        .requires("html")
+                        .help("Write an HMAC-SHA256 of the --html page, keyed by the KEY file, to FILE.sig"),
+                ),
+        )
+        .subcommand(
+            App::new("growth")
+                .about("Show how many lines each directory gained between two recorded scans")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Root whose scans to compare [default: the current directory]"),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("WHEN")
+                        .help("Start of the period: now, a span ago like 30d, 2024-01-01 or 2024-01-01T12:00, local unless it ends in Z"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .takes_value(true)
+                        .value_name("WHEN")
+                        .help("End of the period, like --from [default: now]"),
+                )
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("Only the N fastest-growing directories"),
+                )
+                .arg(
+                    Arg::new("utc")
+                        .long("utc")
+                        .help("Print the snapshot times in UTC instead of local time"),
+                ),
+        )
+        .subcommand(
+            App::new("watch")
+                .about("Rescan periodically and print the total whenever it changes")
+                .args(walk_args())
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Directory to watch [default: the current directory]"),
+                )
+                .arg(
+                    Arg::new("interval")
+                        .long("interval")
+                        .takes_value(true)
+                        .value_name("SECS")
+                        .help("Time between scans [default: 2]"),
+                ),
+        )
+        .subcommand(
+            App::new("badge")
+                .about("Write a lines-of-code badge for the README")
+                .args(walk_args())
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Tree to count [default: the current directory]"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print shields.io endpoint JSON, for a badge that updates from CI"),
+                )
+                .arg(
+                    Arg::new("label")
+                        .long("label")
+                        .takes_value(true)
+                        .value_name("TEXT")
+                        .help("Text on the left of the badge [default: lines of code]"),
+                )
+                .arg(
+                    Arg::new("svg")
+                        .long("svg")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help(
+                            "Render the badge image to FILE, for repos that can't use shields.io",
+                        ),
+                )
+                .arg(
+                    Arg::new("value")
+                        .long("value")
+                        .takes_value(true)
+                        .value_name("TEXT")
+                        .help("Text on the right instead of the line count, which skips the scan"),
+                )
+                .arg(
+                    Arg::new("color")
+                        .long("color")
+                        .takes_value(true)
+                        .value_name("COLOR")
+                        .help("shields.io color name or hex for the right side [default: blue]"),
+                )
+                .group(
+                    ArgGroup::new("output")
+                        .args(&["json", "svg"])
+                        .multiple(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            App::new("multi")
+                .about("Scan a list of repositories and compare them")
+                .args(walk_args())
+                .arg(
+                    Arg::new("repos")
+                        .long("repos")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("FILE")
+                        .help("One local path or git URL per line"),
+                )
+                .arg(
+                    Arg::new("workdir")
+                        .long("workdir")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .help("Where remote repositories are cloned [default: ~/.cache/lc/repos]"),
+                ),
+        )
+        .subcommand(
+            App::new("explain")
+                .about("Say why a path is counted or skipped, naming the ignore rule or flag")
+                .args(walk_args())
+                .arg(Arg::new("path").required(true).value_name("PATH"))
+                .arg(
+                    Arg::new("root")
+                        .long("root")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .help("Where the scan would start [default: the current directory]"),
+                ),
+        )
+        .subcommand(
+            App::new("bench")
+                .about("Time counting a generated tree, failing below a throughput floor")
+                .arg(
+                    Arg::new("fixture")
+                        .long("fixture")
+                        .takes_value(true)
+                        .possible_values(["synthetic"])
+                        .default_value("synthetic")
+                        .help("The tree to count, the same on every machine"),
+                )
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .takes_value(true)
+                        .value_name("SIZE")
+                        .help("Roughly how much the fixture holds [default: 64MB]"),
+                )
+                .arg(
+                    Arg::new("iterations")
+                        .long("iterations")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("Timed scans, of which the fastest counts [default: 5]"),
+                )
+                .arg(
+                    Arg::new("assert-throughput")
+                        .long("assert-throughput")
+                        .takes_value(true)
+                        .value_name("RATE")
+                        .help("Exit with status 1 below this rate, like 200MB/s"),
+                ),
+        )
+        .subcommand(
+            App::new("cache")
+                .about("Work out what --cache would cost and save before turning it on")
+                .subcommand_required(true)
+                .subcommand(
+                    App::new("estimate")
+                        .about("Size the cache from metadata and time a sample of reads, without writing anything")
+                        .args(walk_args())
+                        .arg(
+                            Arg::new("path")
+                                .value_name("PATH")
+                                .help("Tree to estimate for [default: the current directory]"),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("verify-signature")
+                .about("Check a report file against the FILE.sig written by --sign")
+                .arg(Arg::new("path").required(true).value_name("FILE"))
+                .arg(
+                    Arg::new("key")
+                        .long("key")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("KEY")
+                        .help("The key file the report was signed with"),
+                ),
+        )
+        .subcommand(
+            App::new("verify-counts")
+                .about("Count files with three independent strategies and report disagreements")
+                .arg(Arg::new("path").required(true).value_name("PATH")),
+        )
+        .get_matches();
+
+    let result = match calls.subcommand() {
+        Some(("scan", scan_calls)) => scan(scan_calls),
+        Some(("diff", diff)) => diff_trees(diff),
+        Some(("tree-diff", diff)) => tree_diff(diff),
+        Some(("report", report)) => report_runs(report),
+        Some(("growth", growth)) => growth_report(growth),
+        Some(("watch", watch_calls)) => watch(watch_calls),
+        Some(("badge", badge)) => badge_files(badge),
+        Some(("multi", multi)) => scan_repositories(multi),
+        Some(("explain", explain)) => explain_path(explain),
+        Some(("verify-counts", verify)) => verify_counts(verify),
+        Some(("verify-signature", verify)) => verify_signature(verify),
+        Some(("cache", cache)) => match cache.subcommand() {
+            Some(("estimate", estimate)) => cache_estimate(estimate),
+            _ => unreachable!("clap requires a cache subcommand"),
+        },
+        Some(("bench", bench)) => bench_throughput(bench),
+        _ => scan(&calls),
+    };
+    if let Err(err) = result {
+        fail(err);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+
+    fn walk(directory_path: &Path, options: Options) -> std::io::Result<Entry> {
+        Ok(Walker::new(&RealFs, options).walk(directory_path)?)
+    }
+
+    // writes a throwaway tree under the temp dir and returns its root
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("lc-test-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    fn ignore() -> Options {
+        Options {
+            ignore: true,
+            ..Options::default()
+        }
+    }
 
     #[test]
-    fn verbose() {
+    fn verbose() -> std::io::Result<()> {
         println!("[tree]");
         let start_execution = Instant::now();
-        let result = linecount_verbose(Path::new(&fetch_directory().unwrap()), None).unwrap();
+        let tree = walk(Path::new(&fetch_directory().unwrap()), Options::default()).unwrap();
+        print_tree(&mut io::stdout(), &tree, 0, false)?;
         let end_execution = Instant::now();
-        println!("\n[sum]   {result}");
+        println!("\n[sum]   {}", tree.lines);
         println!("[execution]   {:?}", end_execution - start_execution);
+        Ok(())
     }
     #[test]
     fn verbose_ignore() -> std::io::Result<()> {
         println!("[tree]");
         let start_execution = Instant::now();
-        let result = linecount_verbose_ignore(Path::new(&fetch_directory().unwrap()), None)?;
+        let tree = walk(Path::new(&fetch_directory().unwrap()), ignore())?;
+        print_tree(&mut io::stdout(), &tree, 0, false)?;
         let end_execution = Instant::now();
-        println!("\n[sum]   {result}");
+        println!("\n[sum]   {}", tree.lines);
         println!("[execution]   {:?}", end_execution - start_execution);
         Ok(())
     }
     #[test]
     fn abridged_ignore() -> std::io::Result<()> {
-        let result = linecount_abridged_ignore(Path::new(&fetch_directory().unwrap()))?;
+        let result = walk(Path::new(&fetch_directory().unwrap()), ignore())?.lines;
         println!("{result}");
         Ok(())
     }
     #[test]
+    fn reserved_outputs() {
+        assert!(Path::new(CACHE_FILE).is_reserved());
+        assert!(Path::new("src").join(CHECKPOINT_FILE).is_reserved());
+        assert!(!Path::new("src/main.rs").is_reserved());
+        assert!(Path::new("src/.report.json.lc-tmp-42").is_reserved());
+
+        reserve_output(Path::new("src/report.json"));
+        assert!(Path::new("src/report.json").is_reserved());
+        assert!(Path::new("./src/../src/report.json").is_reserved());
+        assert!(!Path::new("report.json").is_reserved());
+    }
+    #[test]
+    fn output_file() -> std::io::Result<()> {
+        let root = fixture("output", &[("a.rs", "1\n")]);
+        let file = root.join("report.txt");
+        let mut output = Output::new(file.to_str());
+        writeln!(output, "6710")?;
+        assert!(!file.exists());
+        output.finish()?;
+        assert_eq!(fs::read_to_string(&file)?, "6710\n");
+        // registered, so a rescan of the same tree leaves it out
+        assert!(file.is_reserved());
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_output_file() -> std::io::Result<()> {
+        let root = fixture("compressed-output", &[("a.rs", "1\n")]);
+        let file = root.join("report.json");
+        let seal = Seal {
+            compress: true,
+            key: None,
+        };
+        let mut output = Output::new(file.to_str()).sealed(seal);
+        writeln!(output, "{{}}")?;
+        output.finish()?;
+        let compressed = root.join("report.json.zst");
+        assert!(!file.exists());
+        assert_eq!(fs::read(&compressed)?, zstd::compress(b"{}\n"));
+        assert!(compressed.is_reserved());
+        Ok(())
+    }
+
+    #[test]
+    fn saved_index_left_out() -> std::io::Result<()> {
+        let root = fixture("saved-index", &[("a.rs", "1\n")]);
+        let file = root.join("idx.bin");
+        reserve_output(&file);
+        for _ in 0..2 {
+            let tree = walk(&root, Options::default())?;
+            assert_eq!(tree.files(), 1);
+            index::write(&file, &tree)?;
+        }
+        assert!(file.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn rendered_rows() -> std::io::Result<()> {
+        let root = fixture("rendered", &[("a.rs", "1\n2\n"), ("sub/b.rs", "1\n")]);
+        let tree = walk(&root, Options::default())?;
+        let (mut full, mut shallow) = (Vec::new(), Vec::new());
+        print_tree(&mut full, &tree, 0, false)?;
+        print_shallow(&mut shallow, &tree, false)?;
+
+        let path = root.to_str().unwrap();
+        let row = |indent: usize, name: &str, lines: u128| {
+            format!("{:indent$}{name:WIDTH$} {lines}\n", "")
+        };
+        let expected = format!(
+            "{path}/\n{}  {path}/sub/\n{}",
+            row(2, "a.rs", 2),
+            row(4, "b.rs", 1)
+        );
+        assert_eq!(String::from_utf8_lossy(&full), expected);
+        let expected = format!("{path}/\n{}{}", row(2, "a.rs", 2), row(2, "sub/", 1));
+        assert_eq!(String::from_utf8_lossy(&shallow), expected);
+        Ok(())
+    }
+    #[test]
+    fn shallow() -> std::io::Result<()> {
+        let tree = walk(Path::new("src"), Options::default())?;
+        print_shallow(&mut io::stdout(), &tree, false)?;
+        let rows: u128 = tree.children.iter().map(|child| child.lines).sum();
+        assert_eq!(rows, tree.lines);
+        Ok(())
+    }
+    #[test]
+    fn flat() -> std::io::Result<()> {
+        let root = fixture(
+            "flat",
+            &[("a.txt", "1\n2\n"), ("b.txt", "1\n"), ("sub/c.txt", "1\n")],
+        );
+        let flat = Options {
+            recurse: false,
+            ..Options::default()
+        };
+        assert_eq!(walk(&root, flat)?.lines, 3);
+        assert_eq!(walk(&root, Options::default())?.lines, 4);
+        Ok(())
+    }
+    #[test]
+    fn row_limit() -> std::io::Result<()> {
+        let root = fixture(
+            "limit",
+            &[("a.rs", "1\n"), ("b.rs", "1\n"), ("sub/c.rs", "1\n")],
+        );
+        let tree = walk(&root, Options::default())?;
+        let mut limited = RowLimit {
+            inner: Vec::new(),
+            remaining: 2,
+            omitted: 0,
+        };
+        print_tree(&mut limited, &tree, 0, false)?;
+        let mut full = Vec::new();
+        print_tree(&mut full, &tree, 0, false)?;
+        let full = String::from_utf8(full).unwrap();
+        let first_two: String = full.split_inclusive('\n').take(2).collect();
+        assert_eq!(String::from_utf8(limited.inner).unwrap(), first_two);
+        assert_eq!(limited.omitted, full.lines().count() - 2);
+        Ok(())
+    }
+
+    #[test]
+    fn assertion_fields() -> std::io::Result<()> {
+        let root = fixture("fields", &[("a.rs", "1\n2\n"), ("b.py", "1\n")]);
+        let tree = walk(&root, Options::default())?;
+        assert!(!check_assertions(
+            &tree,
+            &[
+                "lines == 3 && files == 2",
+                "langs.rust.lines > langs.python.lines",
+                "langs.go.files == 0"
+            ]
+        ));
+        assert!(check_assertions(&tree, &["bytes > 100"]));
+        assert_eq!(report_fields(&tree)("langs.rust"), None);
+        assert_eq!(report_fields(&tree)("types.code.files"), Some(2.0));
+        assert_eq!(report_fields(&tree)("types.media.bytes"), Some(0.0));
+        assert_eq!(report_fields(&tree)("types.video.bytes"), None);
+        assert_eq!(report_fields(&tree)("vendored.lines"), Some(0.0));
+        Ok(())
+    }
+    #[test]
+    fn combined_roots() -> std::io::Result<()> {
+        let root = fixture("roots", &[("a/x.rs", "1\n2\n"), ("b/y.rs", "1\n")]);
+        let roots = vec![
+            walk(&root.join("a"), Options::default())?,
+            walk(&root.join("b"), Options::default())?,
+        ];
+        let tree = Entry::combine(roots);
+        assert_eq!((tree.lines, tree.files(), tree.children.len()), (3, 2, 2));
+        Ok(())
+    }
+    #[test]
+    fn newest_below() -> std::io::Result<()> {
+        let root = fixture("mtime", &[("old/a.rs", "1\n"), ("new/b.rs", "1\n")]);
+        let at = |secs| std::time::UNIX_EPOCH + Duration::from_secs(secs);
+        for (file, secs) in [("old/a.rs", 1_000), ("new/b.rs", 2_000)] {
+            fs::File::options()
+                .write(true)
+                .open(root.join(file))?
+                .set_modified(at(secs))?;
+        }
+        let tree = walk(&root, Options::default())?;
+        assert_eq!(tree.modified, Some(at(2_000)));
+        assert_eq!(tree.children[1].modified, Some(at(1_000)));
+        assert_eq!(newest(&tree.children[1]), "1970-01-01T00:16:40Z");
+        Ok(())
+    }
+    #[test]
+    fn human_sizes() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(1536), "1.5 KB");
+        assert_eq!(human_bytes(5 * 1024 * 1024 * 1024), "5.0 GB");
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("10MB"), Some(10 * 1024 * 1024));
+        assert_eq!(parse_size("1.5k"), Some(1536));
+        assert_eq!(parse_size("2 GiB"), Some(2 << 30));
+        assert_eq!(parse_size("MB"), None);
+        assert_eq!(parse_size("3 parsecs"), None);
+    }
+    #[test]
     fn abridged() -> std::io::Result<()> {
-        let result = linecount_abridged(Path::new(&fetch_directory().unwrap()))?;
+        let result = walk(Path::new(&fetch_directory().unwrap()), Options::default())?.lines;
         println!("{result}");
         Ok(())
     }
 }
-
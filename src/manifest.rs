@@ -0,0 +1,75 @@
+use std::path::Path;
+
+// a build manifest next to a directory tells us that directory is build output
+pub struct Rule {
+    pub name: &'static str,
+    pub manifest: &'static str,
+    pub excludes: &'static [&'static str],
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        name: "cargo",
+        manifest: "Cargo.toml",
+        excludes: &["target"],
+    },
+    Rule {
+        name: "npm",
+        manifest: "package.json",
+        excludes: &["node_modules", "dist"],
+    },
+    Rule {
+        name: "cmake",
+        manifest: "CMakeLists.txt",
+        excludes: &["build"],
+    },
+];
+
+pub fn rule_names() -> Vec<&'static str> {
+    RULES.iter().map(|rule| rule.name).collect()
+}
+
+fn active<'a>(
+    entries: &'a [&Path],
+    disabled: &'a [String],
+) -> impl Iterator<Item = &'static Rule> + 'a {
+    RULES
+        .iter()
+        .filter(|rule| !disabled.iter().any(|name| name == rule.name))
+        .filter(|rule| {
+            entries
+                .iter()
+                .any(|entry| entry.file_name().is_some_and(|name| name == rule.manifest))
+        })
+}
+
+// directory names to skip given the entries of their parent
+pub fn excluded_dirs(entries: &[&Path], disabled: &[String]) -> Vec<&'static str> {
+    active(entries, disabled)
+        .flat_map(|rule| rule.excludes.iter().copied())
+        .collect()
+}
+
+// the rule behind skipping `dir`, for `lc explain`
+pub fn excluding_rule(entries: &[&Path], disabled: &[String], dir: &Path) -> Option<&'static Rule> {
+    active(entries, disabled).find(|rule| rule.excludes.iter().any(|name| dir.ends_with(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests() {
+        let entries = [Path::new("r/Cargo.toml"), Path::new("r/package.json")];
+        assert_eq!(
+            excluded_dirs(&entries, &[]),
+            ["target", "node_modules", "dist"]
+        );
+        assert_eq!(excluded_dirs(&entries, &["npm".to_string()]), ["target"]);
+        assert!(excluded_dirs(&[Path::new("r/build")], &[]).is_empty());
+        let rule = excluding_rule(&entries, &[], Path::new("r/dist"));
+        assert_eq!(rule.map(|rule| rule.name), Some("npm"));
+        assert!(excluding_rule(&entries, &[], Path::new("r/src")).is_none());
+    }
+}
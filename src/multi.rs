@@ -0,0 +1,98 @@
+use crate::error::LcError;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    Local(PathBuf),
+    Remote(String),
+}
+
+impl Source {
+    pub fn name(&self) -> String {
+        let tail = match self {
+            Source::Local(path) => path
+                .canonicalize()
+                .unwrap_or_else(|_| path.clone())
+                .to_string_lossy()
+                .trim_end_matches('/')
+                .to_string(),
+            Source::Remote(url) => url
+                .trim_end_matches('/')
+                .trim_end_matches(".git")
+                .to_string(),
+        };
+        tail.rsplit(['/', ':']).next().unwrap_or(&tail).to_string()
+    }
+}
+
+// one repository per line: a local path or anything git can clone
+pub fn parse_repos(contents: &str) -> Vec<Source> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(
+            |line| match line.contains("://") || line.starts_with("git@") {
+                true => Source::Remote(line.to_string()),
+                false => Source::Local(PathBuf::from(line)),
+            },
+        )
+        .collect()
+}
+
+fn git(arguments: &[&str], directory: &Path) -> Result<(), LcError> {
+    let failed = |reason: String| LcError::Git {
+        command: arguments.join(" "),
+        reason,
+    };
+    let status = Command::new("git")
+        .args(arguments)
+        .current_dir(directory)
+        .status()
+        .map_err(|err| failed(err.to_string()))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(failed(status.to_string())),
+    }
+}
+
+// remote repositories are cloned once into `workdir` and fast-forwarded after that
+pub fn checkout(source: &Source, workdir: &Path) -> Result<PathBuf, LcError> {
+    match source {
+        Source::Local(path) => Ok(path.clone()),
+        Source::Remote(url) => {
+            let target = workdir.join(source.name());
+            if target.join(".git").exists() {
+                git(&["pull", "--ff-only", "--quiet"], &target)?;
+            } else {
+                std::fs::create_dir_all(workdir)?;
+                let target = target.to_string_lossy();
+                git(&["clone", "--quiet", "--depth", "1", url, &target], workdir)?;
+            }
+            Ok(target)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repos_file() {
+        let repos = parse_repos(
+            "# org inventory\n../lc\nhttps://github.com/ethanwater/lc.git\ngit@github.com:org/api.git\n\n",
+        );
+        assert_eq!(
+            repos,
+            [
+                Source::Local(PathBuf::from("../lc")),
+                Source::Remote("https://github.com/ethanwater/lc.git".to_string()),
+                Source::Remote("git@github.com:org/api.git".to_string()),
+            ]
+        );
+        let names: Vec<String> = repos.iter().map(Source::name).collect();
+        assert_eq!(names, ["lc", "lc", "api"]);
+    }
+}
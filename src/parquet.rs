@@ -0,0 +1,269 @@
+use crate::atomic;
+use crate::report::relative;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::io;
+use std::path::Path;
+
+// a single row group of plain-encoded, uncompressed, required columns: the smallest file
+// every Parquet reader accepts, written without pulling arrow into the build
+const MAGIC: &[u8] = b"PAR1";
+
+// thrift compact protocol type ids
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+// parquet enums
+const TYPE_INT64: i32 = 2;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REQUIRED: i32 = 0;
+const UTF8: i32 = 0;
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+const UNCOMPRESSED: i32 = 0;
+const DATA_PAGE: i32 = 0;
+
+#[derive(Default)]
+struct Compact {
+    out: Vec<u8>,
+    // last field id written at each struct depth, for the delta-encoded field headers
+    last: Vec<i16>,
+    current: i16,
+}
+
+impl Compact {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn zigzag(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        match id - self.current {
+            delta @ 1..=15 => self.out.push((delta as u8) << 4 | kind),
+            _ => {
+                self.out.push(kind);
+                self.zigzag(id as i64);
+            }
+        }
+        self.current = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.zigzag(value as i64);
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        self.zigzag(value);
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, BINARY);
+        self.varint(value.len() as u64);
+        self.out.extend_from_slice(value);
+    }
+
+    fn list(&mut self, id: i16, kind: u8, size: usize) {
+        self.field(id, LIST);
+        self.list_header(kind, size);
+    }
+
+    fn list_header(&mut self, kind: u8, size: usize) {
+        match size {
+            0..=14 => self.out.push((size as u8) << 4 | kind),
+            _ => {
+                self.out.push(0xf0 | kind);
+                self.varint(size as u64);
+            }
+        }
+    }
+
+    // a struct as a field, or with `id` None as a list element
+    fn begin(&mut self, id: Option<i16>) {
+        if let Some(id) = id {
+            self.field(id, STRUCT);
+        }
+        self.last.push(self.current);
+        self.current = 0;
+    }
+
+    fn end(&mut self) {
+        self.out.push(0);
+        self.current = self.last.pop().unwrap_or(0);
+    }
+}
+
+struct Column {
+    name: &'static str,
+    kind: i32,
+    values: Vec<u8>,
+}
+
+fn page_header(size: usize, rows: usize) -> Vec<u8> {
+    let mut header = Compact::default();
+    header.begin(None);
+    header.i32(1, DATA_PAGE);
+    header.i32(2, size as i32);
+    header.i32(3, size as i32);
+    header.begin(Some(5));
+    header.i32(1, rows as i32);
+    header.i32(2, PLAIN);
+    header.i32(3, RLE);
+    header.i32(4, RLE);
+    header.end();
+    header.end();
+    header.out
+}
+
+// one row per file: path relative to the root, lines, bytes
+pub fn encode(tree: &Entry) -> Vec<u8> {
+    fn visit(tree: &Entry, entry: &Entry, columns: &mut [Column; 3], rows: &mut usize) {
+        if entry.kind == Kind::Dir {
+            for child in &entry.children {
+                visit(tree, child, columns, rows);
+            }
+            return;
+        }
+        let path = relative(tree, &entry.path).to_string_lossy();
+        columns[0]
+            .values
+            .extend_from_slice(&(path.len() as u32).to_le_bytes());
+        columns[0].values.extend_from_slice(path.as_bytes());
+        columns[1]
+            .values
+            .extend_from_slice(&(entry.lines as i64).to_le_bytes());
+        columns[2]
+            .values
+            .extend_from_slice(&(entry.bytes as i64).to_le_bytes());
+        *rows += 1;
+    }
+
+    let column = |name, kind| Column {
+        name,
+        kind,
+        values: Vec::new(),
+    };
+    let mut columns = [
+        column("path", TYPE_BYTE_ARRAY),
+        column("lines", TYPE_INT64),
+        column("bytes", TYPE_INT64),
+    ];
+    let mut rows = 0;
+    visit(tree, tree, &mut columns, &mut rows);
+
+    let mut file = MAGIC.to_vec();
+    let mut chunks = Vec::new();
+    for column in &columns {
+        let offset = file.len();
+        file.extend(page_header(column.values.len(), rows));
+        file.extend_from_slice(&column.values);
+        chunks.push((offset, file.len() - offset));
+    }
+
+    let mut footer = Compact::default();
+    footer.begin(None);
+    footer.i32(1, 1);
+    footer.list(2, STRUCT, columns.len() + 1);
+    footer.begin(None);
+    footer.binary(4, b"schema");
+    footer.i32(5, columns.len() as i32);
+    footer.end();
+    for column in &columns {
+        footer.begin(None);
+        footer.i32(1, column.kind);
+        footer.i32(3, REQUIRED);
+        footer.binary(4, column.name.as_bytes());
+        if column.kind == TYPE_BYTE_ARRAY {
+            footer.i32(6, UTF8);
+        }
+        footer.end();
+    }
+    footer.i64(3, rows as i64);
+    footer.list(4, STRUCT, 1);
+    footer.begin(None);
+    footer.list(1, STRUCT, columns.len());
+    for (column, (offset, size)) in columns.iter().zip(&chunks) {
+        footer.begin(None);
+        footer.i64(2, *offset as i64);
+        footer.begin(Some(3));
+        footer.i32(1, column.kind);
+        footer.list(2, I32, 1);
+        footer.zigzag(PLAIN as i64);
+        footer.list(3, BINARY, 1);
+        footer.varint(column.name.len() as u64);
+        footer.out.extend_from_slice(column.name.as_bytes());
+        footer.i32(4, UNCOMPRESSED);
+        footer.i64(5, rows as i64);
+        footer.i64(6, *size as i64);
+        footer.i64(7, *size as i64);
+        footer.i64(9, *offset as i64);
+        footer.end();
+        footer.end();
+    }
+    footer.i64(2, chunks.iter().map(|(_, size)| *size as i64).sum());
+    footer.i64(3, rows as i64);
+    footer.end();
+    footer.binary(6, b"lc");
+    footer.end();
+
+    file.extend_from_slice(&footer.out);
+    file.extend_from_slice(&(footer.out.len() as u32).to_le_bytes());
+    file.extend_from_slice(MAGIC);
+    file
+}
+
+pub fn write(path: &Path, tree: &Entry) -> io::Result<()> {
+    atomic::write(path, &encode(tree))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+
+    #[test]
+    fn compact_protocol() {
+        let mut compact = Compact::default();
+        compact.begin(None);
+        compact.i32(1, -1);
+        compact.i64(20, 300);
+        compact.list(21, I32, 16);
+        compact.end();
+        assert_eq!(
+            compact.out,
+            [0x15, 0x01, 0x06, 0x28, 0xd8, 0x04, 0x19, 0xf5, 0x10, 0x00]
+        );
+    }
+
+    #[test]
+    fn file_layout() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n2\n");
+        memory.insert("r/sub/b.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        let file = encode(&tree);
+        assert_eq!(&file[..4], MAGIC);
+        assert_eq!(&file[file.len() - 4..], MAGIC);
+        let footer = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap());
+        let metadata = &file[file.len() - 8 - footer as usize..file.len() - 8];
+        // version 1, then a list of four schema structs
+        assert_eq!(metadata[..4], [0x15, 0x02, 0x19, 0x4c]);
+        let needle = |bytes: &[u8]| file.windows(bytes.len()).any(|window| window == bytes);
+        assert!(needle(b"\x04\x00\x00\x00a.rs\x08\x00\x00\x00sub/b.rs"));
+        assert!(needle(&[2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]));
+        Ok(())
+    }
+}
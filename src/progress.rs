@@ -0,0 +1,122 @@
+// `--progress json`: a JSON object per line on stderr every INTERVAL while the walk runs,
+// and a last one with `"done": true`, for GUIs and build systems drawing their own bars.
+// The ETA goes by the bytes the last recorded run of the same root counted, so a first
+// run, or one over several roots, has none.
+use crate::json::Json;
+use crate::walk::Entry;
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub const INTERVAL: Duration = Duration::from_millis(200);
+
+pub struct Progress {
+    started: Instant,
+    interval: Duration,
+    // bytes the walk should come to, from history
+    expected: Option<u64>,
+    reported: Cell<Instant>,
+    files: Cell<u64>,
+    bytes: Cell<u64>,
+    current: RefCell<PathBuf>,
+}
+
+impl Progress {
+    pub fn new(expected: Option<u64>, interval: Duration) -> Progress {
+        let now = Instant::now();
+        Progress {
+            started: now,
+            interval,
+            expected,
+            reported: Cell::new(now),
+            files: Cell::new(0),
+            bytes: Cell::new(0),
+            current: RefCell::new(PathBuf::new()),
+        }
+    }
+
+    // counts a file in, and hands back an object to print once INTERVAL has passed since
+    // the last one
+    pub fn file(&self, entry: &Entry) -> Option<Json> {
+        self.files.set(self.files.get() + 1);
+        self.bytes.set(self.bytes.get() + entry.bytes);
+        *self.current.borrow_mut() = entry.path.clone();
+        let now = Instant::now();
+        if now.duration_since(self.reported.get()) < self.interval {
+            return None;
+        }
+        self.reported.set(now);
+        Some(self.record(now, false))
+    }
+
+    pub fn finish(&self) -> Json {
+        self.record(Instant::now(), true)
+    }
+
+    fn record(&self, now: Instant, done: bool) -> Json {
+        let bytes = self.bytes.get();
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let eta = match self.expected {
+            _ if done => Json::from(0.0),
+            Some(expected) if expected > bytes && bytes > 0 => {
+                Json::from((expected - bytes) as f64 * elapsed / bytes as f64)
+            }
+            _ => Json::Null,
+        };
+        Json::object(vec![
+            ("files_done", Json::from(self.files.get())),
+            ("bytes_done", Json::from(bytes)),
+            (
+                "current_path",
+                Json::str(self.current.borrow().to_string_lossy()),
+            ),
+            ("elapsed", Json::from(elapsed)),
+            ("eta", eta),
+            ("done", Json::Bool(done)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+    use std::path::Path;
+
+    #[test]
+    fn reports() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n2\n");
+        memory.insert("r/b.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let field = |record: &Json, name: &str| match record {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .unwrap()
+                .1
+                .clone(),
+            _ => unreachable!(),
+        };
+
+        let progress = Progress::new(Some(12), Duration::ZERO);
+        let record = progress.file(&tree.children[0]).unwrap();
+        assert_eq!(field(&record, "files_done"), Json::Int(1));
+        assert_eq!(field(&record, "bytes_done"), Json::Int(4));
+        assert_eq!(field(&record, "current_path"), Json::str("r/a.rs"));
+        assert!(matches!(field(&record, "eta"), Json::Float(eta) if eta >= 0.0));
+        progress.file(&tree.children[1]);
+        let last = progress.finish();
+        assert_eq!(field(&last, "bytes_done"), Json::Int(6));
+        assert_eq!(field(&last, "done"), Json::Bool(true));
+
+        // nothing to go by, and too soon to say anything at all
+        let progress = Progress::new(None, Duration::from_secs(3600));
+        assert_eq!(progress.file(&tree.children[0]), None);
+        assert_eq!(field(&progress.finish(), "eta"), Json::from(0.0));
+        let unknown = Progress::new(None, Duration::ZERO).file(&tree.children[0]);
+        assert_eq!(field(&unknown.unwrap(), "eta"), Json::Null);
+        Ok(())
+    }
+}
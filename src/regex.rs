@@ -0,0 +1,370 @@
+// the regular expressions `--regex`, `--regex-exclude` and `--match-content` take: literals,
+// `.`, classes like `[a-z]`, `[^/]`, `\d`, `\w`, `\s`, anchors `^` and `$`, groups with `|`,
+// and the `*`, `+`, `?` and `{n,m}` repetitions. Matching runs every alternative side by side
+// (Pike's VM), so no pattern can take exponential time on a long path.
+
+type Ranges = Vec<(char, char)>;
+
+#[derive(Clone, Debug)]
+enum Node {
+    Literal(char),
+    Any,
+    Class(Ranges, bool),
+    Start,
+    End,
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat(Box<Node>, u32, Option<u32>),
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Ranges, bool),
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Clone, Debug)]
+pub struct Regex {
+    pattern: String,
+    program: Vec<Inst>,
+}
+
+// repetition bigger than this is surely a typo and would only bloat the program
+const MAX_REPEAT: u32 = 1000;
+
+fn escaped_class(c: char) -> Option<(Ranges, bool)> {
+    let digits = vec![('0', '9')];
+    let word = vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+    let space = vec![('\t', '\r'), (' ', ' ')];
+    Some(match c {
+        'd' => (digits, false),
+        'D' => (digits, true),
+        'w' => (word, false),
+        'W' => (word, true),
+        's' => (space, false),
+        'S' => (space, true),
+        _ => return None,
+    })
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.concatenation()?];
+        while self.chars.next_if_eq(&'|').is_some() {
+            branches.push(self.concatenation()?);
+        }
+        Ok(match branches.len() {
+            1 => branches.pop().unwrap(),
+            _ => Node::Alternate(branches),
+        })
+    }
+
+    fn concatenation(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(&c) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.repetition(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        Ok(match self.chars.next().unwrap() {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                // non-capturing groups are written the same way; nothing is captured anyway
+                if self.chars.next_if_eq(&'?').is_some() && self.chars.next() != Some(':') {
+                    return Err("only (?:...) groups are supported".to_string());
+                }
+                let inner = self.alternation()?;
+                if self.chars.next() != Some(')') {
+                    return Err("unclosed (".to_string());
+                }
+                inner
+            }
+            ')' => return Err("unmatched )".to_string()),
+            '[' => self.class()?,
+            '\\' => {
+                let c = self.chars.next().ok_or("trailing \\")?;
+                match escaped_class(c) {
+                    Some((ranges, negated)) => Node::Class(ranges, negated),
+                    None if c.is_ascii_alphanumeric() => {
+                        return Err(format!("unknown escape \\{c}"))
+                    }
+                    None => Node::Literal(c),
+                }
+            }
+            c @ ('*' | '+' | '?' | '{') => return Err(format!("nothing to repeat before {c}")),
+            c => Node::Literal(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.chars.next_if_eq(&'^').is_some();
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.chars.next().ok_or("unclosed [")?;
+            // a `]` straight after `[` or `[^` is a literal
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = match c {
+                '\\' => {
+                    let c = self.chars.next().ok_or("unclosed [")?;
+                    match escaped_class(c) {
+                        Some((more, false)) => {
+                            ranges.extend(more);
+                            continue;
+                        }
+                        Some((_, true)) => return Err(format!("\\{c} inside [...]")),
+                        None => c,
+                    }
+                }
+                c => c,
+            };
+            let mut ahead = self.chars.clone();
+            if ahead.next() == Some('-') && ahead.peek().is_some_and(|&c| c != ']') {
+                self.chars.next();
+                let high = match self.chars.next().unwrap() {
+                    '\\' => self.chars.next().ok_or("unclosed [")?,
+                    c => c,
+                };
+                if high < low {
+                    return Err(format!("range {low}-{high} is backwards"));
+                }
+                ranges.push((low, high));
+            } else {
+                ranges.push((low, low));
+            }
+        }
+        Ok(Node::Class(ranges, negated))
+    }
+
+    fn repetition(&mut self, mut atom: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = match self.chars.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => {
+                    self.chars.next();
+                    let mut body = String::new();
+                    for c in self.chars.by_ref() {
+                        if c == '}' {
+                            break;
+                        }
+                        body.push(c);
+                    }
+                    let bound = |text: &str| -> Result<u32, String> {
+                        match text.trim().parse() {
+                            Ok(n) if n <= MAX_REPEAT => Ok(n),
+                            _ => Err(format!("bad repetition {{{body}}}")),
+                        }
+                    };
+                    let (min, max) = match body.split_once(',') {
+                        None => (bound(&body)?, Some(bound(&body)?)),
+                        Some((min, "")) => (bound(min)?, None),
+                        Some((min, max)) => (bound(min)?, Some(bound(max)?)),
+                    };
+                    if max.is_some_and(|max| max < min) {
+                        return Err(format!("bad repetition {{{body}}}"));
+                    }
+                    atom = Node::Repeat(Box::new(atom), min, max);
+                    continue;
+                }
+                _ => return Ok(atom),
+            };
+            self.chars.next();
+            // `*?` and friends only change which match is found, never whether there is one
+            self.chars.next_if_eq(&'?');
+            atom = Node::Repeat(Box::new(atom), min, max);
+        }
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Literal(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(ranges, negated) => program.push(Inst::Class(ranges.clone(), *negated)),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, program)),
+        Node::Alternate(branches) => {
+            let mut jumps = Vec::new();
+            for (i, branch) in branches.iter().enumerate() {
+                let split = program.len();
+                if i + 1 < branches.len() {
+                    program.push(Inst::Split(split + 1, 0));
+                }
+                compile(branch, program);
+                if i + 1 < branches.len() {
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat(inner, min, max) => {
+            for _ in 0..*min {
+                compile(inner, program);
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(inner, program);
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(program.len() + 1, 0));
+                        compile(inner, program);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().peekable(),
+        };
+        let node = parser.alternation()?;
+        if parser.chars.next().is_some() {
+            return Err("unmatched )".to_string());
+        }
+        let mut program = Vec::new();
+        compile(&node, &mut program);
+        program.push(Inst::Match);
+        Ok(Regex {
+            pattern: pattern.to_string(),
+            program,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    // anywhere in `text`, like grep; anchor with ^ and $ to match all of it
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let mut seen = vec![usize::MAX; self.program.len()];
+        let (mut current, mut next) = (Vec::new(), Vec::new());
+        for position in 0..=chars.len() {
+            // a fresh thread at every position makes the search unanchored
+            self.add(&mut current, &mut seen, 0, position, chars.len());
+            for &pc in &current {
+                let matched = match (&self.program[pc], chars.get(position)) {
+                    (Inst::Match, _) => return true,
+                    (Inst::Char(c), Some(actual)) => c == actual,
+                    (Inst::Any, Some(_)) => true,
+                    (Inst::Class(ranges, negated), Some(actual)) => {
+                        let inside = ranges
+                            .iter()
+                            .any(|(low, high)| (low..=high).contains(&actual));
+                        inside != *negated
+                    }
+                    _ => false,
+                };
+                if matched {
+                    self.add(&mut next, &mut seen, pc + 1, position + 1, chars.len());
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        false
+    }
+
+    // follows jumps and zero-width assertions so the list only holds threads that consume
+    // a character or match; `seen` stamps each instruction with the position it was added at
+    fn add(&self, list: &mut Vec<usize>, seen: &mut [usize], pc: usize, at: usize, len: usize) {
+        if seen[pc] == at {
+            return;
+        }
+        seen[pc] = at;
+        match self.program[pc] {
+            Inst::Jump(target) => self.add(list, seen, target, at, len),
+            Inst::Split(first, second) => {
+                self.add(list, seen, first, at, len);
+                self.add(list, seen, second, at, len);
+            }
+            Inst::Start if at == 0 => self.add(list, seen, pc + 1, at, len),
+            Inst::End if at == len => self.add(list, seen, pc + 1, at, len),
+            Inst::Start | Inst::End => {}
+            _ => list.push(pc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn syntax() {
+        assert!(matches("(^|/)__snapshots__/", "src/__snapshots__/a.snap"));
+        assert!(matches("(^|/)__snapshots__/", "__snapshots__/"));
+        assert!(!matches("(^|/)__snapshots__/", "src/my__snapshots__/"));
+        assert!(matches(r"\.min\.js$", "web/app.min.js"));
+        assert!(!matches(r"\.min\.js$", "web/app.min.jsx"));
+        assert!(matches(r"^src/[^/]+\.rs$", "src/main.rs"));
+        assert!(!matches(r"^src/[^/]+\.rs$", "src/a/main.rs"));
+        assert!(matches(r"v\d{2,3}(?:-rc)?$", "v123-rc"));
+        assert!(!matches(r"^v\d{2,3}$", "v1234"));
+        assert!(matches("a(b|cd)*e", "xxacdbcde"));
+        assert!(matches("[]a]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("", "anything"));
+    }
+
+    #[test]
+    fn errors() {
+        for pattern in ["(a", "a)", "*a", "[a", "a{3,1}", r"\q", "(?=a)", "a\\"] {
+            assert!(Regex::new(pattern).is_err(), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn linear_time() {
+        // catastrophic for a backtracking matcher
+        let path = "a".repeat(64);
+        assert!(!matches("^(a|a)*(a|a)*b$", &path));
+    }
+}
@@ -0,0 +1,876 @@
+use crate::atomic;
+use crate::json::Json;
+use crate::stats::{self, LanguageTotals};
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// paths in reports are relative to the scanned root so they compare across machines
+pub fn relative<'a>(tree: &Entry, path: &'a Path) -> &'a Path {
+    relative_to(&tree.path, path)
+}
+
+pub fn relative_to<'a>(root: &Path, path: &'a Path) -> &'a Path {
+    match path.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => Path::new("."),
+        Ok(relative) => relative,
+        Err(_) => path,
+    }
+}
+
+// `--alias FROM=TO`, or `--trim-prefix FROM` with an empty `to`
+pub struct Rewrite {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+// the longest matching prefix wins, whichever flag it came from; a path that is all prefix
+// becomes `.` when trimmed
+pub fn rewrite(path: &Path, rewrites: &[Rewrite]) -> PathBuf {
+    let matched = (rewrites.iter())
+        .filter_map(|rewrite| Some((rewrite, path.strip_prefix(&rewrite.from).ok()?)))
+        .max_by_key(|(rewrite, _)| rewrite.from.components().count());
+    match matched {
+        Some((rewrite, rest))
+            if rewrite.to.as_os_str().is_empty() && rest.as_os_str().is_empty() =>
+        {
+            PathBuf::from(".")
+        }
+        Some((rewrite, rest)) if rest.as_os_str().is_empty() => rewrite.to.clone(),
+        Some((rewrite, rest)) => rewrite.to.join(rest),
+        None => path.to_path_buf(),
+    }
+}
+
+// every path in the tree, so paths relative to the root come out the same as before
+pub fn rewrite_paths(tree: &mut Entry, rewrites: &[Rewrite]) {
+    if rewrites.is_empty() {
+        return;
+    }
+    tree.path = rewrite(&tree.path, rewrites);
+    for child in &mut tree.children {
+        rewrite_paths(child, rewrites);
+    }
+}
+
+// ids in json reports: 64-bit FNV-1a over `d:` or `f:` and the root-relative path with `/`
+// separators, as 16 hex digits. it depends on nothing but the path, so the same entity keeps
+// its id across runs, machines and lc versions however the report happens to be ordered
+pub fn stable_id(kind: Kind, relative: &Path) -> String {
+    let components: Vec<_> = relative.iter().map(|part| part.to_string_lossy()).collect();
+    let path = components.join("/");
+    let prefix = ternary!(kind == Kind::Dir => "d:"; "f:");
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in prefix.bytes().chain(path.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+// every file and every directory in `tree`, each sorted by path, so a report lists them in
+// the same order whatever order the tree was assembled in and two runs diff cleanly
+fn by_path(tree: &Entry) -> (Vec<&Entry>, Vec<&Entry>) {
+    fn visit<'a>(entry: &'a Entry, files: &mut Vec<&'a Entry>, dirs: &mut Vec<&'a Entry>) {
+        match entry.kind {
+            Kind::Dir => {
+                dirs.push(entry);
+                for child in &entry.children {
+                    visit(child, files, dirs);
+                }
+            }
+            _ => files.push(entry),
+        }
+    }
+    let (mut files, mut dirs) = (Vec::new(), Vec::new());
+    visit(tree, &mut files, &mut dirs);
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    dirs.sort_by(|a, b| a.path.cmp(&b.path));
+    (files, dirs)
+}
+
+fn record(tree: &Entry, entry: &Entry) -> Json {
+    let relative = relative(tree, &entry.path);
+    let mut fields = vec![
+        ("id", Json::str(stable_id(entry.kind, relative))),
+        ("path", Json::str(relative.to_string_lossy())),
+        ("lines", Json::from(entry.lines)),
+        ("bytes", Json::from(entry.bytes)),
+    ];
+    if let Some(hash) = entry.hash {
+        fields.push(("hash", Json::str(hash_hex(hash))));
+    }
+    Json::object(fields)
+}
+
+// `--hash xxh3` values, spelled the way `xxhsum -H3` prints them
+pub fn hash_hex(hash: u64) -> String {
+    format!("{hash:016x}")
+}
+
+// stops the tree `max_depth` levels below its root, like `du --max-depth`: a directory at the
+// cutoff keeps its totals, and one aggregate row stands in for everything under it
+pub fn truncate(entry: &Entry, max_depth: usize) -> Entry {
+    if entry.kind != Kind::Dir {
+        return entry.clone();
+    }
+    let children = match max_depth {
+        0 if entry.children.is_empty() => Vec::new(),
+        0 => vec![Entry {
+            path: entry.path.join(format!(
+                "(+{} files, {} lines below)",
+                entry.files(),
+                entry.lines
+            )),
+            kind: Kind::File,
+            lines: entry.lines,
+            bytes: entry.bytes,
+            chars: entry.chars,
+            visual: entry.visual,
+            blank: entry.blank,
+            comment: entry.comment,
+            ..Entry::combine(Vec::new())
+        }],
+        _ => (entry.children.iter())
+            .map(|child| truncate(child, max_depth - 1))
+            .collect(),
+    };
+    Entry {
+        children,
+        ..entry.childless()
+    }
+}
+
+// keeps the `keep` largest files and folds the rest of each directory into one aggregate
+// row, so a report over a huge tree stays renderable; returns how many files were folded
+pub fn collapse(tree: &Entry, keep: usize) -> (Entry, usize) {
+    fn file_lines(entry: &Entry, lines: &mut Vec<u128>) {
+        match entry.kind {
+            Kind::Dir => entry
+                .children
+                .iter()
+                .for_each(|child| file_lines(child, lines)),
+            _ => lines.push(entry.lines),
+        }
+    }
+    fn fold(entry: &Entry, threshold: u128, folded: &mut usize) -> Entry {
+        let mut children = Vec::with_capacity(entry.children.len());
+        let mut small: Vec<&Entry> = Vec::new();
+        for child in &entry.children {
+            match child.kind {
+                Kind::Dir => children.push(fold(child, threshold, folded)),
+                _ if child.lines > threshold => children.push(child.clone()),
+                _ => small.push(child),
+            }
+        }
+        match small[..] {
+            [] => {}
+            [only] => children.push(only.clone()),
+            _ => {
+                *folded += small.len();
+                children.push(Entry {
+                    path: entry.path.join(format!("({} smaller files)", small.len())),
+                    kind: Kind::File,
+                    lines: small.iter().map(|file| file.lines).sum(),
+                    bytes: small.iter().map(|file| file.bytes).sum(),
+                    chars: small.iter().map(|file| file.chars).sum(),
+                    visual: small.iter().map(|file| file.visual).sum(),
+                    blank: small.iter().map(|file| file.blank).sum(),
+                    comment: small.iter().map(|file| file.comment).sum(),
+                    ..Entry::combine(Vec::new())
+                });
+            }
+        }
+        Entry {
+            children,
+            ..entry.childless()
+        }
+    }
+
+    let mut lines = Vec::new();
+    file_lines(tree, &mut lines);
+    if lines.len() <= keep {
+        return (tree.clone(), 0);
+    }
+    lines.sort_unstable_by(|a, b| b.cmp(a));
+    let mut folded = 0;
+    let collapsed = fold(tree, lines[keep], &mut folded);
+    (collapsed, folded)
+}
+
+// one line per file while the walk is still running; `root` is only set with several roots
+pub fn ndjson_file(root: &Path, entry: &Entry, multi_root: bool) -> Json {
+    let mut fields = vec![
+        ("type", Json::str("file")),
+        (
+            "path",
+            Json::str(relative_to(root, &entry.path).to_string_lossy()),
+        ),
+        ("lines", Json::from(entry.lines)),
+        ("bytes", Json::from(entry.bytes)),
+    ];
+    if let Some(hash) = entry.hash {
+        fields.push(("hash", Json::str(hash_hex(hash))));
+    }
+    if multi_root {
+        fields.insert(1, ("root", Json::str(root.to_string_lossy())));
+    }
+    Json::object(fields)
+}
+
+// the closing line of a stream, once every file has been sent
+pub fn ndjson_totals(tree: &Entry) -> Json {
+    Json::object(vec![
+        ("type", Json::str("totals")),
+        ("lines", Json::from(tree.lines)),
+        ("bytes", Json::from(tree.bytes)),
+        ("files", Json::from(tree.files())),
+        ("directories", Json::from(tree.directories())),
+    ])
+}
+
+// `sections` are optional extras (cache stats and the like) appended after the totals
+pub fn json(tree: &Entry, sections: Vec<(&str, Json)>) -> Json {
+    let (files, dirs) = by_path(tree);
+    let files: Vec<Json> = files.iter().map(|file| record(tree, file)).collect();
+    let dirs: Vec<Json> = dirs.iter().map(|dir| record(tree, dir)).collect();
+
+    let mut fields = vec![
+        ("root", Json::str(tree.path.to_string_lossy())),
+        (
+            "totals",
+            Json::object(vec![
+                ("lines", Json::from(tree.lines)),
+                ("bytes", Json::from(tree.bytes)),
+                ("files", Json::from(files.len())),
+                ("directories", Json::from(dirs.len())),
+            ]),
+        ),
+    ];
+    fields.extend(sections);
+    fields.push(("files", Json::Array(files)));
+    fields.push(("directories", Json::Array(dirs)));
+    Json::object(fields)
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+// github-flavored tables: top-level directories, extensions, then the overall totals
+pub fn markdown(tree: &Entry) -> String {
+    fn table(title: &str, column: &str, rows: &BTreeMap<String, (usize, u128, u64)>) -> String {
+        let mut table =
+            format!("## {title}\n\n| {column} | files | lines | bytes |\n|---|--:|--:|--:|\n");
+        for (name, (files, lines, bytes)) in rows {
+            table.push_str(&format!(
+                "| {} | {files} | {lines} | {bytes} |\n",
+                markdown_cell(name)
+            ));
+        }
+        table
+    }
+
+    fn visit(entry: &Entry, extensions: &mut BTreeMap<String, (usize, u128, u64)>) {
+        if entry.kind == Kind::Dir {
+            return entry
+                .children
+                .iter()
+                .for_each(|child| visit(child, extensions));
+        }
+        let extension = match entry.path.extension() {
+            Some(extension) => format!(".{}", extension.to_string_lossy()),
+            None => "(none)".to_string(),
+        };
+        let row = extensions.entry(extension).or_insert((0, 0, 0));
+        *row = (row.0 + 1, row.1 + entry.lines, row.2 + entry.bytes);
+    }
+
+    let (mut directories, mut extensions) = (BTreeMap::new(), BTreeMap::new());
+    for child in &tree.children {
+        let name = match child.kind {
+            Kind::Dir => format!("{}/", child.name()),
+            _ => ".".to_string(),
+        };
+        let row = directories.entry(name).or_insert((0, 0, 0));
+        *row = (
+            row.0 + child.files(),
+            row.1 + child.lines,
+            row.2 + child.bytes,
+        );
+    }
+    visit(tree, &mut extensions);
+
+    format!(
+        "# Line counts for `{}`\n\n{}\n{}\n**Total:** {} lines in {} files ({} bytes)\n",
+        tree.path.display(),
+        table("Directories", "directory", &directories),
+        table("Extensions", "extension", &extensions),
+        tree.lines,
+        tree.files(),
+        tree.bytes
+    )
+}
+
+pub fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+fn tsv_field(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+// one row per file for spreadsheets; `separator` is ',' for csv or '\t' for tsv
+pub fn table(tree: &Entry, separator: char) -> String {
+    let files = by_path(tree).0;
+    let hashed = files.iter().any(|entry| entry.hash.is_some());
+    let header = [
+        "path",
+        "extension",
+        "content_type",
+        "language",
+        "lines",
+        "bytes",
+        "hash",
+    ];
+    let columns = ternary!(hashed => header.len(); header.len() - 1);
+    let mut rows = header[..columns].join(&separator.to_string()) + "\n";
+    for entry in files {
+        let path = relative(tree, &entry.path).to_string_lossy();
+        let extension = entry
+            .path
+            .extension()
+            .map(|extension| extension.to_string_lossy())
+            .unwrap_or_default();
+        let content_type = entry.content_type().name();
+        let language = entry.language().unwrap_or("");
+        let fields = [&*path, &*extension, content_type, language];
+        let fields: Vec<String> = match separator {
+            ',' => fields.iter().map(|field| csv_field(field)).collect(),
+            _ => fields.iter().map(|field| tsv_field(field)).collect(),
+        };
+        rows.push_str(&format!(
+            "{}{separator}{}{separator}{}",
+            fields.join(&separator.to_string()),
+            entry.lines,
+            entry.bytes
+        ));
+        if hashed {
+            let hash = entry.hash.map(hash_hex).unwrap_or_default();
+            rows.push_str(&format!("{separator}{hash}"));
+        }
+        rows.push('\n');
+    }
+    rows
+}
+
+// `lines bytes path` per file and a total after more than one, in `wc -l -c`'s layout:
+// every count right-aligned to the width of the total bytes. paths under `base`, usually
+// the working directory, are shown relative to it, as wc shows what it was given. lines
+// are lc's, so a file without a final newline has one more than wc would say
+pub fn wc(tree: &Entry, base: &Path) -> String {
+    let files = by_path(tree).0;
+    let width = tree.bytes.to_string().len();
+    let mut rows = String::new();
+    for entry in &files {
+        let path = match entry.path.strip_prefix(base) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => &entry.path,
+        };
+        rows.push_str(&format!(
+            "{:>width$} {:>width$} {}\n",
+            entry.lines,
+            entry.bytes,
+            path.display()
+        ));
+    }
+    if files.len() > 1 {
+        rows.push_str(&format!(
+            "{:>width$} {:>width$} total\n",
+            tree.lines, tree.bytes
+        ));
+    }
+    rows
+}
+
+// lc's language names as cloc spells them; declared languages lc doesn't know pass through
+const CLOC_NAMES: &[(&str, &str)] = &[
+    ("rust", "Rust"),
+    ("python", "Python"),
+    ("javascript", "JavaScript"),
+    ("typescript", "TypeScript"),
+    ("go", "Go"),
+    ("c", "C"),
+    ("cpp", "C++"),
+    ("csharp", "C#"),
+    ("java", "Java"),
+    ("kotlin", "Kotlin"),
+    ("swift", "Swift"),
+    ("ruby", "Ruby"),
+    ("php", "PHP"),
+    ("shell", "Bourne Shell"),
+    ("lua", "Lua"),
+    ("haskell", "Haskell"),
+    ("sql", "SQL"),
+    ("html", "HTML"),
+    ("css", "CSS"),
+    ("markdown", "Markdown"),
+    ("toml", "TOML"),
+    ("yaml", "YAML"),
+    ("json", "JSON"),
+    ("xml", "XML"),
+    ("make", "make"),
+    ("dockerfile", "Dockerfile"),
+    ("cmake", "CMake"),
+];
+
+// cloc's classic table (or its `--csv` layout), so lc drops into scripts that parse cloc.
+// blank and comment need a walk with `Options::line_kinds`; files over `LARGE_FILE`, which
+// are streamed, count every line as code
+pub fn cloc(tree: &Entry, elapsed: Duration, csv: bool) -> String {
+    let mut languages: Vec<_> = stats::languages(tree)
+        .into_iter()
+        .map(
+            |(language, totals)| match CLOC_NAMES.iter().find(|(lc, _)| *lc == language) {
+                Some((_, cloc)) => (cloc.to_string(), totals),
+                None => (language, totals),
+            },
+        )
+        .collect();
+    let code = |totals: &LanguageTotals| totals.lines - totals.blank - totals.comment;
+    languages.sort_by(|a, b| code(&b.1).cmp(&code(&a.1)).then_with(|| a.0.cmp(&b.0)));
+    let sum = LanguageTotals {
+        files: languages.iter().map(|(_, totals)| totals.files).sum(),
+        lines: languages.iter().map(|(_, totals)| totals.lines).sum(),
+        blank: languages.iter().map(|(_, totals)| totals.blank).sum(),
+        comment: languages.iter().map(|(_, totals)| totals.comment).sum(),
+        bytes: 0,
+    };
+    let seconds = elapsed.as_secs_f64().max(0.001);
+    let banner = format!(
+        "lc v {}  T={:.2} s ({:.1} files/s, {:.1} lines/s)",
+        env!("CARGO_PKG_VERSION"),
+        elapsed.as_secs_f64(),
+        sum.files as f64 / seconds,
+        sum.lines as f64 / seconds
+    );
+
+    if csv {
+        let mut rows = format!("files,language,blank,comment,code,\"{banner}\"\n");
+        let row = |name: &str, totals: &LanguageTotals| {
+            let (files, blank, comment) = (totals.files, totals.blank, totals.comment);
+            format!("{files},{name},{blank},{comment},{}\n", code(totals))
+        };
+        for (language, totals) in &languages {
+            rows.push_str(&row(&csv_field(language), totals));
+        }
+        rows.push_str(&row("SUM", &sum));
+        return rows;
+    }
+    let rule = "-".repeat(79);
+    let row = |name: &str, totals: &LanguageTotals| {
+        let (files, blank, comment) = (totals.files, totals.blank, totals.comment);
+        format!(
+            "{name:<27} {files:>6} {blank:>14} {comment:>14} {:>14}\n",
+            code(totals)
+        )
+    };
+    let mut table = format!(
+        "{banner}\n{rule}\n{:<27} {:>6} {:>14} {:>14} {:>14}\n{rule}\n",
+        "Language", "files", "blank", "comment", "code"
+    );
+    for (language, totals) in &languages {
+        table.push_str(&row(language, totals));
+    }
+    table.push_str(&format!("{rule}\n{}{rule}\n", row("SUM:", &sum)));
+    table
+}
+
+// one totals record per run, so scheduled scans build up a time series
+pub fn append_totals(path: &Path, tree: &Entry, timestamp: &str) -> io::Result<()> {
+    let root = tree.path.to_string_lossy();
+    let files = tree.files();
+    let record = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => {
+            let fresh = path
+                .metadata()
+                .map(|metadata| metadata.len() == 0)
+                .unwrap_or(true);
+            let header = ternary!(fresh => "timestamp,root,files,lines,bytes\n"; "");
+            format!(
+                "{header}{timestamp},{},{files},{},{}\n",
+                csv_field(&root),
+                tree.lines,
+                tree.bytes
+            )
+        }
+        Some("jsonl") | Some("ndjson") => {
+            let record = Json::object(vec![
+                ("timestamp", Json::str(timestamp)),
+                ("root", Json::str(root)),
+                ("files", Json::from(files)),
+                ("lines", Json::from(tree.lines)),
+                ("bytes", Json::from(tree.bytes)),
+            ]);
+            format!("{record}\n")
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: --append needs a .csv or .jsonl file", path.display()),
+            ))
+        }
+    };
+
+    atomic::append(path, record.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+
+    #[test]
+    fn append_series() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r,1/a.rs", "1\n2\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r,1"))?;
+
+        let dir = std::env::temp_dir().join(format!("lc-test-{}-append", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let (csv, jsonl) = (dir.join("series.csv"), dir.join("series.jsonl"));
+        let _ = std::fs::remove_file(&csv);
+        let _ = std::fs::remove_file(&jsonl);
+
+        append_totals(&csv, &tree, "2024-01-01T00:00:00Z")?;
+        append_totals(&csv, &tree, "2024-01-02T00:00:00Z")?;
+        append_totals(&jsonl, &tree, "2024-01-01T00:00:00Z")?;
+        assert_eq!(
+            std::fs::read_to_string(&csv)?,
+            "timestamp,root,files,lines,bytes\n\
+             2024-01-01T00:00:00Z,\"r,1\",1,2,4\n\
+             2024-01-02T00:00:00Z,\"r,1\",1,2,4\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&jsonl)?,
+            "{\"timestamp\":\"2024-01-01T00:00:00Z\",\"root\":\"r,1\",\"files\":1,\"lines\":2,\"bytes\":4}\n"
+        );
+        assert!(append_totals(&dir.join("series.txt"), &tree, "").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn per_file_table() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n2\n");
+        memory.insert("r/sub/b,\tc.txt", "1\n");
+        memory.insert("r/sub/Makefile", "");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        assert_eq!(
+            table(&tree, ','),
+            "path,extension,content_type,language,lines,bytes\n\
+             a.rs,rs,code,rust,2,4\n\
+             sub/Makefile,,code,make,0,0\n\
+             \"sub/b,\tc.txt\",txt,text,,1,2\n"
+        );
+        assert_eq!(
+            table(&tree, '\t').lines().last(),
+            Some("sub/b,\\tc.txt\ttxt\ttext\t\t1\t2")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wc_layout() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        memory.insert("r/sub/b.txt", "x".repeat(500));
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        // what `wc -l -c r/a.rs r/sub/b.txt` prints from inside r's parent
+        assert_eq!(
+            wc(&tree, Path::new("")),
+            "  1   2 r/a.rs\n  1 500 r/sub/b.txt\n  2 502 total\n"
+        );
+        let file = &tree.children[0];
+        assert_eq!(wc(file, Path::new("r")), "1 2 a.rs\n");
+        Ok(())
+    }
+
+    #[test]
+    fn hashed_table() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n2\n");
+        memory.insert("r/logo.png", "xyz");
+        let options = Options {
+            hashes: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("r"))?;
+        // the same digests as `xxhsum -H3`, the png's included though its lines aren't read
+        assert_eq!(
+            table(&tree, ','),
+            "path,extension,content_type,language,lines,bytes,hash\n\
+             a.rs,rs,code,rust,2,4,9dc5cef91ba42b84\n\
+             logo.png,png,media,,0,3,6276d2656f411f1f\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ndjson_lines() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/sub/b.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let file = &tree.children[0].children[0];
+
+        assert_eq!(
+            ndjson_file(&tree.path, file, false).to_string(),
+            r#"{"type":"file","path":"sub/b.rs","lines":1,"bytes":2}"#
+        );
+        assert_eq!(
+            ndjson_file(&tree.path, file, true).to_string(),
+            r#"{"type":"file","root":"r","path":"sub/b.rs","lines":1,"bytes":2}"#
+        );
+        assert_eq!(
+            ndjson_totals(&tree).to_string(),
+            r#"{"type":"totals","lines":1,"bytes":2,"files":1,"directories":2}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn markdown_tables() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/build.rs", "1\n");
+        memory.insert("r/src/a.rs", "1\n2\n");
+        memory.insert("r/src/a|b.md", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        assert_eq!(
+            markdown(&tree),
+            "# Line counts for `r`\n\n\
+             ## Directories\n\n\
+             | directory | files | lines | bytes |\n|---|--:|--:|--:|\n\
+             | . | 1 | 1 | 2 |\n\
+             | src/ | 2 | 3 | 6 |\n\n\
+             ## Extensions\n\n\
+             | extension | files | lines | bytes |\n|---|--:|--:|--:|\n\
+             | .md | 1 | 1 | 2 |\n\
+             | .rs | 2 | 3 | 6 |\n\n\
+             **Total:** 4 lines in 3 files (8 bytes)\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn collapsed_report() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/big.rs", "1\n2\n3\n4\n");
+        memory.insert("r/sub/mid.rs", "1\n2\n3\n");
+        memory.insert("r/sub/a.rs", "1\n");
+        memory.insert("r/sub/b.rs", "1\n");
+        memory.insert("r/sub/c.rs", "");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        let (collapsed, folded) = collapse(&tree, 2);
+        assert_eq!(folded, 3);
+        assert_eq!((collapsed.lines, collapsed.bytes), (tree.lines, tree.bytes));
+        let names: Vec<&str> = collapsed.children[1]
+            .children
+            .iter()
+            .map(Entry::name)
+            .collect();
+        assert_eq!(names, ["mid.rs", "(3 smaller files)"]);
+        assert_eq!(collapsed.children[1].children[1].lines, 2);
+        assert_eq!(collapse(&tree, 5).1, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn json_report() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n2\n");
+        memory.insert("r/sub/b.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        let report = json(&tree, vec![("extra", Json::Null)]).to_string();
+        assert!(report.starts_with(
+            r#"{"root":"r","totals":{"lines":3,"bytes":6,"files":2,"directories":2},"extra":null,"#
+        ));
+        let b = stable_id(Kind::File, Path::new("sub/b.rs"));
+        assert!(report.contains(&format!(
+            r#"{{"id":"{b}","path":"sub/b.rs","lines":1,"bytes":2}}"#
+        )));
+        assert!(report.contains(r#"","path":".","lines":3,"bytes":6}"#));
+        Ok(())
+    }
+
+    #[test]
+    fn ordered_by_path() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/z.rs", "1\n");
+        memory.insert("r/a/b.rs", "1\n");
+        memory.insert("r/a/c/d.rs", "1\n");
+        memory.insert("r/a.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        // the order a walk spread over threads might hand its entries back in
+        fn shuffle(entry: &mut Entry) {
+            entry.children.reverse();
+            entry.children.iter_mut().for_each(shuffle);
+        }
+        let mut shuffled = tree.clone();
+        shuffle(&mut shuffled);
+
+        assert_eq!(table(&tree, ','), table(&shuffled, ','));
+        assert_eq!(json(&tree, Vec::new()), json(&shuffled, Vec::new()));
+        let paths: Vec<String> = by_path(&shuffled)
+            .0
+            .iter()
+            .map(|file| relative(&tree, &file.path).display().to_string())
+            .collect();
+        assert_eq!(paths, ["a/b.rs", "a/c/d.rs", "a.rs", "z.rs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn truncated() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        memory.insert("r/src/b.rs", "1\n2\n");
+        memory.insert("r/src/deep/c.rs", "1\n2\n3\n");
+        memory.insert("r/src/deep/d.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        let cut = truncate(&tree, 2);
+        assert_eq!(cut.lines, tree.lines);
+        let deep = &cut.children[1].children[1];
+        let names: Vec<&str> = deep.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["(+2 files, 4 lines below)"]);
+        assert_eq!((deep.lines, deep.children[0].lines), (4, 4));
+        let root = truncate(&tree, 0);
+        assert_eq!(root.children[0].name(), "(+4 files, 7 lines below)");
+        assert_eq!(truncate(&tree, 3).files(), tree.files());
+        Ok(())
+    }
+
+    #[test]
+    fn rewritten() -> io::Result<()> {
+        let rewrites = [
+            Rewrite {
+                from: PathBuf::from("/home/ci/builds/workspace/"),
+                to: PathBuf::new(),
+            },
+            Rewrite {
+                from: PathBuf::from("/home/ci/builds/workspace/vendor"),
+                to: PathBuf::from("$VENDOR"),
+            },
+        ];
+        let at = |path: &str| rewrite(Path::new(path), &rewrites);
+        assert_eq!(at("/home/ci/builds/workspace/lc/src"), Path::new("lc/src"));
+        assert_eq!(at("/home/ci/builds/workspace"), Path::new("."));
+        assert_eq!(
+            at("/home/ci/builds/workspace/vendor/x"),
+            Path::new("$VENDOR/x")
+        );
+        assert_eq!(
+            at("/home/ci/builds/workspaces"),
+            Path::new("/home/ci/builds/workspaces")
+        );
+
+        let mut memory = MemoryFs::new();
+        memory.insert("/ws/r/src/a.rs", "1\n");
+        let mut tree = Walker::new(&memory, Options::default()).walk(Path::new("/ws/r"))?;
+        let before = ndjson_file(&tree.path, &tree.children[0].children[0], false);
+        let alias = Rewrite {
+            from: PathBuf::from("/ws"),
+            to: PathBuf::from("~"),
+        };
+        rewrite_paths(&mut tree, &[alias]);
+        assert_eq!(tree.path, Path::new("~/r"));
+        let file = &tree.children[0].children[0];
+        assert_eq!(ndjson_file(&tree.path, file, false), before);
+        Ok(())
+    }
+
+    #[test]
+    fn cloc_layouts() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert(
+            "r/a.rs",
+            "// one\n\nfn a() {} /* trailing */\n/*\n  block\n*/\nfn b() {}\nfn c() {}\n",
+        );
+        memory.insert("r/b.py", "1\n");
+        memory.insert("r/notes", "1\n");
+        let options = Options {
+            line_kinds: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("r"))?;
+
+        let table = cloc(&tree, Duration::from_millis(10), false);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("lc v "));
+        assert_eq!(
+            lines[2],
+            format!(
+                "{:<29}files{:>15}{:>15}{:>15}",
+                "Language", "blank", "comment", "code"
+            )
+        );
+        assert_eq!(
+            lines[4],
+            format!("Rust{:>30}{:>15}{:>15}{:>15}", 1, 1, 4, 3)
+        );
+        assert_eq!(
+            lines[5],
+            format!("Python{:>28}{:>15}{:>15}{:>15}", 1, 0, 0, 1)
+        );
+        assert_eq!(
+            lines[7],
+            format!("SUM:{:>30}{:>15}{:>15}{:>15}", 2, 1, 4, 4)
+        );
+        assert!(lines
+            .iter()
+            .all(|line| line.len() == 79 || line.starts_with("lc v")));
+
+        let csv = cloc(&tree, Duration::from_millis(10), true);
+        let rows: Vec<&str> = csv.lines().skip(1).collect();
+        assert!(csv.starts_with("files,language,blank,comment,code,\"lc v "));
+        assert_eq!(rows, ["1,Rust,1,4,3", "1,Python,0,0,1", "2,SUM,1,4,4"]);
+        Ok(())
+    }
+
+    #[test]
+    fn stable_ids() {
+        // pinned, so a change to the scheme shows up here before it breaks someone's diffing
+        assert_eq!(stable_id(Kind::File, Path::new("a.rs")), "949a209df5a00e89");
+        assert_ne!(
+            stable_id(Kind::File, Path::new("src")),
+            stable_id(Kind::Dir, Path::new("src"))
+        );
+
+        let mut seen = std::collections::HashSet::new();
+        for dir in 0..300 {
+            for file in 0..300 {
+                let path = format!("dir{dir}/file{file}.rs");
+                assert!(
+                    seen.insert(stable_id(Kind::File, Path::new(&path))),
+                    "{path}"
+                );
+            }
+            assert!(seen.insert(stable_id(Kind::Dir, Path::new(&format!("dir{dir}")))));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+// policy warnings printed after the summary: `<expr>: <message>` per line, in `.lcrules` at
+// the scanned root or the file given to `--rules`. The expression is an --assert one, e.g.
+// `types.media.bytes > 0.3 * bytes: media is over 30% of the repo`
+use std::io;
+
+pub const FILE: &str = ".lcrules";
+
+#[derive(Debug, PartialEq)]
+pub struct Rule {
+    pub line: usize,
+    pub condition: String,
+    pub message: String,
+}
+
+pub fn parse_rules(contents: &str) -> io::Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(':') {
+            Some((condition, message)) if !condition.trim().is_empty() => rules.push(Rule {
+                line: number + 1,
+                condition: condition.trim().to_string(),
+                message: message.trim().to_string(),
+            }),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("rules line {}: expected `<expr>: <message>`", number + 1),
+                ))
+            }
+        }
+    }
+    Ok(rules)
+}
+
+// the messages of the rules that hold, in file order; a rule that cannot be evaluated is
+// an error naming its line
+pub fn triggered<'a, F: Fn(&str) -> Option<f64>>(
+    rules: &'a [Rule],
+    lookup: &F,
+) -> Result<Vec<&'a str>, String> {
+    let mut messages = Vec::new();
+    for rule in rules {
+        match crate::expr::evaluate(&rule.condition, lookup) {
+            Ok(value) if value != 0.0 => messages.push(rule.message.as_str()),
+            Ok(_) => {}
+            Err(err) => return Err(format!("rules line {}: {err}", rule.line)),
+        }
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings() -> io::Result<()> {
+        let rules = parse_rules(
+            "# policy\n\
+             types.media.bytes > 0.3 * bytes: media bytes exceed 30% of repo\n\
+             \n\
+             types.executable.files > 0: executables present in source tree\n",
+        )?;
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].line, 4);
+
+        let lookup = |name: &str| match name {
+            "bytes" => Some(100.0),
+            "types.media.bytes" => Some(40.0),
+            "types.executable.files" => Some(0.0),
+            _ => None,
+        };
+        assert_eq!(
+            triggered(&rules, &lookup),
+            Ok(vec!["media bytes exceed 30% of repo"])
+        );
+
+        let unknown = parse_rules("types.video.bytes > 0: videos")?;
+        assert!(triggered(&unknown, &lookup)
+            .unwrap_err()
+            .starts_with("rules line 1:"));
+        assert!(parse_rules("no message here").is_err());
+        assert!(parse_rules(": no condition").is_err());
+        Ok(())
+    }
+}
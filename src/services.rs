@@ -0,0 +1,123 @@
+use crate::report::relative;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const UNASSIGNED: &str = "(unassigned)";
+
+// `<service> <path prefix>` per line, prefixes relative to the scanned root
+pub fn parse_services(contents: &str) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut services = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(char::is_whitespace) {
+            Some((name, prefix)) => {
+                let prefix = prefix.trim().trim_start_matches("./").trim_end_matches('/');
+                services.push((name.to_string(), PathBuf::from(prefix)));
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "services file line {}: expected `<name> <path>`",
+                        number + 1
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(services)
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ServiceTotals {
+    pub name: String,
+    pub files: usize,
+    pub lines: u128,
+    pub bytes: u64,
+}
+
+// each file goes to the service with the longest matching prefix
+pub fn totals(tree: &Entry, services: &[(String, PathBuf)]) -> Vec<ServiceTotals> {
+    let mut totals: Vec<ServiceTotals> = services
+        .iter()
+        .map(|(name, _)| name.clone())
+        .chain([UNASSIGNED.to_string()])
+        .fold(Vec::new(), |mut totals: Vec<ServiceTotals>, name| {
+            if !totals.iter().any(|total| total.name == name) {
+                totals.push(ServiceTotals {
+                    name,
+                    ..ServiceTotals::default()
+                });
+            }
+            totals
+        });
+    visit(tree, tree, services, &mut totals);
+    totals.retain(|total| total.name != UNASSIGNED || total.files > 0);
+    totals
+}
+
+fn visit(
+    tree: &Entry,
+    entry: &Entry,
+    services: &[(String, PathBuf)],
+    totals: &mut [ServiceTotals],
+) {
+    if entry.kind == Kind::Dir {
+        for child in &entry.children {
+            visit(tree, child, services, totals);
+        }
+        return;
+    }
+    let path = relative(tree, &entry.path);
+    let name = services
+        .iter()
+        .filter(|(_, prefix)| path.starts_with(prefix) || prefix == Path::new(""))
+        .max_by_key(|(_, prefix)| prefix.components().count())
+        .map(|(name, _)| name.as_str())
+        .unwrap_or(UNASSIGNED);
+    let total = totals.iter_mut().find(|total| total.name == name).unwrap();
+    total.files += 1;
+    total.lines += entry.lines;
+    total.bytes += entry.bytes;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+
+    #[test]
+    fn per_service() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/services/api/main.go", "1\n2\n");
+        memory.insert("r/services/api/admin/panel.go", "1\n");
+        memory.insert("r/services/web/app.ts", "1\n2\n3\n");
+        memory.insert("r/README.md", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        let services = parse_services(
+            "# registry\napi services/api/\nadmin ./services/api/admin\nweb services/web\n",
+        )?;
+        let lines: Vec<(String, u128)> = totals(&tree, &services)
+            .into_iter()
+            .map(|total| (total.name, total.lines))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ("api".to_string(), 2),
+                ("admin".to_string(), 1),
+                ("web".to_string(), 3),
+                (UNASSIGNED.to_string(), 1)
+            ]
+        );
+        assert!(parse_services("lonely").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,462 @@
+use crate::encoding::Encoding;
+use crate::gitignore;
+use crate::goal;
+use crate::lang::{is_binary, ContentType};
+use crate::report::relative;
+use crate::vfs::Kind;
+use crate::walk::Entry;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// windows MAX_PATH, the limit most tooling still trips over
+pub const DEFAULT_PATH_LIMIT: usize = 260;
+
+#[derive(Debug, Default)]
+pub struct PathLengths {
+    pub deepest: usize,
+    pub deepest_path: PathBuf,
+    pub longest: usize,
+    pub longest_path: PathBuf,
+    pub over_limit: Vec<(PathBuf, usize)>,
+}
+
+impl PathLengths {
+    pub fn collect(tree: &Entry, limit: usize) -> PathLengths {
+        let mut lengths = PathLengths::default();
+        lengths.visit(tree, 0, limit);
+        lengths.over_limit.sort();
+        lengths
+    }
+
+    fn visit(&mut self, entry: &Entry, depth: usize, limit: usize) {
+        let length = entry.path.as_os_str().len();
+        if depth > self.deepest {
+            self.deepest = depth;
+            self.deepest_path = entry.path.clone();
+        }
+        if length > self.longest {
+            self.longest = length;
+            self.longest_path = entry.path.clone();
+        }
+        if length > limit {
+            self.over_limit.push((entry.path.clone(), length));
+        }
+        for child in &entry.children {
+            self.visit(child, depth + 1, limit);
+        }
+    }
+}
+
+// above this a file is effectively incompressible: binary, encrypted or already compressed
+pub const RANDOM_ENTROPY: f64 = 7.5;
+// below this the content is mostly repeats, which is what generators produce
+pub const REPETITIVE_ENTROPY: f64 = 1.5;
+
+// order-0 byte entropy scaled by the share of bytes on lines not seen before,
+// a cheap stand-in for what a dictionary compressor would squeeze out
+pub fn entropy(content: &[u8]) -> f64 {
+    if content.is_empty() {
+        return 0.0;
+    }
+    let mut frequencies = [0usize; 256];
+    for byte in content {
+        frequencies[*byte as usize] += 1;
+    }
+    let total = content.len() as f64;
+    let shannon: f64 = frequencies
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+
+    let mut seen = HashSet::new();
+    let unique: usize = content
+        .split(|byte| *byte == b'\n')
+        .filter(|line| seen.insert(*line))
+        .map(|line| line.len() + 1)
+        .sum();
+    shannon * (unique.min(content.len()) as f64 / total)
+}
+
+#[derive(Debug, Default)]
+pub struct EntropyReport {
+    pub files: usize,
+    pub average: f64,
+    pub random: Vec<(PathBuf, f64)>,
+    pub repetitive: Vec<(PathBuf, f64)>,
+}
+
+impl EntropyReport {
+    pub fn collect(tree: &Entry) -> EntropyReport {
+        let mut report = EntropyReport::default();
+        let mut sum = 0.0;
+        report.visit(tree, &mut sum);
+        if report.files > 0 {
+            report.average = sum / report.files as f64;
+        }
+        report
+    }
+
+    fn visit(&mut self, entry: &Entry, sum: &mut f64) {
+        if let (Kind::File, Some(entropy)) = (entry.kind, entry.entropy) {
+            self.files += 1;
+            *sum += entropy;
+            if entropy >= RANDOM_ENTROPY {
+                self.random.push((entry.path.clone(), entropy));
+            } else if entropy <= REPETITIVE_ENTROPY && entry.bytes > 0 {
+                self.repetitive.push((entry.path.clone(), entropy));
+            }
+        }
+        for child in &entry.children {
+            self.visit(child, sum);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EncodingTotals {
+    pub files: usize,
+    pub lines: u128,
+    pub chars: u128,
+    pub bytes: u64,
+}
+
+pub fn encodings(tree: &Entry) -> BTreeMap<Encoding, EncodingTotals> {
+    fn visit(entry: &Entry, breakdown: &mut BTreeMap<Encoding, EncodingTotals>) {
+        if let Some(encoding) = entry.encoding {
+            let totals = breakdown.entry(encoding).or_default();
+            totals.files += 1;
+            totals.lines += entry.lines;
+            totals.chars += entry.chars;
+            totals.bytes += entry.bytes;
+        }
+        for child in &entry.children {
+            visit(child, breakdown);
+        }
+    }
+
+    let mut breakdown = BTreeMap::new();
+    visit(tree, &mut breakdown);
+    breakdown
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LanguageTotals {
+    pub files: usize,
+    pub lines: u128,
+    pub bytes: u64,
+    // filled in by `languages` alone
+    pub blank: u128,
+    pub comment: u128,
+}
+
+pub fn languages(tree: &Entry) -> BTreeMap<String, LanguageTotals> {
+    fn visit(entry: &Entry, breakdown: &mut BTreeMap<String, LanguageTotals>) {
+        if entry.kind == Kind::File {
+            if let Some(language) = entry.language() {
+                let totals = breakdown.entry(language.to_string()).or_default();
+                totals.files += 1;
+                totals.lines += entry.lines;
+                totals.bytes += entry.bytes;
+                totals.blank += entry.blank;
+                totals.comment += entry.comment;
+            }
+        }
+        for child in &entry.children {
+            visit(child, breakdown);
+        }
+    }
+
+    let mut breakdown = BTreeMap::new();
+    visit(tree, &mut breakdown);
+    breakdown
+}
+
+// the same totals by content type, for `types.media.bytes` in --assert and .lcrules
+pub fn content_types(tree: &Entry) -> BTreeMap<ContentType, LanguageTotals> {
+    fn visit(entry: &Entry, breakdown: &mut BTreeMap<ContentType, LanguageTotals>) {
+        if entry.kind == Kind::File {
+            let totals = breakdown.entry(entry.content_type()).or_default();
+            totals.files += 1;
+            totals.lines += entry.lines;
+            totals.bytes += entry.bytes;
+        }
+        for child in &entry.children {
+            visit(child, breakdown);
+        }
+    }
+
+    let mut breakdown = BTreeMap::new();
+    visit(tree, &mut breakdown);
+    breakdown
+}
+
+// files under `gitignore::VENDORED_DIRS`, the third-party share of the totals
+pub fn vendored(tree: &Entry) -> LanguageTotals {
+    fn visit(root: &Path, entry: &Entry, totals: &mut LanguageTotals) {
+        if entry.kind == Kind::File && gitignore::is_vendored(root, &entry.path) {
+            totals.files += 1;
+            totals.lines += entry.lines;
+            totals.bytes += entry.bytes;
+        }
+        for child in &entry.children {
+            visit(root, child, totals);
+        }
+    }
+
+    let mut totals = LanguageTotals::default();
+    visit(&tree.path, tree, &mut totals);
+    totals
+}
+
+// test code by `goal::in_bucket`, to set against the rest
+pub fn tests(tree: &Entry) -> LanguageTotals {
+    fn visit(root: &Entry, entry: &Entry, totals: &mut LanguageTotals) {
+        if entry.kind == Kind::File && goal::in_bucket("tests", relative(root, &entry.path)) {
+            totals.files += 1;
+            totals.lines += entry.lines;
+            totals.bytes += entry.bytes;
+        }
+        for child in &entry.children {
+            visit(root, child, totals);
+        }
+    }
+
+    let mut totals = LanguageTotals::default();
+    visit(tree, tree, &mut totals);
+    totals
+}
+
+// what a line of text averages when there is nothing better to go by
+pub const BYTES_PER_LINE: f64 = 32.0;
+
+// the lines a tree sized from metadata alone probably holds: its files' bytes at
+// `bytes_per_line`, leaving out binaries by extension
+pub fn estimated_lines(tree: &Entry, bytes_per_line: f64) -> u128 {
+    match tree.kind {
+        Kind::Dir => (tree.children.iter())
+            .map(|child| estimated_lines(child, bytes_per_line))
+            .sum(),
+        _ if is_binary(&tree.path) => 0,
+        _ => (tree.bytes as f64 / bytes_per_line).ceil() as u128,
+    }
+}
+
+// minified files and their bytes
+pub fn minified(tree: &Entry) -> (usize, u64) {
+    match tree.kind {
+        Kind::Dir => (tree.children.iter().map(minified))
+            .fold((0, 0), |(files, bytes), (more, size)| {
+                (files + more, bytes + size)
+            }),
+        _ if tree.content_type() == ContentType::Minified => (1, tree.bytes),
+        _ => (0, 0),
+    }
+}
+
+// lines by how deep their file sits below the root; index 0 is depth 1, the root's own files
+pub fn depth_profile(tree: &Entry) -> Vec<u128> {
+    fn visit(entry: &Entry, depth: usize, profile: &mut Vec<u128>) {
+        if entry.kind != Kind::Dir {
+            if profile.len() <= depth {
+                profile.resize(depth + 1, 0);
+            }
+            profile[depth] += entry.lines;
+            return;
+        }
+        for child in &entry.children {
+            visit(child, depth + 1, profile);
+        }
+    }
+
+    let mut profile = Vec::new();
+    match tree.kind {
+        Kind::Dir => tree
+            .children
+            .iter()
+            .for_each(|child| visit(child, 0, &mut profile)),
+        _ => visit(tree, 0, &mut profile),
+    }
+    profile
+}
+
+// one block per value, scaled to the largest; anything above zero gets at least the lowest.
+// `ascii` draws with plain characters, for logs that mangle anything else
+pub fn sparkline(values: &[u128], ascii: bool) -> String {
+    let blocks = match ascii {
+        true => ['_', '.', '-', ':', '=', '+', '*', '#'],
+        false => ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'],
+    };
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|&value| match value {
+            0 => ' ',
+            _ => blocks[((value * 7).div_ceil(max)) as usize],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use crate::walk::{Options, Walker};
+    use std::path::Path;
+
+    #[test]
+    fn entropy_extremes() {
+        let mut state = 0x2545f491u32;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let repeated = "let x = 1;\n".repeat(200);
+        let code = "fn main() {\n    let answer = 42;\n    println!(\"{answer}\");\n}\n";
+
+        assert_eq!(entropy(b""), 0.0);
+        assert!(entropy(&noise) >= RANDOM_ENTROPY);
+        assert!(entropy(repeated.as_bytes()) <= REPETITIVE_ENTROPY);
+        let handwritten = entropy(code.as_bytes());
+        assert!(handwritten > REPETITIVE_ENTROPY && handwritten < RANDOM_ENTROPY);
+    }
+
+    #[test]
+    fn depths() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        memory.insert("r/src/b.rs", "1\n2\n3\n4\n");
+        memory.insert("r/src/deep/er/c.rs", "1\n2\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        assert_eq!(depth_profile(&tree), [1, 4, 0, 2]);
+        assert_eq!(sparkline(&depth_profile(&tree), false), "▃█ ▅");
+        assert_eq!(sparkline(&depth_profile(&tree), true), "-# =");
+        assert_eq!(sparkline(&[], false), "");
+        Ok(())
+    }
+
+    #[test]
+    fn minified_assets() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/src/app.js", "let a = 1;\n");
+        memory.insert("r/dist/app.min.js", "let a=1;\n");
+        memory.insert("r/dist/style.css", format!("{}\n", "a{b:c}".repeat(200)));
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        assert_eq!(minified(&tree), (2, 9 + 1201));
+        Ok(())
+    }
+
+    #[test]
+    fn content_type_totals() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/main.rs", "1\n2\n");
+        memory.insert("r/lib.rs", "1\n");
+        memory.insert("r/logo.png", "xyz");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let breakdown = content_types(&tree);
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(breakdown[&ContentType::Code].files, 2);
+        assert_eq!(breakdown[&ContentType::Code].bytes, 6);
+        assert_eq!(breakdown[&ContentType::Media].bytes, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn vendored_totals() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/src/main.rs", "1\n2\n");
+        memory.insert("r/third_party/zlib/inflate.c", "1\n2\n3\n");
+        memory.insert("r/extern/json.hpp", "1\n");
+        memory.insert("r/src/extern.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let totals = vendored(&tree);
+        assert_eq!((totals.files, totals.lines), (2, 4));
+
+        let options = Options {
+            no_vendored: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("r"))?;
+        assert_eq!((tree.files(), tree.lines), (2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_totals() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/src/app.ts", "1\n2\n3\n");
+        memory.insert("r/src/app.spec.ts", "1\n2\n");
+        memory.insert("r/spec/helper.rb", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let totals = tests(&tree);
+        assert_eq!((totals.files, totals.lines), (2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn estimate() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "x".repeat(64));
+        memory.insert("r/b.txt", "x".repeat(10));
+        memory.insert("r/logo.png", "x".repeat(1000));
+        let options = Options {
+            skip_larger_than: Some(0),
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("r"))?;
+        assert_eq!(tree.lines, 0);
+        assert_eq!(estimated_lines(&tree, BYTES_PER_LINE), 2 + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn encoding_breakdown() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.txt", "a\nb\n");
+        memory.insert("r/b.txt", b"caf\xe9\n".to_vec());
+        memory.insert("r/c.txt", b"\xff\xfeh\x00\n\x00".to_vec());
+        let options = Options {
+            encodings: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("r"))?;
+
+        let breakdown = encodings(&tree);
+        let totals = |files, lines, chars, bytes| EncodingTotals {
+            files,
+            lines,
+            chars,
+            bytes,
+        };
+        assert_eq!(breakdown[&Encoding::Utf8], totals(1, 2, 4, 4));
+        assert_eq!(breakdown[&Encoding::Latin1], totals(1, 1, 5, 5));
+        assert_eq!(breakdown[&Encoding::Utf16Le], totals(1, 1, 2, 6));
+        assert_eq!(tree.lines, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn path_lengths() -> std::io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a/b/c.rs", "");
+        memory.insert("r/a/longer-name.rs", "");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+
+        let lengths = PathLengths::collect(&tree, 12);
+        assert_eq!(lengths.deepest, 3);
+        assert_eq!(lengths.deepest_path, Path::new("r/a/b/c.rs"));
+        assert_eq!(lengths.longest_path, Path::new("r/a/longer-name.rs"));
+        assert_eq!(
+            lengths.over_limit,
+            [(PathBuf::from("r/a/longer-name.rs"), 18)]
+        );
+        Ok(())
+    }
+}
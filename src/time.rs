@@ -0,0 +1,445 @@
+use std::env;
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+
+// days since 1970-01-01 to (year, month, day), Howard Hinnant's civil_from_days
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// the inverse, from the same paper
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    }
+}
+
+// wall-clock fields at `offset` seconds east of UTC
+fn stamp(time: SystemTime, offset: i64, separator: char) -> String {
+    let seconds = unix_seconds(time) + offset;
+    let (days, rest) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{year:04}-{month:02}-{day:02}{separator}{:02}:{:02}:{:02}",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+fn offset_suffix(offset: i64) -> String {
+    let sign = ternary!(offset < 0 => '-'; '+');
+    let minutes = offset.abs() / 60;
+    format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+pub fn iso8601_utc(time: SystemTime) -> String {
+    format!("{}Z", stamp(time, 0, 'T'))
+}
+
+pub fn iso8601_local(time: SystemTime) -> String {
+    let offset = local_offset(time);
+    format!("{}{}", stamp(time, offset, 'T'), offset_suffix(offset))
+}
+
+// for people: `2024-02-29 13:34:56 +01:00`, or `2024-02-29 12:34:56 UTC`
+pub fn readable(time: SystemTime, utc: bool) -> String {
+    match utc {
+        true => format!("{} UTC", stamp(time, 0, ' ')),
+        false => {
+            let offset = local_offset(time);
+            format!("{} {}", stamp(time, offset, ' '), offset_suffix(offset))
+        }
+    }
+}
+
+// seconds east of UTC in force at `time`, going by $TZ like libc does, then
+// /etc/localtime; UTC when neither says otherwise
+pub fn local_offset(time: SystemTime) -> i64 {
+    let seconds = unix_seconds(time);
+    let offset = match env::var("TZ") {
+        Ok(zone) => {
+            let zone = zone.strip_prefix(':').unwrap_or(&zone);
+            let file = match zone.starts_with('/') {
+                true => fs::read(zone),
+                false => fs::read(format!("{ZONEINFO}/{zone}")),
+            };
+            file.ok()
+                .and_then(|data| tzif_offset(&data, seconds))
+                .or_else(|| posix_offset(zone, seconds))
+        }
+        Err(_) => fs::read("/etc/localtime")
+            .ok()
+            .and_then(|data| tzif_offset(&data, seconds)),
+    };
+    offset.unwrap_or(0)
+}
+
+// a compiled zoneinfo file (RFC 8536): the 64-bit block when there is one, and past its
+// last transition the POSIX rule in the footer
+fn tzif_offset(data: &[u8], seconds: i64) -> Option<i64> {
+    let header = |at: usize| -> Option<[usize; 6]> {
+        let header = data
+            .get(at..at + 44)
+            .filter(|header| header.starts_with(b"TZif"))?;
+        let mut counts = [0; 6];
+        for (i, count) in counts.iter_mut().enumerate() {
+            let field = &header[20 + 4 * i..24 + 4 * i];
+            *count = u32::from_be_bytes(field.try_into().ok()?) as usize;
+        }
+        Some(counts)
+    };
+    // isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt
+    let block = |counts: [usize; 6], width: usize| {
+        let [isut, isstd, leap, times, types, chars] = counts;
+        times * (width + 1) + types * 6 + chars + leap * (width + 4) + isstd + isut
+    };
+    let (mut at, mut width, mut counts) = (0, 4, header(0)?);
+    if data[4] >= b'2' {
+        at = 44 + block(counts, 4);
+        width = 8;
+        counts = header(at)?;
+    }
+    let [_, _, _, times, types, _] = counts;
+    let body = at + 44;
+    let transition = |i: usize| -> Option<i64> {
+        let field = data.get(body + i * width..body + (i + 1) * width)?;
+        Some(match width {
+            4 => i32::from_be_bytes(field.try_into().ok()?) as i64,
+            _ => i64::from_be_bytes(field.try_into().ok()?),
+        })
+    };
+    let utoff = |kind: usize| -> Option<i64> {
+        let at = body + times * (width + 1) + kind * 6;
+        Some(i32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?) as i64)
+    };
+
+    let mut last = None;
+    for i in 0..times {
+        match transition(i)? <= seconds {
+            true => last = Some(i),
+            false => break,
+        }
+    }
+    if last.map_or(times == 0, |i| i + 1 == times) && width == 8 {
+        let footer = data.get(body + block(counts, 8)..).unwrap_or_default();
+        let footer = String::from_utf8_lossy(footer);
+        if let Some(offset) = posix_offset(footer.trim(), seconds) {
+            return Some(offset);
+        }
+    }
+    let kind = match last {
+        Some(i) => *data.get(body + times * width + i)? as usize,
+        None => 0,
+    };
+    ternary!(kind < types => utoff(kind); None)
+}
+
+// `CET-1CEST,M3.5.0,M10.5.0/3`: names, offsets west of UTC, and when daylight saving
+// starts and ends in local time
+fn posix_offset(spec: &str, seconds: i64) -> Option<i64> {
+    fn name(spec: &str) -> Option<&str> {
+        let rest = match spec.strip_prefix('<') {
+            Some(quoted) => &quoted[quoted.find('>')? + 1..],
+            None => spec.trim_start_matches(|c: char| c.is_ascii_alphabetic()),
+        };
+        ternary!(rest.len() < spec.len() => Some(rest); None)
+    }
+    // `[+-]hh[:mm[:ss]]`, also used for the transition times
+    fn clock(spec: &str) -> Option<(i64, &str)> {
+        let (sign, spec) = match spec.as_bytes().first()? {
+            b'-' => (-1, &spec[1..]),
+            b'+' => (1, &spec[1..]),
+            _ => (1, spec),
+        };
+        let end = spec
+            .find(|c: char| !c.is_ascii_digit() && c != ':')
+            .unwrap_or(spec.len());
+        let mut seconds = 0;
+        for (i, field) in spec[..end].split(':').enumerate().take(3) {
+            seconds += field.parse::<i64>().ok()? * [3600, 60, 1][i];
+        }
+        Some((sign * seconds, &spec[end..]))
+    }
+    // the local-time second within `year` a `Mm.w.d`, `Jn` or `n` rule names
+    fn rule(spec: &str, year: i64) -> Option<(i64, &str)> {
+        let (date, rest) = spec.split_at(spec.find(['/', ',']).unwrap_or(spec.len()));
+        let jan1 = days_from_civil(year, 1, 1);
+        let day = if let Some(date) = date.strip_prefix('M') {
+            let mut fields = date.splitn(3, '.').map(str::parse::<u32>);
+            let (month, week, weekday) = (
+                fields.next()?.ok()?,
+                fields.next()?.ok()?,
+                fields.next()?.ok()?,
+            );
+            let first = days_from_civil(year, month, 1);
+            let next = match month {
+                12 => days_from_civil(year + 1, 1, 1),
+                _ => days_from_civil(year, month + 1, 1),
+            };
+            // 1970-01-01 was a Thursday
+            let mut day =
+                first + (weekday as i64 - (first + 4)).rem_euclid(7) + 7 * (week as i64 - 1);
+            while day >= next {
+                day -= 7;
+            }
+            day
+        } else if let Some(julian) = date.strip_prefix('J') {
+            // 1 to 365, never counting February 29
+            let n: i64 = julian.parse().ok()?;
+            let leap = days_from_civil(year, 3, 1) - days_from_civil(year, 2, 28) == 2;
+            jan1 + n - 1 + i64::from(leap && n >= 60)
+        } else {
+            jan1 + date.parse::<i64>().ok()?
+        };
+        let (time, rest) = match rest.strip_prefix('/') {
+            Some(time) => clock(time)?,
+            None => (7200, rest),
+        };
+        Some((day * 86_400 + time, rest))
+    }
+
+    let (std, rest) = clock(name(spec)?)?;
+    let std = -std;
+    if rest.is_empty() {
+        return Some(std);
+    }
+    let rest = name(rest)?;
+    let (dst, rest) = match rest.starts_with(',') || rest.is_empty() {
+        true => (std + 3600, rest),
+        false => clock(rest).map(|(dst, rest)| (-dst, rest))?,
+    };
+    let Some(rules) = rest.strip_prefix(',') else {
+        // daylight time without rules: the US default, which libc assumes as well
+        return posix_offset(&format!("{spec},M3.2.0,M11.1.0"), seconds);
+    };
+    let (year, _, _) = civil_from_days((seconds + std).div_euclid(86_400));
+    let (start, rest) = rule(rules, year)?;
+    let (end, _) = rule(rest.strip_prefix(',')?, year)?;
+    // the start is reckoned in standard time, the end in daylight time
+    let (start, end) = (start - std, end - dst);
+    let daylight = match start < end {
+        true => start <= seconds && seconds < end,
+        // southern hemisphere: daylight time spans the new year
+        false => !(end <= seconds && seconds < start),
+    };
+    Some(ternary!(daylight => dst; std))
+}
+
+// `now`, a span back from it like `7d`, a local date like `2024-01-01`, or a local
+// `2024-01-01T12:00:00`; a trailing `Z` makes it UTC
+pub fn parse_time(value: &str, now: SystemTime) -> Option<SystemTime> {
+    let value = value.trim();
+    if value == "now" {
+        return Some(now);
+    }
+    if let Some(ago) = parse_ago(value) {
+        return now.checked_sub(ago);
+    }
+    let (value, utc) = match value.strip_suffix('Z') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    let (date, clock) = match value.split_once(['T', ' ']) {
+        Some((date, clock)) => (date, clock),
+        None => (value, "00:00:00"),
+    };
+    let number = |field: &str, range: std::ops::RangeInclusive<u32>| {
+        field.parse().ok().filter(|value| range.contains(value))
+    };
+    let date: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = date[..] else {
+        return None;
+    };
+    let clock: Vec<&str> = clock.split(':').collect();
+    let (hour, minute, second) = match clock[..] {
+        [hour, minute] => (hour, minute, "0"),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return None,
+    };
+    let days = days_from_civil(
+        year.parse().ok()?,
+        number(month, 1..=12)?,
+        number(day, 1..=31)?,
+    );
+    let seconds = days * 86_400
+        + i64::from(number(hour, 0..=23)?) * 3600
+        + i64::from(number(minute, 0..=59)?) * 60
+        + i64::from(number(second, 0..=60)?);
+    let wall = UNIX_EPOCH.checked_add(Duration::from_secs(u64::try_from(seconds).ok()?))?;
+    let offset = ternary!(utc => 0; local_offset(wall));
+    match offset < 0 {
+        true => wall.checked_add(Duration::from_secs(offset.unsigned_abs())),
+        false => wall.checked_sub(Duration::from_secs(offset as u64)),
+    }
+}
+
+// `30m`, `12h`, `7d` or `2w`; a unit is required, so a bare year is never taken for one
+fn parse_ago(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let scale = match unit {
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        'w' => 7 * 86_400,
+        _ => return None,
+    };
+    let number: u64 = value[..value.len() - 1].parse().ok()?;
+    Some(Duration::from_secs(number.checked_mul(scale)?))
+}
+
+// `500ms`, `5s`, `2m`, or bare seconds
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, scale) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1.0)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60.0)
+    } else {
+        (value, 1.0)
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    Duration::try_from_secs_f64(number * scale).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_utc() {
+        assert_eq!(iso8601_utc(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(iso8601_utc(leap_day), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn offsets() {
+        let at = |date: (i64, u32, u32)| {
+            let (year, month, day) = date;
+            days_from_civil(year, month, day) * 86_400 + 12 * 3600
+        };
+        let berlin = "CET-1CEST,M3.5.0,M10.5.0/3";
+        assert_eq!(posix_offset(berlin, at((2024, 1, 15))), Some(3600));
+        assert_eq!(posix_offset(berlin, at((2024, 7, 1))), Some(7200));
+        // summer time ends 03:00 local on the last Sunday of October, 01:00 UTC
+        let last_sunday = days_from_civil(2024, 10, 27) * 86_400;
+        assert_eq!(posix_offset(berlin, last_sunday + 3599), Some(7200));
+        assert_eq!(posix_offset(berlin, last_sunday + 3600), Some(3600));
+        assert_eq!(
+            posix_offset("EST5EDT,M3.2.0,M11.1.0", at((2024, 7, 1))),
+            Some(-14_400)
+        );
+        let sydney = "AEST-10AEDT,M10.1.0,M4.1.0/3";
+        assert_eq!(posix_offset(sydney, at((2024, 1, 15))), Some(39_600));
+        assert_eq!(posix_offset(sydney, at((2024, 7, 1))), Some(36_000));
+        assert_eq!(posix_offset("<+0530>-5:30", 0), Some(19_800));
+        assert_eq!(posix_offset("UTC0", 0), Some(0));
+        assert_eq!(posix_offset("nonsense", 0), None);
+    }
+
+    #[test]
+    fn zoneinfo() {
+        // version 2: an empty 32-bit block, then one transition at 0 from -01:00 to the
+        // footer's zone
+        let mut data = Vec::new();
+        let header = |data: &mut Vec<u8>, times: u32, types: u32, chars: u32| {
+            data.extend(b"TZif2");
+            data.extend([0; 15]);
+            for count in [0, 0, 0, times, types, chars] {
+                data.extend(u32::to_be_bytes(count));
+            }
+        };
+        header(&mut data, 0, 0, 0);
+        header(&mut data, 1, 2, 4);
+        data.extend(0i64.to_be_bytes());
+        data.push(1);
+        data.extend((-3600i32).to_be_bytes());
+        data.extend([0, 0]);
+        data.extend(3600i32.to_be_bytes());
+        data.extend([0, 0]);
+        data.extend(b"XX\0\0");
+        data.extend(b"\nCET-1CEST,M3.5.0,M10.5.0/3\n");
+        assert_eq!(tzif_offset(&data, -1), Some(-3600));
+        let july = days_from_civil(2024, 7, 1) * 86_400;
+        assert_eq!(tzif_offset(&data, july), Some(7200));
+        assert_eq!(tzif_offset(b"not a zone", 0), None);
+    }
+
+    #[test]
+    fn parses_times() {
+        let now = UNIX_EPOCH + Duration::from_secs(5);
+        let utc = |value| parse_time(value, now).map(iso8601_utc);
+        assert_eq!(parse_time("now", now), Some(now));
+        let week = UNIX_EPOCH + Duration::from_secs(30 * 86_400);
+        assert_eq!(
+            parse_time("7d", week),
+            Some(UNIX_EPOCH + Duration::from_secs(23 * 86_400))
+        );
+        assert_eq!(parse_time("2h", week), parse_time("120m", week));
+        assert_eq!(
+            utc("2024-02-29T12:34:56Z").as_deref(),
+            Some("2024-02-29T12:34:56Z")
+        );
+        assert_eq!(
+            utc("2024-02-29 12:34Z").as_deref(),
+            Some("2024-02-29T12:34:00Z")
+        );
+        for bad in [
+            "2024-13-01",
+            "2024-01",
+            "yesterday",
+            "2024-01-01T25:00",
+            "1960-01-01Z",
+            "7",
+            "d",
+            "-3d",
+        ] {
+            assert_eq!(parse_time(bad, now), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn formats_local() {
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(readable(leap_day, true), "2024-02-29 12:34:56 UTC");
+        assert_eq!(stamp(leap_day, -5400, 'T'), "2024-02-29T11:04:56");
+        assert_eq!(offset_suffix(-5400), "-01:30");
+        assert_eq!(offset_suffix(19_800), "+05:30");
+        assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("5s"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration("soon"), None);
+    }
+}
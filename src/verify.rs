@@ -0,0 +1,101 @@
+use crate::vfs::{FileSystem, Kind};
+use crate::walk::count_lines;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+const CHUNK: usize = 8192;
+
+// the reference: decode and let the standard library decide what a line is
+pub fn by_lines_iterator(content: &[u8]) -> u128 {
+    String::from_utf8_lossy(content).lines().count() as u128
+}
+
+// the fast path the walker uses
+pub fn by_newline_scan(content: &[u8]) -> u128 {
+    count_lines(content)
+}
+
+// fixed-size reads, so a line split across a chunk boundary gets exercised
+pub fn by_chunks(reader: &mut dyn Read) -> io::Result<u128> {
+    let mut buffer = [0u8; CHUNK];
+    let (mut newlines, mut last) = (0u128, None);
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        newlines += buffer[..read].iter().filter(|byte| **byte == b'\n').count() as u128;
+        last = Some(buffer[read - 1]);
+    }
+    Ok(match last {
+        Some(b'\n') | None => newlines,
+        Some(_) => newlines + 1,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Verdict {
+    pub path: PathBuf,
+    pub lines_iterator: u128,
+    pub newline_scan: u128,
+    pub chunked: u128,
+}
+
+impl Verdict {
+    pub fn agrees(&self) -> bool {
+        self.lines_iterator == self.newline_scan && self.newline_scan == self.chunked
+    }
+}
+
+pub fn verify_file(fs: &dyn FileSystem, path: &Path) -> io::Result<Verdict> {
+    let content = fs.read(path)?;
+    Ok(Verdict {
+        path: path.to_path_buf(),
+        lines_iterator: by_lines_iterator(&content),
+        newline_scan: by_newline_scan(&content),
+        chunked: by_chunks(&mut fs.open(path)?)?,
+    })
+}
+
+// every regular file under `path`, hidden ones included; nothing is filtered here
+pub fn verify(fs: &dyn FileSystem, path: &Path) -> io::Result<Vec<Verdict>> {
+    match fs.metadata(path)?.kind {
+        Kind::File => Ok(vec![verify_file(fs, path)?]),
+        Kind::Dir => {
+            let mut children = fs.read_dir(path)?;
+            children.sort();
+            let mut verdicts = Vec::new();
+            for child in children {
+                verdicts.extend(verify(fs, &child)?);
+            }
+            Ok(verdicts)
+        }
+        Kind::Other => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+
+    #[test]
+    fn strategies_agree() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/empty", "");
+        memory.insert("r/crlf", "a\r\nb\r\n");
+        memory.insert("r/unterminated", "a\nb");
+        memory.insert(
+            "r/boundary",
+            "x".repeat(CHUNK - 1) + "\n" + &"y".repeat(CHUNK),
+        );
+        memory.insert("r/sub/invalid", b"\xff\n\xfe".to_vec());
+
+        let verdicts = verify(&memory, Path::new("r"))?;
+        assert_eq!(verdicts.len(), 5);
+        assert!(verdicts.iter().all(Verdict::agrees));
+        Ok(())
+    }
+}
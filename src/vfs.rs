@@ -0,0 +1,221 @@
+#[cfg(test)]
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Dir,
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    pub kind: Kind,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+// everything the walker needs from a backend; new sources only implement these three
+pub trait FileSystem: Sync {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>>;
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        self.open(path)?.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    // FIFOs and devices named explicitly by the user; these can block forever, so
+    // the whole read is bounded by both size and time
+    fn read_special(&self, path: &Path, _cap: u64, _timeout: Duration) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{}: not a regular file or directory", path.display()),
+        ))
+    }
+
+    // reuses the caller's allocation, which is what matters for piles of tiny files
+    fn read_into(&self, path: &Path, buffer: &mut Vec<u8>) -> io::Result<()> {
+        buffer.clear();
+        self.open(path)?.read_to_end(buffer)?;
+        Ok(())
+    }
+}
+
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = fs::metadata(path)?;
+        let kind = match metadata.file_type() {
+            filetype if filetype.is_file() => Kind::File,
+            filetype if filetype.is_dir() => Kind::Dir,
+            _ => Kind::Other,
+        };
+        Ok(Metadata {
+            kind,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn read_special(&self, path: &Path, cap: u64, timeout: Duration) -> io::Result<Vec<u8>> {
+        let (sender, receiver) = mpsc::channel();
+        let owned = path.to_path_buf();
+        // opening a FIFO blocks until a writer shows up, so even the open happens over
+        // there; on timeout the thread is abandoned and dies with the process
+        thread::spawn(move || {
+            let result = fs::File::open(&owned).and_then(|file| {
+                let mut contents = Vec::new();
+                file.take(cap + 1).read_to_end(&mut contents)?;
+                Ok(contents)
+            });
+            let _ = sender.send(result);
+        });
+
+        let contents = receiver.recv_timeout(timeout).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "{}: no end of input after {:?}; is anything writing to it?",
+                    path.display(),
+                    timeout
+                ),
+            )
+        })??;
+        if contents.len() as u64 > cap {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: more than {cap} bytes, refusing to read on",
+                    path.display()
+                ),
+            ));
+        }
+        Ok(contents)
+    }
+}
+
+// files only; directories exist implicitly as the parents of files
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+#[cfg(test)]
+impl MemoryFs {
+    pub fn new() -> MemoryFs {
+        MemoryFs::default()
+    }
+
+    pub fn insert<P: AsRef<Path>>(&mut self, path: P, contents: impl Into<Vec<u8>>) {
+        self.files
+            .insert(path.as_ref().to_path_buf(), contents.into());
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+    }
+}
+
+#[cfg(test)]
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{}: no such file or directory", path.display()),
+    )
+}
+
+#[cfg(test)]
+impl FileSystem for MemoryFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        if !self.is_dir(path) {
+            return Err(not_found(path));
+        }
+        let mut children: Vec<PathBuf> = Vec::new();
+        for file in self.files.keys() {
+            if let Ok(rest) = file.strip_prefix(path) {
+                if let Some(first) = rest.components().next() {
+                    let child = path.join(first);
+                    if !children.contains(&child) {
+                        children.push(child);
+                    }
+                }
+            }
+        }
+        Ok(children)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        match self.files.get(path) {
+            Some(contents) => Ok(Metadata {
+                kind: Kind::File,
+                len: contents.len() as u64,
+                modified: None,
+            }),
+            None if self.is_dir(path) => Ok(Metadata {
+                kind: Kind::Dir,
+                len: 0,
+                modified: None,
+            }),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        match self.files.get(path) {
+            Some(contents) => Ok(Box::new(contents.as_slice())),
+            None => Err(not_found(path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_tree() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("root/a.rs", "fn main() {}\n");
+        memory.insert("root/sub/b.rs", "");
+        memory.insert("root/sub/c.rs", "");
+
+        let mut children = memory.read_dir(Path::new("root"))?;
+        children.sort();
+        assert_eq!(
+            children,
+            [PathBuf::from("root/a.rs"), PathBuf::from("root/sub")]
+        );
+        assert_eq!(memory.metadata(Path::new("root/sub"))?.kind, Kind::Dir);
+        assert_eq!(memory.metadata(Path::new("root/a.rs"))?.len, 13);
+        assert_eq!(memory.read(Path::new("root/a.rs"))?, b"fn main() {}\n");
+        assert!(memory.metadata(Path::new("root/missing")).is_err());
+        Ok(())
+    }
+}
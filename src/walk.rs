@@ -0,0 +1,2485 @@
+use crate::attributes::{self, Gitattributes, Linguist};
+use crate::cache::Cache;
+use crate::document;
+use crate::encoding::{self, Encoding};
+use crate::error::LcError;
+use crate::gitignore::{self, Gitignore};
+use crate::glob::Glob;
+use crate::goal;
+use crate::lang::{self, is_binary, ContentType};
+use crate::manifest;
+use crate::regex::Regex;
+use crate::stats::entropy;
+use crate::time::iso8601_utc;
+use crate::vfs::{FileSystem, Kind, Metadata};
+use crate::xxh3::{self, Xxh3};
+use crate::{Junk, Reserved, Visible};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub ignore: bool,
+    pub recurse: bool,
+    pub entropy: bool,
+    // manifest rules (see `manifest::RULES`) turned off by name
+    pub disabled_rules: Vec<String>,
+    pub manifest_rules: bool,
+    // skip `gitignore::DEFAULT_DIRS` in every directory
+    pub default_ignores: bool,
+    // add the size of directory entries themselves, like `du -b`
+    pub include_dir_bytes: bool,
+    // detect every file's encoding and count characters, not just lines
+    pub encodings: bool,
+    // how long a FIFO or device given as a root may take to deliver its contents
+    pub special_timeout: Duration,
+    // count rows as if long lines were wrapped at this many characters
+    pub wrap_width: Option<usize>,
+    // open files with a known binary extension too, instead of sizing them from metadata
+    pub read_binary: bool,
+    // fingerprint every file's content, for matching files across trees
+    pub hashes: bool,
+    // tell blank and comment-only lines apart from code, for `--format cloc`
+    pub line_kinds: bool,
+    // count editor temp and backup files instead of skipping them
+    pub include_junk: bool,
+    // walk into .git, .hg and .svn, which are otherwise skipped like any hidden name
+    pub include_vcs: bool,
+    // count dotfiles and walk into dot-directories
+    pub hidden: bool,
+    // count the text lines of PDF, DOCX and ODT files rather than none
+    pub extract_documents: bool,
+    // a machine-wide ignore file applied under every root, see `gitignore::global_path`
+    pub global_ignore: Option<PathBuf>,
+    // skipped whatever the ignore files say, matched against paths relative to the root
+    pub exclude: Vec<Glob>,
+    // when any are given, only files matching one of them are counted; directories are
+    // still entered, since `src/**/*.rs` never matches `src` itself
+    pub include: Vec<Glob>,
+    // the same two for regular expressions, matched against the relative path with `/`
+    // separators; directories end in `/`, so `(^|/)vendor/` prunes a whole subtree
+    pub regex: Vec<Regex>,
+    pub regex_exclude: Vec<Regex>,
+    // count only files with one of these extensions, compared without case; directories
+    // left with no such file are dropped from the tree altogether
+    pub extensions: Vec<String>,
+    // files with these extensions are skipped, compared without case
+    pub skip_extensions: Vec<String>,
+    // count only files of these content types; like `extensions`, directories left with
+    // none are dropped
+    pub types: Vec<ContentType>,
+    // count only files whose first `match_content_bytes` match one of these, whatever
+    // their names; directories left with none are dropped
+    pub match_content: Vec<Regex>,
+    pub match_content_bytes: u64,
+    // count only files at least / at most this many bytes; directories left with none are
+    // dropped
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    // count only files modified after this; directories left with none are dropped
+    pub modified_since: Option<SystemTime>,
+    // leave lockfiles and generated code out, see `lang::is_generated_name`
+    pub no_generated: bool,
+    // files larger than this are sized from metadata and never opened, like binaries
+    pub skip_larger_than: Option<u64>,
+    // Some(true) counts only test code, Some(false) leaves it out, going by
+    // `goal::in_bucket`; directories left with no files are dropped
+    pub tests: Option<bool>,
+    // leave out `gitignore::VENDORED_DIRS` wherever they turn up
+    pub no_vendored: bool,
+    // count git LFS pointer files at the size of the object they point to, see `lang::lfs_size`
+    pub lfs: bool,
+    // follow linguist-vendored, linguist-generated and linguist-language= in .gitattributes
+    // files, so language numbers line up with GitHub's
+    pub linguist: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            ignore: false,
+            recurse: true,
+            entropy: false,
+            disabled_rules: Vec::new(),
+            manifest_rules: true,
+            default_ignores: false,
+            include_dir_bytes: false,
+            encodings: false,
+            special_timeout: Duration::from_secs(5),
+            wrap_width: None,
+            read_binary: false,
+            hashes: false,
+            line_kinds: false,
+            include_junk: false,
+            include_vcs: false,
+            hidden: false,
+            extract_documents: false,
+            global_ignore: None,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            regex: Vec::new(),
+            regex_exclude: Vec::new(),
+            extensions: Vec::new(),
+            skip_extensions: Vec::new(),
+            types: Vec::new(),
+            match_content: Vec::new(),
+            match_content_bytes: 64 * 1024,
+            min_size: None,
+            max_size: None,
+            modified_since: None,
+            skip_larger_than: None,
+            no_generated: false,
+            tests: None,
+            no_vendored: false,
+            lfs: false,
+            linguist: false,
+        }
+    }
+}
+
+// what `Walker::explain` found: the path left out, the target or one of its directories,
+// and why; or the counted file (None for a directory) and the `!` rule that let it back in
+pub enum Verdict {
+    Skipped(PathBuf, String),
+    Counted(Option<Box<Entry>>, Option<String>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub kind: Kind,
+    pub lines: u128,
+    pub bytes: u64,
+    // size of the directory entries in this subtree, whether or not `bytes` includes it
+    pub dir_bytes: u64,
+    // files only, and only filled in when `Options::encodings` is on
+    pub encoding: Option<Encoding>,
+    pub chars: u128,
+    // lines as displayed when wrapped at `Options::wrap_width`
+    pub visual: u128,
+    // blank lines and lines holding only a comment, counted under `Options::line_kinds`
+    pub blank: u128,
+    pub comment: u128,
+    // estimated bits per byte after compression, files only
+    pub entropy: Option<f64>,
+    // content fingerprint, only when `Options::hashes` asked for one
+    pub hash: Option<u64>,
+    // newest file anywhere below, for directories
+    pub modified: Option<SystemTime>,
+    // set by an `lc:language=` marker or a path override; see `Entry::language`
+    pub declared_language: Option<String>,
+    // estimated bytes below directories that could not be listed, from their own metadata
+    pub unscanned: u64,
+    pub children: Vec<Entry>,
+}
+
+impl Entry {
+    // an unnamed parent for several scanned roots, so totals and reports see one tree
+    pub fn combine(roots: Vec<Entry>) -> Entry {
+        Entry {
+            path: PathBuf::new(),
+            kind: Kind::Dir,
+            lines: roots.iter().map(|root| root.lines).sum(),
+            bytes: roots.iter().map(|root| root.bytes).sum(),
+            dir_bytes: roots.iter().map(|root| root.dir_bytes).sum(),
+            encoding: None,
+            chars: roots.iter().map(|root| root.chars).sum(),
+            visual: roots.iter().map(|root| root.visual).sum(),
+            blank: roots.iter().map(|root| root.blank).sum(),
+            comment: roots.iter().map(|root| root.comment).sum(),
+            entropy: None,
+            hash: None,
+            modified: newest(&roots),
+            declared_language: None,
+            unscanned: roots.iter().map(|root| root.unscanned).sum(),
+            children: roots,
+        }
+    }
+
+    // a copy of this entry's own fields, for rebuilding a tree without a deep clone
+    pub fn childless(&self) -> Entry {
+        Entry {
+            path: self.path.clone(),
+            kind: self.kind,
+            lines: self.lines,
+            bytes: self.bytes,
+            dir_bytes: self.dir_bytes,
+            encoding: self.encoding,
+            chars: self.chars,
+            visual: self.visual,
+            blank: self.blank,
+            comment: self.comment,
+            entropy: self.entropy,
+            hash: self.hash,
+            modified: self.modified,
+            declared_language: self.declared_language.clone(),
+            unscanned: self.unscanned,
+            children: Vec::new(),
+        }
+    }
+
+    // a directory that could not be listed; only its estimated size is known
+    pub fn unreadable(&self) -> bool {
+        self.kind == Kind::Dir && self.children.is_empty() && self.unscanned > 0
+    }
+
+    pub fn files(&self) -> usize {
+        match self.kind {
+            Kind::Dir => self.children.iter().map(Entry::files).sum(),
+            _ => 1,
+        }
+    }
+
+    pub fn directories(&self) -> usize {
+        match self.kind {
+            Kind::Dir => 1 + self.children.iter().map(Entry::directories).sum::<usize>(),
+            _ => 0,
+        }
+    }
+
+    // a declared language wins over the one guessed from the file name
+    pub fn language(&self) -> Option<&str> {
+        match &self.declared_language {
+            Some(language) => Some(language),
+            None => lang::language(&self.path),
+        }
+    }
+
+    pub fn content_type(&self) -> ContentType {
+        let language = self.language();
+        match lang::is_minified(&self.path, language, self.lines, self.bytes) {
+            true => ContentType::Minified,
+            false => lang::content_type(&self.path, language),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("?")
+    }
+}
+
+// drops from later roots whatever an earlier root already counted, going by canonical
+// path, so `lc src src/module` counts src/module once; returns each trimmed root's path
+// with what was removed from it combined, and leaves out roots that were nothing but overlap
+pub fn remove_overlap(
+    roots: &mut Vec<Entry>,
+    canonical: &dyn Fn(&Path) -> PathBuf,
+) -> Vec<(PathBuf, Entry)> {
+    fn prune(
+        entry: &mut Entry,
+        seen: &mut HashSet<PathBuf>,
+        canonical: &dyn Fn(&Path) -> PathBuf,
+    ) -> Entry {
+        let mut removed = Vec::new();
+        for mut child in std::mem::take(&mut entry.children) {
+            if !seen.insert(canonical(&child.path)) {
+                removed.push(child);
+                continue;
+            }
+            if child.kind == Kind::Dir {
+                let below = prune(&mut child, seen, canonical);
+                if !below.children.is_empty() {
+                    removed.push(below);
+                }
+            }
+            entry.children.push(child);
+        }
+        let removed = Entry::combine(removed);
+        entry.lines -= removed.lines;
+        entry.bytes -= removed.bytes;
+        entry.dir_bytes -= removed.dir_bytes;
+        entry.chars -= removed.chars;
+        entry.visual -= removed.visual;
+        entry.blank -= removed.blank;
+        entry.comment -= removed.comment;
+        entry.unscanned -= removed.unscanned;
+        if !removed.children.is_empty() {
+            entry.modified = newest(&entry.children);
+        }
+        removed
+    }
+
+    let mut seen = HashSet::new();
+    let mut overlaps = Vec::new();
+    for mut root in std::mem::take(roots) {
+        let path = root.path.clone();
+        if !seen.insert(canonical(&root.path)) {
+            overlaps.push((path, Entry::combine(vec![root])));
+            continue;
+        }
+        let removed = prune(&mut root, &mut seen, canonical);
+        if !removed.children.is_empty() {
+            overlaps.push((path, removed));
+        }
+        roots.push(root);
+    }
+    overlaps
+}
+
+// files below this share one read buffer instead of allocating their own
+pub const SMALL_FILE: u64 = 4096;
+// files above this are counted a chunk at a time rather than read whole
+pub const LARGE_FILE: u64 = 1 << 30;
+const STREAM_CHUNK: usize = 1 << 20;
+// most a FIFO or device named as a root is allowed to produce
+pub const SPECIAL_CAP: u64 = 64 * 1024 * 1024;
+
+pub struct Walker<'a> {
+    fs: &'a dyn FileSystem,
+    options: Options,
+    cache: Option<&'a Cache>,
+    scratch: RefCell<Vec<u8>>,
+    on_file: Option<&'a dyn Fn(&Entry)>,
+    cancel: Option<&'a AtomicBool>,
+    // the directory `walk` started from, which `Options::exclude` patterns are relative to
+    root: RefCell<PathBuf>,
+    junk: Cell<usize>,
+    vcs: Cell<usize>,
+    linguist: Cell<usize>,
+    documents: Cell<(usize, u64)>,
+    large: Cell<(usize, u64)>,
+    generated: Cell<(usize, u128)>,
+    lfs: Cell<(usize, u64)>,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(fs: &'a dyn FileSystem, options: Options) -> Walker<'a> {
+        Walker {
+            fs,
+            options,
+            cache: None,
+            scratch: RefCell::new(Vec::with_capacity(SMALL_FILE as usize)),
+            on_file: None,
+            cancel: None,
+            root: RefCell::new(PathBuf::new()),
+            junk: Cell::new(0),
+            vcs: Cell::new(0),
+            linguist: Cell::new(0),
+            documents: Cell::new((0, 0)),
+            large: Cell::new((0, 0)),
+            generated: Cell::new((0, 0)),
+            lfs: Cell::new((0, 0)),
+        }
+    }
+
+    pub fn cache(mut self, cache: &'a Cache) -> Walker<'a> {
+        self.cache = Some(cache);
+        self
+    }
+
+    // called with every file as soon as it is counted, for output that streams
+    pub fn on_file(mut self, on_file: &'a dyn Fn(&Entry)) -> Walker<'a> {
+        self.on_file = Some(on_file);
+        self
+    }
+
+    // checked before every directory and listed file; once set, the walk returns
+    // `LcError::Cancelled` instead of a partial tree
+    pub fn cancel(mut self, cancel: &'a AtomicBool) -> Walker<'a> {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    // what the CLI already rejects while parsing flags, for callers building Options by hand
+    fn check_options(&self) -> Result<(), LcError> {
+        if self.options.wrap_width == Some(0) {
+            return Err(LcError::InvalidOptions(
+                "wrap width must be at least one column".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // the flag that drops `path`, as it would be typed
+    fn excluded(&self, path: &Path, is_dir: bool) -> Option<String> {
+        let skipped = &self.options.skip_extensions;
+        if !is_dir && !skipped.is_empty() {
+            let extension = path
+                .extension()
+                .map(|extension| extension.to_string_lossy());
+            if let Some(skip) = extension.and_then(|extension| {
+                (skipped.iter()).find(|skip| skip.eq_ignore_ascii_case(&extension))
+            }) {
+                return Some(format!("--no-ext {skip}"));
+            }
+        }
+        let root = self.root.borrow();
+        let relative = path.strip_prefix(&*root).unwrap_or(path);
+        if let Some(glob) = (self.options.exclude)
+            .iter()
+            .find(|glob| glob.is_match(relative, is_dir))
+        {
+            return Some(format!("--exclude '{}'", glob.as_str()));
+        }
+        if !is_dir && self.options.tests == Some(false) && goal::in_bucket("tests", relative) {
+            return Some("--no-tests".to_string());
+        }
+        let exclude = &self.options.regex_exclude;
+        if exclude.is_empty() {
+            return None;
+        }
+        let text = regex_subject(relative, is_dir);
+        (exclude.iter())
+            .find(|regex| regex.is_match(&text))
+            .map(|regex| format!("--regex-exclude '{}'", regex.as_str()))
+    }
+
+    // the filter a file fails to match, None when it is counted
+    fn filtered_out(&self, path: &Path) -> Option<&'static str> {
+        let root = self.root.borrow();
+        let relative = path.strip_prefix(&*root).unwrap_or(path);
+        let include = &self.options.include;
+        let regex = &self.options.regex;
+        let extensions = &self.options.extensions;
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy());
+        if !extensions.is_empty()
+            && !extension.is_some_and(|extension| {
+                (extensions.iter()).any(|wanted| wanted.eq_ignore_ascii_case(&extension))
+            })
+        {
+            return Some("--ext");
+        }
+        if !include.is_empty() && !include.iter().any(|glob| glob.is_match(relative, false)) {
+            return Some("--include");
+        }
+        if self.options.tests == Some(true) && !goal::in_bucket("tests", relative) {
+            return Some("--tests-only");
+        }
+        let text = regex_subject(relative, false);
+        match regex.is_empty() || regex.iter().any(|regex| regex.is_match(&text)) {
+            true => None,
+            false => Some("--regex"),
+        }
+    }
+
+    // the size bound a file falls outside, None when it is counted
+    fn outside_size(&self, len: u64) -> Option<&'static str> {
+        if self.options.min_size.is_some_and(|min| len < min) {
+            return Some("--min-size");
+        }
+        match self.options.max_size.is_some_and(|max| len > max) {
+            true => Some("--max-size"),
+            false => None,
+        }
+    }
+
+    // whether a file is new enough for `modified_since`; one without a time never is
+    fn modified_after(&self, modified: Option<SystemTime>) -> bool {
+        match self.options.modified_since {
+            Some(since) => modified.is_some_and(|modified| modified > since),
+            None => true,
+        }
+    }
+
+    // by name, or by a marker in the first few lines
+    fn is_generated(&self, path: &Path) -> bool {
+        if lang::is_generated_name(path) {
+            return true;
+        }
+        let mut head = Vec::new();
+        let read = (self.fs.open(path)).and_then(|file| file.take(4096).read_to_end(&mut head));
+        read.is_ok() && lang::has_generated_marker(&head)
+    }
+
+    // whether the start of a file matches `--match-content`; one that can't be opened is
+    // let through, so counting it reports the error as usual
+    fn content_matches(&self, path: &Path) -> bool {
+        let patterns = &self.options.match_content;
+        if patterns.is_empty() {
+            return true;
+        }
+        let mut head = Vec::new();
+        let read = (self.fs.open(path)).and_then(|file| {
+            file.take(self.options.match_content_bytes)
+                .read_to_end(&mut head)
+        });
+        if read.is_err() {
+            return true;
+        }
+        let text = String::from_utf8_lossy(&head);
+        patterns.iter().any(|regex| regex.is_match(&text))
+    }
+
+    // vendored and generated files skipped so far, going by .gitattributes
+    pub fn linguist_excluded(&self) -> usize {
+        self.linguist.get()
+    }
+
+    // junk files skipped so far, across every walk with this walker
+    pub fn junk(&self) -> usize {
+        self.junk.get()
+    }
+
+    // version control directories skipped so far
+    pub fn vcs_skipped(&self) -> usize {
+        self.vcs.get()
+    }
+
+    // documents counted so far and their pages, where the document says how many
+    pub fn documents(&self) -> (usize, u64) {
+        self.documents.get()
+    }
+
+    // generated files left out so far, and their lines
+    pub fn generated(&self) -> (usize, u128) {
+        self.generated.get()
+    }
+
+    // git LFS pointers found so far, and the sizes of the objects they stand for
+    pub fn lfs(&self) -> (usize, u64) {
+        self.lfs.get()
+    }
+
+    // files left unread for `skip_larger_than` so far, and their bytes
+    pub fn large_skipped(&self) -> (usize, u64) {
+        self.large.get()
+    }
+
+    pub fn walk(&self, root: &Path) -> Result<Entry, LcError> {
+        self.check_options()?;
+        let metadata = self.fs.metadata(root)?;
+        match metadata.kind {
+            Kind::File => Ok(self.count_file(root.to_path_buf(), &metadata)?),
+            Kind::Dir => {
+                let global = match (&self.options.global_ignore, self.options.ignore) {
+                    (Some(file), true) => gitignore::read(self.fs, file.clone(), root)?,
+                    _ => None,
+                };
+                let gitignores: Vec<&Gitignore> = global.iter().collect();
+                *self.root.borrow_mut() = root.to_path_buf();
+                self.walk_dir(root, metadata.len, &gitignores, &[], false)
+            }
+            // only reached when named explicitly; walks skip these entries entirely
+            Kind::Other => {
+                let content =
+                    self.fs
+                        .read_special(root, SPECIAL_CAP, self.options.special_timeout)?;
+                let lines = count_lines(&content);
+                Ok(Entry {
+                    lines,
+                    bytes: content.len() as u64,
+                    dir_bytes: 0,
+                    encoding: None,
+                    chars: 0,
+                    visual: (self.options.wrap_width)
+                        .map_or(lines, |width| visual_lines(&content, width)),
+                    blank: 0,
+                    comment: 0,
+                    entropy: None,
+                    hash: None,
+                    modified: metadata.modified,
+                    declared_language: lang::marker(&content),
+                    unscanned: 0,
+                    path: root.to_path_buf(),
+                    kind: Kind::File,
+                    children: Vec::new(),
+                })
+            }
+        }
+    }
+
+    // `lc -`: whatever arrives on `reader` until it closes, counted as one file named
+    // `label`, streamed so a log of any size fits
+    pub fn walk_reader(&self, label: &Path, reader: &mut dyn Read) -> Result<Entry, LcError> {
+        self.check_options()?;
+        let entry = self.read_stream(label.to_path_buf(), reader, None)?;
+        if let Some(on_file) = self.on_file {
+            on_file(&entry);
+        }
+        Ok(entry)
+    }
+
+    // why a walk of `root` would or wouldn't count `target`, checking each directory on the
+    // way down with the same rules `walk_dir` applies
+    pub fn explain(&self, root: &Path, target: &Path) -> Result<Verdict, LcError> {
+        self.check_options()?;
+        let Ok(relative) = target.strip_prefix(root) else {
+            return Err(LcError::InvalidOptions(format!(
+                "{} is not under {}",
+                target.display(),
+                root.display()
+            )));
+        };
+        *self.root.borrow_mut() = root.to_path_buf();
+        let mut gitignores = Vec::new();
+        if let (Some(file), true) = (&self.options.global_ignore, self.options.ignore) {
+            gitignores.extend(gitignore::read(self.fs, file.clone(), root)?);
+        }
+        let mut attributes = Vec::new();
+        let (mut directory, mut rule) = (root.to_path_buf(), None);
+
+        let components: Vec<_> = relative.components().collect();
+        for (depth, component) in components.iter().enumerate() {
+            if self.options.ignore {
+                for name in gitignore::FILES {
+                    gitignores.extend(gitignore::load(self.fs, &directory, name)?);
+                }
+            }
+            if self.options.linguist {
+                attributes.extend(attributes::load(self.fs, &directory)?);
+            }
+            let path = directory.join(component);
+            let skipped = |why: String| Ok(Verdict::Skipped(path.clone(), why));
+            if path.is_reserved() {
+                return skipped("lc writes it itself".to_string());
+            }
+            if !self.options.include_junk && path.is_junk() {
+                return skipped("an editor temp or backup file, see --include-junk".to_string());
+            }
+            let vcs = gitignore::is_vcs(&path);
+            if vcs && !self.options.include_vcs {
+                return skipped("version control metadata, see --include-vcs".to_string());
+            }
+            if !vcs && !self.options.hidden && !path.is_visible() {
+                return skipped("its name starts with a dot, see --hidden".to_string());
+            }
+            let metadata = self.fs.metadata(&path)?;
+            let is_dir = metadata.kind == Kind::Dir;
+            let in_force: Vec<&Gitignore> = gitignores.iter().collect();
+            match gitignore::deciding_rule(&in_force, &path, is_dir) {
+                Some((line, true)) => return skipped(format!("ignored by {line}")),
+                Some((line, false)) => rule = Some(line),
+                None => {}
+            }
+            if let Some(flag) = self.excluded(&path, is_dir) {
+                return skipped(format!("excluded by {flag}"));
+            }
+            let in_force: Vec<&Gitattributes> = attributes.iter().collect();
+            let linguist = match self.options.linguist && !is_dir {
+                true => attributes::linguist(&in_force, &path),
+                false => Linguist::default(),
+            };
+            if linguist.excluded() {
+                let attribute = ternary!(linguist.vendored == Some(true) => "linguist-vendored"; "linguist-generated");
+                return skipped(format!("marked {attribute} in .gitattributes"));
+            }
+            match metadata.kind {
+                Kind::File => {
+                    if let Some(filter) = self.filtered_out(&path) {
+                        return skipped(format!("not matched by {filter}"));
+                    }
+                }
+                Kind::Dir => {
+                    let entries = self.fs.read_dir(&directory)?;
+                    let names: Vec<&Path> = entries.iter().map(PathBuf::as_path).collect();
+                    let manifest = match self.options.manifest_rules {
+                        true => {
+                            manifest::excluding_rule(&names, &self.options.disabled_rules, &path)
+                        }
+                        false => None,
+                    };
+                    if let Some(manifest) = manifest {
+                        return skipped(format!(
+                            "build output next to {}, see --no-manifest-rule {}",
+                            manifest.manifest, manifest.name
+                        ));
+                    }
+                    if self.options.default_ignores
+                        && gitignore::DEFAULT_DIRS
+                            .iter()
+                            .any(|dir| path.ends_with(dir))
+                    {
+                        return skipped(
+                            "a default exclusion, see --no-default-ignores".to_string(),
+                        );
+                    }
+                    if self.options.no_vendored
+                        && gitignore::VENDORED_DIRS
+                            .iter()
+                            .any(|dir| path.ends_with(dir))
+                    {
+                        return skipped("vendored code, see --no-vendored".to_string());
+                    }
+                    if !self.options.recurse && depth + 1 < components.len() {
+                        return skipped("not entered with --no-recurse".to_string());
+                    }
+                }
+                Kind::Other => return skipped("not a regular file or directory".to_string()),
+            }
+            directory = path;
+        }
+
+        let metadata = self.fs.metadata(target)?;
+        if metadata.kind != Kind::File {
+            return Ok(Verdict::Counted(None, rule));
+        }
+        if let Some(bound) = self.outside_size(metadata.len) {
+            let why = format!("{} bytes, outside {bound}", metadata.len);
+            return Ok(Verdict::Skipped(target.to_path_buf(), why));
+        }
+        if !self.modified_after(metadata.modified) {
+            let why = match metadata.modified {
+                Some(modified) => {
+                    format!("last modified {}, before --since", iso8601_utc(modified))
+                }
+                None => "no modification time to compare with --since".to_string(),
+            };
+            return Ok(Verdict::Skipped(target.to_path_buf(), why));
+        }
+        if !self.content_matches(target) {
+            let why = "its first bytes don't match --match-content".to_string();
+            return Ok(Verdict::Skipped(target.to_path_buf(), why));
+        }
+        let mut file = self.count_file(target.to_path_buf(), &metadata)?;
+        if file.declared_language.is_none() && self.options.linguist {
+            let in_force: Vec<&Gitattributes> = attributes.iter().collect();
+            file.declared_language = (attributes::linguist(&in_force, target).language.as_deref())
+                .map(lang::from_linguist);
+        }
+        let types = &self.options.types;
+        if !types.is_empty() && !types.contains(&file.content_type()) {
+            let why = format!("{} is not among --type", file.content_type().name());
+            return Ok(Verdict::Skipped(file.path, why));
+        }
+        if self.options.no_generated && self.is_generated(target) {
+            let why = "a lockfile or generated code, see --no-generated".to_string();
+            return Ok(Verdict::Skipped(file.path, why));
+        }
+        Ok(Verdict::Counted(Some(Box::new(file)), rule))
+    }
+
+    // counts exactly the listed files, flat under `label`; nothing is filtered
+    pub fn walk_files(&self, label: &Path, paths: Vec<PathBuf>) -> Result<Entry, LcError> {
+        self.check_options()?;
+        let mut children = Vec::with_capacity(paths.len());
+        for path in paths {
+            if self.cancelled() {
+                return Err(LcError::Cancelled);
+            }
+            match self.fs.metadata(&path) {
+                Ok(metadata) if metadata.kind == Kind::File => {
+                    children.push(self.count_file(path, &metadata)?)
+                }
+                Ok(_) => eprintln!("lc: {}: not a regular file, skipped", path.display()),
+                Err(err) => eprintln!("lc: {}: {err}", path.display()),
+            }
+        }
+
+        Ok(Entry {
+            path: label.to_path_buf(),
+            kind: Kind::Dir,
+            lines: children.iter().map(|child| child.lines).sum(),
+            bytes: children.iter().map(|child| child.bytes).sum(),
+            dir_bytes: 0,
+            encoding: None,
+            chars: children.iter().map(|child| child.chars).sum(),
+            visual: children.iter().map(|child| child.visual).sum(),
+            blank: children.iter().map(|child| child.blank).sum(),
+            comment: children.iter().map(|child| child.comment).sum(),
+            entropy: None,
+            hash: None,
+            modified: newest(&children),
+            declared_language: None,
+            unscanned: children.iter().map(|child| child.unscanned).sum(),
+            children,
+        })
+    }
+
+    fn count_file(&self, path: PathBuf, metadata: &Metadata) -> io::Result<Entry> {
+        let entry = self.read_file(path, metadata)?;
+        if let Some(on_file) = self.on_file {
+            on_file(&entry);
+        }
+        Ok(entry)
+    }
+
+    // the size of the object a git LFS pointer stands for, when `Options::lfs` is on; a
+    // pointer is a few lines of text, so nothing bigger is opened to check
+    fn lfs_size(&self, path: &Path, metadata: &Metadata) -> Option<u64> {
+        if !self.options.lfs || metadata.len > lang::LFS_POINTER_MAX {
+            return None;
+        }
+        lang::lfs_size(&self.fs.read(path).ok()?)
+    }
+
+    fn read_file(&self, path: PathBuf, metadata: &Metadata) -> io::Result<Entry> {
+        // entropy, encodings and wrapping need the content itself, which the cache does not keep
+        let cache = self.cache.filter(|_| {
+            !self.options.entropy
+                && !self.options.encodings
+                && !self.options.hashes
+                && self.options.wrap_width.is_none()
+                && !self.options.line_kinds
+        });
+        let binary = !self.options.read_binary && is_binary(&path);
+        let large =
+            !binary && (self.options.skip_larger_than).is_some_and(|limit| metadata.len > limit);
+        let lfs = self.lfs_size(&path, metadata);
+        if let Some(size) = lfs {
+            let (files, bytes) = self.lfs.get();
+            self.lfs.set((files + 1, bytes + size));
+        } else if large {
+            let (files, bytes) = self.large.get();
+            self.large.set((files + 1, bytes + metadata.len));
+        } else if let Some(format) = document::format(&path) {
+            return self.read_document(path, metadata, format);
+        }
+        // binaries by extension are sized from metadata alone, without ever being opened
+        let known = match binary || large || lfs.is_some() {
+            true => Some((0, None)),
+            false => cache.and_then(|cache| cache.lookup(&path, metadata)),
+        };
+        if let Some((lines, declared_language)) = known {
+            return Ok(Entry {
+                lines,
+                bytes: lfs.unwrap_or(metadata.len),
+                dir_bytes: 0,
+                encoding: None,
+                chars: 0,
+                visual: lines,
+                blank: 0,
+                comment: 0,
+                entropy: None,
+                hash: match binary && self.options.hashes {
+                    true => Some(self.hash_file(&path)?),
+                    false => None,
+                },
+                modified: metadata.modified,
+                declared_language,
+                unscanned: 0,
+                path,
+                kind: Kind::File,
+                children: Vec::new(),
+            });
+        }
+
+        // too big to hold in memory, and on 32-bit targets too big to even address
+        if metadata.len > LARGE_FILE {
+            return self.stream_file(path, metadata, cache);
+        }
+
+        let started = Instant::now();
+        let (mut scratch, owned);
+        let content: &[u8] = match metadata.len < SMALL_FILE {
+            true => {
+                scratch = self.scratch.borrow_mut();
+                self.fs.read_into(&path, &mut scratch)?;
+                &scratch
+            }
+            false => {
+                owned = self.fs.read(&path)?;
+                &owned
+            }
+        };
+        let (encoding, lines, chars) = match self.options.encodings {
+            true => {
+                let encoding = encoding::detect(content);
+                let (lines, chars) = encoding::count(content, encoding);
+                (Some(encoding), lines, chars)
+            }
+            false => match encoding::sniff_utf16(content) {
+                Some(utf16) => (None, encoding::count(content, utf16).0, 0),
+                None => (None, count_lines(content), 0),
+            },
+        };
+        let declared_language = lang::marker(content);
+        if let Some(cache) = cache {
+            let language = declared_language.as_deref();
+            cache.store(&path, metadata, lines, language, started.elapsed());
+        }
+        let wrap = self.options.wrap_width;
+        let (blank, comment) = match self.options.line_kinds {
+            true => {
+                let language = declared_language.as_deref().or(lang::language(&path));
+                line_kinds(content, language)
+            }
+            false => (0, 0),
+        };
+        Ok(Entry {
+            lines,
+            bytes: content.len() as u64,
+            dir_bytes: 0,
+            encoding,
+            chars,
+            visual: wrap.map_or(lines, |width| visual_lines(content, width)),
+            blank,
+            comment,
+            entropy: self.options.entropy.then(|| entropy(content)),
+            hash: self.options.hashes.then(|| fingerprint(content)),
+            modified: metadata.modified,
+            declared_language,
+            unscanned: 0,
+            path,
+            kind: Kind::File,
+            children: Vec::new(),
+        })
+    }
+
+    // a document that can't be parsed still counts, just without pages or lines
+    fn read_document(
+        &self,
+        path: PathBuf,
+        metadata: &Metadata,
+        format: document::Format,
+    ) -> io::Result<Entry> {
+        let content = self.fs.read(&path)?;
+        let extract = self.options.extract_documents;
+        let (pages, lines) = document::read(format, &content, extract).unwrap_or((None, 0));
+        let (documents, total) = self.documents.get();
+        self.documents
+            .set((documents + 1, total + pages.unwrap_or(0)));
+        Ok(Entry {
+            lines,
+            bytes: content.len() as u64,
+            dir_bytes: 0,
+            encoding: None,
+            chars: 0,
+            visual: lines,
+            blank: 0,
+            comment: 0,
+            entropy: None,
+            hash: self.options.hashes.then(|| fingerprint(&content)),
+            modified: metadata.modified,
+            declared_language: None,
+            unscanned: 0,
+            path,
+            kind: Kind::File,
+            children: Vec::new(),
+        })
+    }
+
+    // binaries are opened for `Options::hashes` alone, never to count their lines
+    fn hash_file(&self, path: &Path) -> io::Result<u64> {
+        let mut reader = self.fs.open(path)?;
+        let mut buffer = vec![0; STREAM_CHUNK];
+        let mut hasher = Xxh3::default();
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => return Ok(hasher.finish()),
+                Ok(read) => hasher.write(&buffer[..read]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    // lines, bytes, hash and marker only: encodings, entropy and wrapping need the whole
+    // content and are left unset, and UTF-16 is counted by its newline bytes
+    fn stream_file(
+        &self,
+        path: PathBuf,
+        metadata: &Metadata,
+        cache: Option<&Cache>,
+    ) -> io::Result<Entry> {
+        let started = Instant::now();
+        let mut reader = self.fs.open(&path)?;
+        let entry = self.read_stream(path, &mut reader, metadata.modified)?;
+        if let Some(cache) = cache {
+            let language = entry.declared_language.as_deref();
+            cache.store(
+                &entry.path,
+                metadata,
+                entry.lines,
+                language,
+                started.elapsed(),
+            );
+        }
+        Ok(entry)
+    }
+
+    fn read_stream(
+        &self,
+        path: PathBuf,
+        reader: &mut dyn Read,
+        modified: Option<SystemTime>,
+    ) -> io::Result<Entry> {
+        let mut buffer = vec![0; STREAM_CHUNK];
+        let (mut bytes, mut newlines, mut last) = (0u64, 0u128, None);
+        let (mut hasher, mut declared_language) = (Xxh3::default(), None);
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            let chunk = &buffer[..read];
+            if bytes == 0 {
+                declared_language = lang::marker(chunk);
+            }
+            if self.options.hashes {
+                hasher.write(chunk);
+            }
+            newlines += count_newlines(chunk);
+            last = chunk.last().copied();
+            bytes += read as u64;
+        }
+        let lines = ternary!(matches!(last, Some(b'\n') | None) => newlines; newlines + 1);
+        Ok(Entry {
+            lines,
+            bytes,
+            dir_bytes: 0,
+            encoding: None,
+            chars: 0,
+            visual: lines,
+            blank: 0,
+            comment: 0,
+            entropy: None,
+            hash: self.options.hashes.then(|| hasher.finish()),
+            modified,
+            declared_language,
+            unscanned: 0,
+            path,
+            kind: Kind::File,
+            children: Vec::new(),
+        })
+    }
+
+    // `gitignores` and `attributes` are the files in force here, outermost first
+    fn walk_dir(
+        &self,
+        directory_path: &Path,
+        own_bytes: u64,
+        gitignores: &[&Gitignore],
+        attributes: &[&Gitattributes],
+        nested: bool,
+    ) -> Result<Entry, LcError> {
+        if self.cancelled() {
+            return Err(LcError::Cancelled);
+        }
+        let entries = match self.fs.read_dir(directory_path) {
+            Ok(entries) => entries,
+            // below the root a locked directory is reported with its stat size instead of
+            // failing the whole walk
+            Err(err) if nested && err.kind() == io::ErrorKind::PermissionDenied => {
+                return Ok(Entry {
+                    path: directory_path.to_path_buf(),
+                    dir_bytes: own_bytes,
+                    unscanned: own_bytes,
+                    ..Entry::combine(Vec::new())
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+        // each directory's own ignore files join those of its parents for its whole subtree
+        let mut own = Vec::new();
+        if self.options.ignore {
+            for name in gitignore::FILES {
+                own.extend(gitignore::load(self.fs, directory_path, name)?);
+            }
+        }
+        let mut gitignores = gitignores.to_vec();
+        gitignores.extend(&own);
+        let own = match self.options.linguist {
+            true => attributes::load(self.fs, directory_path)?,
+            false => None,
+        };
+        let mut attributes = attributes.to_vec();
+        attributes.extend(&own);
+        let mut build_dirs = match self.options.manifest_rules {
+            true => {
+                let names: Vec<&Path> = entries.iter().map(PathBuf::as_path).collect();
+                manifest::excluded_dirs(&names, &self.options.disabled_rules)
+            }
+            false => Vec::new(),
+        };
+        if self.options.default_ignores {
+            build_dirs.extend(gitignore::DEFAULT_DIRS);
+        }
+        if self.options.no_vendored {
+            build_dirs.extend(gitignore::VENDORED_DIRS);
+        }
+
+        let (mut files, mut dirs) = (Vec::new(), Vec::new());
+        for path in entries {
+            if path.is_reserved() {
+                continue;
+            }
+            // before the dotfile check, so a skipped `.DS_Store` still shows up in the count
+            if !self.options.include_junk && path.is_junk() {
+                self.junk.set(self.junk.get() + 1);
+                continue;
+            }
+            let vcs = gitignore::is_vcs(&path);
+            if vcs && !self.options.include_vcs {
+                self.vcs.set(self.vcs.get() + 1);
+                continue;
+            }
+            if !vcs && !self.options.hidden && !path.is_visible() {
+                continue;
+            }
+            let metadata = self.fs.metadata(&path)?;
+            let is_dir = metadata.kind == Kind::Dir;
+            if gitignore::is_ignored(&gitignores, &path, is_dir)
+                || self.excluded(&path, is_dir).is_some()
+            {
+                continue;
+            }
+            let linguist = match self.options.linguist && !is_dir {
+                true => attributes::linguist(&attributes, &path),
+                false => Linguist::default(),
+            };
+            if linguist.excluded() {
+                self.linguist.set(self.linguist.get() + 1);
+                continue;
+            }
+            match metadata.kind {
+                Kind::File if self.filtered_out(&path).is_some() => {}
+                Kind::File if self.outside_size(metadata.len).is_some() => {}
+                Kind::File if !self.modified_after(metadata.modified) => {}
+                Kind::File if !self.content_matches(&path) => {}
+                Kind::File => files.push((path, metadata, linguist.language)),
+                Kind::Dir if build_dirs.iter().any(|dir| path.ends_with(dir)) => {}
+                Kind::Dir => dirs.push((path, metadata.len)),
+                Kind::Other => {}
+            }
+        }
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        dirs.sort();
+
+        let mut children = Vec::with_capacity(files.len() + dirs.len());
+        for (path, metadata, language) in files {
+            // `on_file` hears of it only once it has passed every filter below
+            let mut file = self.read_file(path, &metadata)?;
+            // an `lc:language=` marker in the file still has the last word
+            if file.declared_language.is_none() {
+                file.declared_language = language.as_deref().map(lang::from_linguist);
+            }
+            // known only once the file has had its say through a marker
+            let types = &self.options.types;
+            if !types.is_empty() && !types.contains(&file.content_type()) {
+                continue;
+            }
+            if self.options.no_generated && self.is_generated(&file.path) {
+                let (files, lines) = self.generated.get();
+                self.generated.set((files + 1, lines + file.lines));
+                continue;
+            }
+            if let Some(on_file) = self.on_file {
+                on_file(&file);
+            }
+            children.push(file);
+        }
+        if self.options.recurse {
+            for (path, len) in dirs {
+                let dir = self.walk_dir(&path, len, &gitignores, &attributes, true)?;
+                let filtered = !self.options.extensions.is_empty()
+                    || !self.options.types.is_empty()
+                    || !self.options.match_content.is_empty()
+                    || self.options.min_size.is_some()
+                    || self.options.max_size.is_some()
+                    || self.options.modified_since.is_some()
+                    || self.options.no_generated
+                    || self.options.tests.is_some()
+                    || !self.options.include.is_empty()
+                    || !self.options.regex.is_empty()
+                    || !self.options.regex_exclude.is_empty()
+                    || !self.options.skip_extensions.is_empty();
+                if !filtered || dir.files() > 0 || dir.unreadable() {
+                    children.push(dir);
+                }
+            }
+        }
+
+        let bytes: u64 = children.iter().map(|child| child.bytes).sum();
+        Ok(Entry {
+            path: directory_path.to_path_buf(),
+            kind: Kind::Dir,
+            lines: children.iter().map(|child| child.lines).sum(),
+            bytes: bytes + ternary!(self.options.include_dir_bytes => own_bytes; 0),
+            dir_bytes: own_bytes + children.iter().map(|child| child.dir_bytes).sum::<u64>(),
+            encoding: None,
+            chars: children.iter().map(|child| child.chars).sum(),
+            visual: children.iter().map(|child| child.visual).sum(),
+            blank: children.iter().map(|child| child.blank).sum(),
+            comment: children.iter().map(|child| child.comment).sum(),
+            entropy: None,
+            hash: None,
+            modified: newest(&children),
+            declared_language: None,
+            unscanned: children.iter().map(|child| child.unscanned).sum(),
+            children,
+        })
+    }
+}
+
+fn regex_subject(relative: &Path, is_dir: bool) -> String {
+    let text = relative.to_string_lossy().replace('\\', "/");
+    ternary!(is_dir => format!("{text}/"); text)
+}
+
+fn newest(children: &[Entry]) -> Option<SystemTime> {
+    children.iter().filter_map(|child| child.modified).max()
+}
+
+// same answer as `str::lines().count()` without decoding: every newline ends a line,
+// plus whatever trails the last one
+pub fn count_lines(content: &[u8]) -> u128 {
+    let newlines = count_newlines(content);
+    match content.last() {
+        Some(b'\n') | None => newlines,
+        Some(_) => newlines + 1,
+    }
+}
+
+fn count_newlines(content: &[u8]) -> u128 {
+    content.iter().filter(|byte| **byte == b'\n').count() as u128
+}
+
+// XXH3, so the same content hashes the same in every build and `--hash xxh3` values can
+// be kept and compared between runs
+pub fn fingerprint(content: &[u8]) -> u64 {
+    xxh3::xxh3(content)
+}
+
+// each line takes ceil(chars / width) rows, and an empty line still takes one
+// blank lines, and lines holding nothing but a comment in `language`'s syntax, the way cloc
+// splits them: a line with any code on it, before or after a comment, is code
+pub fn line_kinds(content: &[u8], language: Option<&str>) -> (u128, u128) {
+    let (line_comment, block) = language.map_or((None, None), lang::comments);
+    let (mut blank, mut comment) = (0, 0);
+    let mut open: Option<&str> = None;
+    for line in String::from_utf8_lossy(content).lines() {
+        let mut rest = line.trim();
+        if rest.is_empty() {
+            blank += 1;
+            continue;
+        }
+        let mut code = false;
+        while !rest.is_empty() {
+            if let Some(end) = open {
+                match rest.find(end) {
+                    Some(at) => {
+                        rest = &rest[at + end.len()..];
+                        open = None;
+                    }
+                    None => break,
+                }
+            } else if let Some((start, end)) = block.filter(|(start, _)| rest.starts_with(start)) {
+                rest = &rest[start.len()..];
+                open = Some(end);
+            } else if line_comment.is_some_and(|prefix| rest.starts_with(prefix)) {
+                break;
+            } else {
+                code = true;
+                // only a block opened later on the line still matters
+                match block.and_then(|(start, _)| rest.find(start)) {
+                    Some(at) => rest = &rest[at..],
+                    None => break,
+                }
+            }
+            rest = rest.trim_start();
+        }
+        if !code {
+            comment += 1;
+        }
+    }
+    (blank, comment)
+}
+
+pub fn visual_lines(content: &[u8], width: usize) -> u128 {
+    let content = content.strip_suffix(b"\n").unwrap_or(content);
+    if content.is_empty() {
+        return 0;
+    }
+    content
+        .split(|byte| *byte == b'\n')
+        .map(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            let chars = String::from_utf8_lossy(line).chars().count();
+            chars.div_ceil(width).max(1) as u128
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::{MemoryFs, RealFs};
+
+    fn memory() -> MemoryFs {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/.gitignore", "/build\n");
+        memory.insert("repo/main.rs", "fn main() {\n}\n");
+        memory.insert("repo/.hidden", "1\n2\n3\n");
+        memory.insert("repo/build/out.txt", "1\n2\n3\n4\n");
+        memory.insert("repo/src/lib.rs", "1\n2\n3\n");
+        memory
+    }
+
+    #[test]
+    fn walk_memory() -> io::Result<()> {
+        let memory = memory();
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("repo"))?;
+        assert_eq!(tree.lines, 9);
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["main.rs", "build", "src"]);
+        Ok(())
+    }
+
+    #[test]
+    fn gitignored() -> io::Result<()> {
+        let mut memory = memory();
+        memory.insert("repo/.gitignore", "/build\n*.log\n!keep.log\nsrc/gen/\n");
+        memory.insert("repo/src/debug.log", "1\n");
+        memory.insert("repo/src/keep.log", "1\n");
+        memory.insert("repo/src/gen/out.rs", "1\n");
+        let options = Options {
+            ignore: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["main.rs", "src"]);
+        let names: Vec<&str> = tree.children[1].children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["keep.log", "lib.rs"]);
+        Ok(())
+    }
+
+    // one file that exists but may not be opened
+    struct Forbidden(MemoryFs, &'static str);
+
+    impl FileSystem for Forbidden {
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            self.0.read_dir(path)
+        }
+        fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+            self.0.metadata(path)
+        }
+        fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read + '_>> {
+            match path == Path::new(self.1) {
+                true => Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+                false => self.0.open(path),
+            }
+        }
+    }
+
+    #[test]
+    fn error_categories() {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        memory.insert("r/sub/.gitignore", "*.log\n");
+        let cancel = AtomicBool::new(true);
+        let walked = Walker::new(&memory, Options::default())
+            .cancel(&cancel)
+            .walk(Path::new("r"));
+        assert!(matches!(walked, Err(LcError::Cancelled)));
+
+        let narrow = Options {
+            wrap_width: Some(0),
+            ..Options::default()
+        };
+        let walked = Walker::new(&memory, narrow).walk(Path::new("r"));
+        assert!(matches!(walked, Err(LcError::InvalidOptions(_))));
+
+        // a .gitignore that can't be read fails the walk rather than counting what it hides
+        let options = Options {
+            ignore: true,
+            ..Options::default()
+        };
+        let fs = Forbidden(memory, "r/sub/.gitignore");
+        let walked = Walker::new(&fs, options).walk(Path::new("r"));
+        assert!(
+            matches!(walked, Err(LcError::Ignore { path, .. }) if path.ends_with("sub/.gitignore"))
+        );
+    }
+
+    #[test]
+    fn lcignore() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/.gitignore", "*.log\n");
+        memory.insert("repo/.lcignore", "/fixtures\n!keep.log\n");
+        memory.insert("repo/a.rs", "1\n");
+        memory.insert("repo/keep.log", "1\n");
+        memory.insert("repo/fixtures/big.json", "1\n");
+        let options = Options {
+            ignore: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["a.rs", "keep.log"]);
+
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("repo"))?;
+        assert_eq!(tree.files(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn global_ignore() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("home/lc/ignore", "node_modules/\n*.lock\n");
+        memory.insert("repo/.gitignore", "!Cargo.lock\n");
+        memory.insert("repo/Cargo.lock", "1\n");
+        memory.insert("repo/yarn.lock", "1\n");
+        memory.insert("repo/web/node_modules/x.js", "1\n");
+        memory.insert("repo/web/app.js", "1\n");
+        let options = Options {
+            ignore: true,
+            global_ignore: Some(PathBuf::from("home/lc/ignore")),
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options.clone()).walk(Path::new("repo"))?;
+        // the project's own ignore files still have the last word
+        assert_eq!(tree.files(), 2);
+        assert_eq!(tree.children[0].name(), "Cargo.lock");
+
+        let missing = Options {
+            global_ignore: Some(PathBuf::from("home/lc/none")),
+            ..options
+        };
+        let tree = Walker::new(&memory, missing).walk(Path::new("repo"))?;
+        assert_eq!(tree.files(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn excludes() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/.gitignore", "!*.min.js\n");
+        memory.insert("repo/app.js", "1\n");
+        memory.insert("repo/web/app.min.js", "1\n");
+        memory.insert("repo/target/debug/out.rs", "1\n");
+        memory.insert("repo/src/target/keep.rs", "1\n");
+        let options = Options {
+            ignore: true,
+            exclude: vec![Glob::new("target/**"), Glob::new("*.min.js")],
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(tree.files(), 2);
+        assert_eq!(names, ["app.js", "src", "target", "web"]);
+        Ok(())
+    }
+
+    #[test]
+    fn overlapping_roots() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("src/a.rs", "1\n");
+        memory.insert("src/module/b.rs", "1\n2\n");
+        memory.insert("src/module/deep/c.rs", "1\n2\n3\n");
+        let walker = Walker::new(&memory, Options::default());
+        let mut roots = vec![
+            walker.walk(Path::new("src/module/deep"))?,
+            walker.walk(Path::new("src"))?,
+            walker.walk(Path::new("src/module"))?,
+        ];
+        let overlaps = remove_overlap(&mut roots, &|path| path.to_path_buf());
+
+        let tree = Entry::combine(roots);
+        assert_eq!((tree.files(), tree.lines), (3, 6));
+        assert_eq!(tree.children.len(), 2);
+        // src keeps module/b.rs but loses module/deep, which the first root counted
+        let src = &tree.children[1];
+        assert_eq!((src.files(), src.lines, src.bytes), (2, 3, 6));
+        assert_eq!(src.children[1].lines, 2);
+
+        let removed: Vec<(&Path, usize, u128)> = overlaps
+            .iter()
+            .map(|(path, removed)| (path.as_path(), removed.files(), removed.lines))
+            .collect();
+        assert_eq!(
+            removed,
+            [(Path::new("src"), 1, 3), (Path::new("src/module"), 2, 5)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn includes() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/README.md", "1\n");
+        memory.insert("repo/src/main.rs", "1\n");
+        memory.insert("repo/src/gen/out.rs", "1\n");
+        memory.insert("repo/src/gen/out.c", "1\n");
+        memory.insert("repo/tests/it.rs", "1\n");
+        let options = Options {
+            include: vec![Glob::new("src/**/*.rs")],
+            exclude: vec![Glob::new("gen/")],
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+        assert_eq!(tree.files(), 1);
+        assert_eq!(tree.children[0].children[0].name(), "main.rs");
+        Ok(())
+    }
+
+    #[test]
+    fn included_dirs() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/src/main.rs", "1\n");
+        memory.insert("repo/docs/guide.md", "1\n");
+        memory.insert("repo/assets/logo.svg", "<svg/>\n");
+        let names = |options: Options| -> io::Result<Vec<String>> {
+            let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+            Ok(tree
+                .children
+                .iter()
+                .map(|child| child.name().to_string())
+                .collect())
+        };
+        // directories left with nothing to count are dropped, as with --ext
+        let include = Options {
+            include: vec![Glob::new("*.rs")],
+            ..Options::default()
+        };
+        assert_eq!(names(include)?, ["src"]);
+        let regex = Options {
+            regex: vec![Regex::new(r"\.md$").unwrap()],
+            ..Options::default()
+        };
+        assert_eq!(names(regex)?, ["docs"]);
+        let skipped = Options {
+            skip_extensions: vec!["svg".to_string()],
+            ..Options::default()
+        };
+        assert_eq!(names(skipped)?, ["docs", "src"]);
+        Ok(())
+    }
+
+    #[test]
+    fn regex_filters() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/src/a.rs", "1\n");
+        memory.insert("repo/src/a_test.rs", "1\n");
+        memory.insert("repo/src/__snapshots__/a.snap", "1\n");
+        memory.insert("repo/docs/b.md", "1\n");
+        let options = Options {
+            regex: vec![Regex::new(r"\.(rs|snap)$").unwrap()],
+            regex_exclude: vec![
+                Regex::new("(^|/)__snapshots__/").unwrap(),
+                Regex::new("_test\\.rs$").unwrap(),
+            ],
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+        let src: Vec<&str> = tree.children[0].children.iter().map(Entry::name).collect();
+        // the snapshot directory is pruned whole, not kept as an empty entry
+        assert_eq!(src, ["a.rs"]);
+        assert_eq!(tree.files(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn extensions() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/Cargo.toml", "1\n");
+        memory.insert("repo/src/main.RS", "1\n2\n");
+        memory.insert("repo/src/logo.png", "x");
+        memory.insert("repo/assets/icons/a.svg", "1\n");
+        let options = Options {
+            extensions: vec!["rs".to_string(), "toml".to_string()],
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["Cargo.toml", "src"]);
+        assert_eq!((tree.files(), tree.lines), (2, 3));
+
+        let options = Options {
+            skip_extensions: vec!["png".to_string(), "SVG".to_string()],
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+        assert_eq!((tree.files(), tree.bytes), (2, 6));
+        Ok(())
+    }
+
+    #[test]
+    fn content_types() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/README.md", "1\n");
+        memory.insert("repo/src/main.rs", "1\n2\n");
+        memory.insert("repo/src/query", "-- lc:language=sql\nselect 1;\n");
+        memory.insert("repo/assets/logo.png", "x");
+        memory.insert("repo/bin/lc.exe", "xy");
+        let walk = |types: Vec<ContentType>| {
+            let options = Options {
+                types,
+                ..Options::default()
+            };
+            Walker::new(&memory, options).walk(Path::new("repo"))
+        };
+
+        let tree = walk(vec![ContentType::Code])?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["src"]);
+        assert_eq!((tree.files(), tree.lines), (2, 4));
+
+        let tree = walk(vec![ContentType::Media, ContentType::Executable])?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["assets", "bin"]);
+        assert_eq!((tree.files(), tree.bytes), (2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn streamed_types() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/README.md", "1\n");
+        memory.insert("r/main.rs", "1\n");
+        memory.insert("r/src/lib.rs", "1\n");
+        let seen = RefCell::new(Vec::new());
+        let record = |entry: &Entry| seen.borrow_mut().push(entry.path.clone());
+        let options = Options {
+            types: vec![ContentType::Code],
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options)
+            .on_file(&record)
+            .walk(Path::new("r"))?;
+        assert_eq!(
+            seen.into_inner(),
+            [PathBuf::from("r/main.rs"), PathBuf::from("r/src/lib.rs")]
+        );
+        assert_eq!(tree.files(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn matched_content() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/bin/deploy", "#!/usr/bin/env bash\necho hi\n");
+        memory.insert("repo/bin/run.py", "#!/usr/bin/env python3\n");
+        memory.insert("repo/src/a.rs", "// Copyright Acme\nfn main() {}\n");
+        memory.insert(
+            "repo/src/b.rs",
+            format!("{}// Copyright Acme\n", "\n".repeat(20)),
+        );
+        memory.insert("repo/docs/a.md", "nothing here\n");
+        let walk = |patterns: &[&str], bytes| {
+            let options = Options {
+                match_content: (patterns.iter())
+                    .map(|pattern| Regex::new(pattern).unwrap())
+                    .collect(),
+                match_content_bytes: bytes,
+                ..Options::default()
+            };
+            Walker::new(&memory, options).walk(Path::new("repo"))
+        };
+
+        let tree = walk(&["^#!/usr/bin/env bash", "Copyright Acme"], 1024)?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["bin", "src"]);
+        assert_eq!((tree.files(), tree.lines), (3, 25));
+        // the copyright in b.rs lies past the bytes looked at
+        let tree = walk(&["Copyright"], 20)?;
+        assert_eq!(tree.files(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn sizes() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/small.txt", "1\n");
+        memory.insert("repo/data/huge.csv", "1\n".repeat(500));
+        memory.insert("repo/src/mid.rs", "1\n".repeat(50));
+        let walk = |min_size, max_size| {
+            let options = Options {
+                min_size,
+                max_size,
+                ..Options::default()
+            };
+            Walker::new(&memory, options).walk(Path::new("repo"))
+        };
+
+        let tree = walk(None, Some(100))?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["small.txt", "src"]);
+        let tree = walk(Some(100), None)?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["data", "src"]);
+        // both bounds are inclusive
+        assert_eq!(walk(Some(100), Some(100))?.lines, 50);
+        Ok(())
+    }
+
+    #[test]
+    fn linguist_attributes() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert(
+            "repo/.gitattributes",
+            "third_party/** linguist-vendored\n*.h linguist-language=C++\n",
+        );
+        memory.insert("repo/third_party/lib.c", "1\n");
+        memory.insert("repo/src/.gitattributes", "*_pb.h linguist-generated\n");
+        memory.insert("repo/src/a.h", "1\n");
+        memory.insert("repo/src/a_pb.h", "1\n");
+        memory.insert("repo/src/b.h", "// lc:language=c\n");
+        let options = Options {
+            linguist: true,
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        let tree = walker.walk(Path::new("repo"))?;
+        let src = &tree.children[0];
+        let languages: Vec<(&str, Option<&str>)> = (src.children.iter())
+            .map(|file| (file.name(), file.language()))
+            .collect();
+        assert_eq!(languages, [("a.h", Some("cpp")), ("b.h", Some("c"))]);
+        assert_eq!((tree.files(), walker.linguist_excluded()), (2, 2));
+        Ok(())
+    }
+
+    #[test]
+    fn nested_gitignores() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("repo/.gitignore", "*.log\n");
+        memory.insert("repo/a.log", "1\n");
+        memory.insert("repo/pkg/.gitignore", "/fixtures\n!trace.log\n");
+        memory.insert("repo/pkg/trace.log", "1\n");
+        memory.insert("repo/pkg/fixtures/x.rs", "1\n");
+        memory.insert("repo/pkg/sub/fixtures/y.rs", "1\n");
+        memory.insert("repo/pkg/sub/z.log", "1\n");
+        memory.insert("repo/other/fixtures/w.rs", "1\n");
+        let options = Options {
+            ignore: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("repo"))?;
+        let mut files = Vec::new();
+        fn visit<'a>(entry: &'a Entry, files: &mut Vec<&'a Path>) {
+            match entry.kind {
+                Kind::Dir => entry.children.iter().for_each(|child| visit(child, files)),
+                _ => files.push(&entry.path),
+            }
+        }
+        visit(&tree, &mut files);
+        // pkg's rules reach only into pkg, anchored to pkg, and override the root's
+        assert_eq!(
+            files,
+            [
+                Path::new("repo/other/fixtures/w.rs"),
+                Path::new("repo/pkg/trace.log"),
+                Path::new("repo/pkg/sub/fixtures/y.rs"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn comment_lines() {
+        let rust = "/* a\n\n   b */ fn main() {\n    // hi\n    let x = 1; // one\n}\n";
+        assert_eq!(line_kinds(rust.as_bytes(), Some("rust")), (1, 2));
+        assert_eq!(
+            line_kinds(b"#!/bin/sh\n\n# x\necho\n", Some("shell")),
+            (1, 2)
+        );
+        assert_eq!(
+            line_kinds(b"--[[ a\nb ]]\n-- c\nprint(1)\n", Some("lua")),
+            (0, 3)
+        );
+        assert_eq!(line_kinds(b"// not json\n\n", Some("json")), (1, 0));
+        assert_eq!(line_kinds(b"# x\n", None), (0, 0));
+    }
+
+    #[test]
+    fn wrapped_lines() -> io::Result<()> {
+        assert_eq!(visual_lines(b"", 80), 0);
+        assert_eq!(visual_lines(b"\n\n", 80), 2);
+        assert_eq!(visual_lines(&[b'x'; 161], 80), 3);
+        assert_eq!(visual_lines("\u{e9}\u{e9}\u{e9}\r\nab".as_bytes(), 2), 3);
+
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.txt", format!("{}\nshort\n", "x".repeat(100)));
+        let options = Options {
+            wrap_width: Some(40),
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options).walk(Path::new("r"))?;
+        assert_eq!((tree.lines, tree.visual), (2, 4));
+        Ok(())
+    }
+
+    // MemoryFs would hand the content out; a binary that is read at all fails the walk
+    struct Unreadable(MemoryFs);
+
+    impl FileSystem for Unreadable {
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            self.0.read_dir(path)
+        }
+        fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+            self.0.metadata(path)
+        }
+        fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read + '_>> {
+            match is_binary(path) {
+                true => Err(io::Error::other(format!("{} opened", path.display()))),
+                false => self.0.open(path),
+            }
+        }
+    }
+
+    #[test]
+    fn binaries_unopened() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/clip.mp4", vec![b'\n'; 64]);
+        memory.insert("r/a.rs", "1\n");
+        let fs = Unreadable(memory);
+        let tree = Walker::new(&fs, Options::default()).walk(Path::new("r"))?;
+        assert_eq!((tree.lines, tree.bytes), (1, 66));
+
+        let read = Options {
+            read_binary: true,
+            ..Options::default()
+        };
+        assert!(Walker::new(&fs, read).walk(Path::new("r")).is_err());
+        Ok(())
+    }
+
+    // `secret/` stats fine but may not be listed, like a directory without read permission
+    struct Locked(MemoryFs);
+
+    impl FileSystem for Locked {
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            match path.ends_with("secret") {
+                true => Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+                false => self.0.read_dir(path),
+            }
+        }
+        fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+            let metadata = self.0.metadata(path)?;
+            match path.ends_with("secret") {
+                true => Ok(Metadata {
+                    len: 4096,
+                    ..metadata
+                }),
+                false => Ok(metadata),
+            }
+        }
+        fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read + '_>> {
+            self.0.open(path)
+        }
+    }
+
+    #[test]
+    fn unscanned_dirs() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        memory.insert("r/sub/secret/key.pem", "x\n");
+        let fs = Locked(memory);
+        let tree = Walker::new(&fs, Options::default()).walk(Path::new("r"))?;
+        assert_eq!((tree.lines, tree.unscanned), (1, 4096));
+        let secret = &tree.children[1].children[0];
+        assert!(secret.unreadable() && !tree.children[1].unreadable());
+
+        // the root itself is still an error
+        let err = Walker::new(&fs, Options::default()).walk(Path::new("r/sub/secret"));
+        assert!(
+            matches!(err, Err(LcError::Io(err)) if err.kind() == io::ErrorKind::PermissionDenied)
+        );
+        Ok(())
+    }
+
+    // xorshift, so every run feeds the same inputs and a failure reproduces
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: usize) -> usize {
+            (self.next() % bound as u64) as usize
+        }
+
+        // random bytes, or runs drawn from the shapes counting code special-cases
+        fn hostile(&mut self) -> Vec<u8> {
+            const PIECES: &[&[u8]] = &[
+                b"\n",
+                b"\r\n",
+                b"\r",
+                b"\n\n\n\n",
+                b"\xef\xbb\xbf",
+                b"\xff\xfe",
+                b"\xfe\xff",
+                b"\xc3",
+                b"\xe2\x82",
+                b"\xf0\x9f\x98\x80",
+                b"\0\0",
+                b"\t",
+                b"lc:language=",
+                b"caf\xc3\xa9",
+            ];
+            let len = self.below(600);
+            let mut content = Vec::with_capacity(len);
+            while content.len() < len {
+                match self.below(3) {
+                    0 => content.push(self.next() as u8),
+                    _ => content.extend_from_slice(PIECES[self.below(PIECES.len())]),
+                }
+            }
+            content
+        }
+    }
+
+    // hands out a few bytes per read, with the odd interruption, so every line and
+    // multi-byte character ends up split across reads somewhere
+    struct Trickle(MemoryFs, u64);
+
+    struct TrickleReader<'a>(Box<dyn io::Read + 'a>, Rng);
+
+    impl io::Read for TrickleReader<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            if self.1.below(8) == 0 {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+            let len = buffer.len().min(1 + self.1.below(7));
+            self.0.read(&mut buffer[..len])
+        }
+    }
+
+    impl FileSystem for Trickle {
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            self.0.read_dir(path)
+        }
+        fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+            self.0.metadata(path)
+        }
+        fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read + '_>> {
+            Ok(Box::new(TrickleReader(self.0.open(path)?, Rng(self.1))))
+        }
+    }
+
+    #[test]
+    fn hostile_input() -> io::Result<()> {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let options = Options {
+            entropy: true,
+            encodings: true,
+            hashes: true,
+            wrap_width: Some(7),
+            ..Options::default()
+        };
+        for round in 0..300 {
+            let content = rng.hostile();
+            let expected = crate::verify::by_lines_iterator(&content);
+            let mut memory = MemoryFs::new();
+            memory.insert("r/input.txt", content.clone());
+            let fs = Trickle(memory, rng.next() | 1);
+            let path = PathBuf::from("r/input.txt");
+            let metadata = fs.metadata(&path)?;
+            let walker = Walker::new(&fs, options.clone());
+
+            let read = walker.walk(&path)?;
+            let streamed = walker.stream_file(path.clone(), &metadata, None)?;
+            let chunked = crate::verify::by_chunks(&mut fs.open(&path)?)?;
+            assert_eq!(
+                (streamed.lines, chunked, count_lines(&content)),
+                (expected, expected, expected),
+                "round {round}: {content:?}"
+            );
+            // a UTF-16 guess counts code units instead, so the read path is held to that
+            let decoded = encoding::count(&content, encoding::detect(&content));
+            assert_eq!(
+                (read.lines, read.chars),
+                decoded,
+                "round {round}: {content:?}"
+            );
+            assert_eq!(streamed.bytes, content.len() as u64);
+            assert!(read.visual >= read.lines);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn modified_since() -> io::Result<()> {
+        let root = std::env::temp_dir().join(format!("lc-since-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        for (path, secs) in [("old/a.rs", 1_000), ("new/b.rs", 3_000), ("c.rs", 2_000)] {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, "1\n")?;
+            std::fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(at(secs))?;
+        }
+        let options = Options {
+            modified_since: Some(at(2_000)),
+            ..Options::default()
+        };
+        let tree = Walker::new(&RealFs, options).walk(&root);
+        std::fs::remove_dir_all(&root)?;
+        let tree = tree?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        // strictly newer: c.rs, modified at the very moment, is left out too
+        assert_eq!(names, ["new"]);
+        Ok(())
+    }
+
+    #[test]
+    fn sparse_large_file() -> io::Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+        let root = std::env::temp_dir().join(format!("lc-sparse-{}", std::process::id()));
+        std::fs::create_dir_all(&root)?;
+        let path = root.join("huge.log");
+        let mut file = std::fs::File::create(&path)?;
+        file.write_all(b"a\nb\n")?;
+        // past 4 GiB, so any 32-bit length or offset along the way would wrap
+        file.set_len((4 << 30) + 4)?;
+        file.seek(SeekFrom::End(-1))?;
+        file.write_all(b"z")?;
+        drop(file);
+
+        let tree = Walker::new(&RealFs, Options::default()).walk(&root);
+        std::fs::remove_dir_all(&root)?;
+        let tree = tree?;
+        assert_eq!((tree.lines, tree.bytes), (3, (4 << 30) + 4));
+        Ok(())
+    }
+
+    #[test]
+    fn junk_files() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        memory.insert("r/a.rs~", "1\n");
+        memory.insert("r/.a.rs.swp", "1\n");
+        memory.insert("r/#a.rs#", "1\n");
+        memory.insert("r/sub/.DS_Store", "1\n");
+        memory.insert("r/sub/scratch.tmp", "1\n");
+        let walker = Walker::new(&memory, Options::default());
+        assert_eq!(walker.walk(Path::new("r"))?.lines, 1);
+        assert_eq!(walker.junk(), 5);
+
+        let options = Options {
+            include_junk: true,
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        // dotfiles stay hidden either way
+        assert_eq!(walker.walk(Path::new("r"))?.lines, 4);
+        assert_eq!(walker.junk(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn generated_files() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/Cargo.lock", "1\n2\n3\n");
+        memory.insert("r/src/main.rs", "1\n");
+        memory.insert("r/src/schema.rs", "// @generated by diesel\n1\n");
+        memory.insert("r/gen/api.pb.go", "1\n");
+        let options = Options {
+            no_generated: true,
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        let tree = walker.walk(Path::new("r"))?;
+        let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+        assert_eq!(names, ["src"]);
+        assert_eq!((tree.files(), tree.lines), (1, 1));
+        assert_eq!(walker.generated(), (3, 6));
+        Ok(())
+    }
+
+    #[test]
+    fn piped() -> Result<(), LcError> {
+        let memory = MemoryFs::new();
+        let log = "GET /\n".repeat(STREAM_CHUNK) + "POST /";
+        let tree = Walker::new(&memory, Options::default())
+            .walk_reader(Path::new("-"), &mut log.as_bytes())?;
+        assert_eq!(
+            (tree.kind, tree.lines, tree.bytes),
+            (Kind::File, STREAM_CHUNK as u128 + 1, log.len() as u64)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_code() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/src/parser.rs", "1\n2\n3\n");
+        memory.insert("r/src/parser_test.go", "1\n");
+        memory.insert("r/tests/cli.rs", "1\n2\n");
+        let walk = |tests| {
+            let options = Options {
+                tests,
+                ..Options::default()
+            };
+            let tree = Walker::new(&memory, options).walk(Path::new("r"))?;
+            let names: Vec<&str> = tree.children.iter().map(Entry::name).collect();
+            Ok::<_, LcError>((names.join(" "), tree.lines))
+        };
+        assert_eq!(walk(Some(true))?, ("src tests".to_string(), 3));
+        assert_eq!(walk(Some(false))?, ("src".to_string(), 3));
+        assert_eq!(walk(None)?.1, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn lfs_pointers() -> io::Result<()> {
+        let pointer = "version https://git-lfs.github.com/spec/v1\noid sha256:00\nsize 5000000\n";
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n2\n");
+        memory.insert("r/intro.mp4", pointer);
+        memory.insert("r/data.csv", pointer);
+        let walk = |lfs| {
+            let walker = Walker::new(
+                &memory,
+                Options {
+                    lfs,
+                    ..Options::default()
+                },
+            );
+            let tree = walker.walk(Path::new("r"))?;
+            Ok::<_, LcError>((tree.files(), tree.lines, tree.bytes, walker.lfs()))
+        };
+        let size = pointer.len() as u64;
+        assert_eq!(walk(false)?, (3, 5, 4 + 2 * size, (0, 0)));
+        assert_eq!(walk(true)?, (3, 2, 4 + 10_000_000, (2, 10_000_000)));
+        Ok(())
+    }
+
+    #[test]
+    fn streamed_without_generated() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/package-lock.json", "{}\n");
+        memory.insert("r/index.js", "1\n");
+        let seen = RefCell::new(Vec::new());
+        let record = |entry: &Entry| seen.borrow_mut().push(entry.path.clone());
+        let options = Options {
+            no_generated: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options)
+            .on_file(&record)
+            .walk(Path::new("r"))?;
+        assert_eq!(seen.into_inner(), [PathBuf::from("r/index.js")]);
+        assert_eq!(tree.files(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn large_files() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n2\n");
+        memory.insert("r/huge.log", "1\n".repeat(1000));
+        memory.insert("r/huge.zip", "x".repeat(1000));
+        let options = Options {
+            skip_larger_than: Some(100),
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        let tree = walker.walk(Path::new("r"))?;
+        assert_eq!((tree.files(), tree.lines, tree.bytes), (3, 2, 3004));
+        // the zip was never going to be read, so it isn't one of them
+        assert_eq!(walker.large_skipped(), (1, 2000));
+        Ok(())
+    }
+
+    #[test]
+    fn documents() -> io::Result<()> {
+        let pdf = "%PDF-1.4\n1 0 obj << /Type /Page >> endobj\n\
+            2 0 obj << /Length 20 >>\nstream\nBT (Hi) Tj ET\nendstream\n";
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        memory.insert("r/spec.pdf", pdf);
+        memory.insert("r/broken.docx", "\n\n\n");
+        let walker = Walker::new(&memory, Options::default());
+        assert_eq!(walker.walk(Path::new("r"))?.lines, 1);
+        assert_eq!(walker.documents(), (2, 1));
+
+        let options = Options {
+            extract_documents: true,
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        assert_eq!(walker.walk(Path::new("r"))?.lines, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn streamed_files() -> io::Result<()> {
+        let memory = memory();
+        let seen = RefCell::new(Vec::new());
+        let record = |entry: &Entry| seen.borrow_mut().push(entry.path.clone());
+        Walker::new(&memory, Options::default())
+            .on_file(&record)
+            .walk(Path::new("repo"))?;
+        assert_eq!(
+            seen.into_inner(),
+            [
+                PathBuf::from("repo/main.rs"),
+                PathBuf::from("repo/build/out.txt"),
+                PathBuf::from("repo/src/lib.rs")
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn walk_files() -> io::Result<()> {
+        let memory = memory();
+        let listed = vec![
+            PathBuf::from("repo/.hidden"),
+            PathBuf::from("repo/missing.rs"),
+            PathBuf::from("repo/src"),
+            PathBuf::from("repo/src/lib.rs"),
+        ];
+        let tree =
+            Walker::new(&memory, Options::default()).walk_files(Path::new("list"), listed)?;
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.lines, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_excludes() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/Cargo.toml", "[package]\n");
+        memory.insert("r/target/debug/out", "1\n2\n3\n");
+        memory.insert("r/sub/target/kept.rs", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        assert_eq!(tree.lines, 2);
+
+        let keep_cargo = Options {
+            disabled_rules: vec!["cargo".to_string()],
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, keep_cargo).walk(Path::new("r"))?;
+        assert_eq!(tree.lines, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn explain_agrees_with_walk() -> io::Result<()> {
+        let files = [
+            "r/Cargo.toml",
+            "r/.gitignore",
+            "r/src/a.rs",
+            "r/src/b.txt",
+            "r/src/.lcignore",
+            "r/src/gen/x.rs",
+            "r/logs/a.log",
+            "r/logs/keep.log",
+            "r/target/out.rs",
+            "r/web/node_modules/m.rs",
+            "r/notes.rs~",
+            "r/docs/skip.rs",
+        ];
+        let mut memory = MemoryFs::new();
+        for file in files {
+            memory.insert(file, "1\n");
+        }
+        memory.insert("r/.gitignore", "*.log\n!keep.log\n");
+        memory.insert("r/src/.lcignore", "gen/\n");
+        let options = Options {
+            ignore: true,
+            default_ignores: true,
+            exclude: vec![Glob::new("docs/")],
+            skip_extensions: vec!["txt".to_string()],
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        let tree = walker.walk(Path::new("r"))?;
+        let mut counted = Vec::new();
+        fn collect(entry: &Entry, counted: &mut Vec<PathBuf>) {
+            match entry.kind {
+                Kind::Dir => entry
+                    .children
+                    .iter()
+                    .for_each(|child| collect(child, counted)),
+                _ => counted.push(entry.path.clone()),
+            }
+        }
+        collect(&tree, &mut counted);
+
+        let mut reasons = Vec::new();
+        for file in files {
+            let verdict = walker.explain(Path::new("r"), Path::new(file))?;
+            let in_tree = counted.iter().any(|path| path == Path::new(file));
+            assert_eq!(matches!(verdict, Verdict::Counted(..)), in_tree, "{file}");
+            match verdict {
+                Verdict::Skipped(path, why) => reasons.push(format!("{}: {why}", path.display())),
+                Verdict::Counted(_, Some(rule)) => reasons.push(format!("{file}: {rule}")),
+                Verdict::Counted(_, None) => {}
+            }
+        }
+        assert_eq!(
+            reasons,
+            [
+                "r/.gitignore: its name starts with a dot, see --hidden",
+                "r/src/b.txt: excluded by --no-ext txt",
+                "r/src/.lcignore: its name starts with a dot, see --hidden",
+                "r/src/gen: ignored by r/src/.lcignore:1: gen/",
+                "r/logs/a.log: ignored by r/.gitignore:1: *.log",
+                "r/logs/keep.log: r/.gitignore:2: !keep.log",
+                "r/target: build output next to Cargo.toml, see --no-manifest-rule cargo",
+                "r/web/node_modules: a default exclusion, see --no-default-ignores",
+                "r/notes.rs~: an editor temp or backup file, see --include-junk",
+                "r/docs: excluded by --exclude 'docs'",
+            ]
+        );
+        assert!(matches!(
+            walker.explain(Path::new("r"), Path::new("elsewhere/a.rs")),
+            Err(LcError::InvalidOptions(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn hidden_and_vcs() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/a.rs", "1\n");
+        memory.insert("r/.git/objects/pack/p.pack", "1\n2\n");
+        memory.insert("r/sub/.hg/store/data", "1\n");
+        memory.insert("r/.svn", "1\n");
+        memory.insert("r/.hidden/x", "1\n");
+        let walker = Walker::new(&memory, Options::default());
+        let tree = walker.walk(Path::new("r"))?;
+        assert_eq!((tree.lines, walker.vcs_skipped()), (1, 3));
+
+        let options = Options {
+            include_vcs: true,
+            default_ignores: true,
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        let tree = walker.walk(Path::new("r"))?;
+        assert_eq!((tree.lines, walker.vcs_skipped()), (5, 0));
+
+        let options = Options {
+            hidden: true,
+            ..Options::default()
+        };
+        let walker = Walker::new(&memory, options);
+        let tree = walker.walk(Path::new("r"))?;
+        assert_eq!((tree.lines, walker.vcs_skipped()), (2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn default_ignores() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/src/a.rs", "1\n");
+        memory.insert("r/web/node_modules/x.js", "1\n2\n");
+        memory.insert("r/vendor/lib.go", "1\n2\n");
+        memory.insert("r/src/build/gen.c", "1\n2\n");
+        memory.insert("r/src/builder.rs", "1\n");
+        let options = Options {
+            default_ignores: true,
+            ..Options::default()
+        };
+        let tree = Walker::new(&memory, options.clone()).walk(Path::new("r"))?;
+        assert_eq!((tree.files(), tree.lines), (2, 2));
+        // a root is counted even when its own name is on the list
+        let tree = Walker::new(&memory, options).walk(Path::new("r/vendor"))?;
+        assert_eq!(tree.lines, 2);
+
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        assert_eq!(tree.lines, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn line_counting() {
+        for sample in ["", "\n", "a", "a\n", "a\r\nb", "a\n\nb\n", "\u{fffd}\n\r"] {
+            assert_eq!(
+                count_lines(sample.as_bytes()),
+                sample.lines().count() as u128
+            );
+        }
+        let invalid = b"\xff\xfe\nok";
+        let lossy = String::from_utf8_lossy(invalid).lines().count() as u128;
+        assert_eq!(count_lines(invalid), lossy);
+    }
+
+    #[test]
+    fn small_and_large_files() -> io::Result<()> {
+        let mut memory = MemoryFs::new();
+        memory.insert("r/small.txt", "1\n2\n");
+        memory.insert("r/large.txt", "x\n".repeat(SMALL_FILE as usize));
+        memory.insert("r/tiny.txt", "1\n");
+        let tree = Walker::new(&memory, Options::default()).walk(Path::new("r"))?;
+        let lines: Vec<u128> = tree.children.iter().map(|child| child.lines).collect();
+        assert_eq!(lines, [SMALL_FILE as u128, 2, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn dir_bytes() -> io::Result<()> {
+        let root = std::env::temp_dir().join(format!("lc-test-{}-dir-bytes", std::process::id()));
+        std::fs::create_dir_all(root.join("sub"))?;
+        std::fs::write(root.join("sub/a.txt"), "12345")?;
+        let entry_sizes =
+            std::fs::metadata(&root)?.len() + std::fs::metadata(root.join("sub"))?.len();
+
+        let plain = Walker::new(&crate::vfs::RealFs, Options::default()).walk(&root)?;
+        assert_eq!((plain.bytes, plain.dir_bytes), (5, entry_sizes));
+        let with_dirs = Options {
+            include_dir_bytes: true,
+            ..Options::default()
+        };
+        let du = Walker::new(&crate::vfs::RealFs, with_dirs).walk(&root)?;
+        assert_eq!((du.bytes, du.dir_bytes), (5 + entry_sizes, entry_sizes));
+        Ok(())
+    }
+
+    #[test]
+    fn file_root() -> io::Result<()> {
+        let memory = memory();
+        let file = Walker::new(&memory, Options::default()).walk(Path::new("repo/main.rs"))?;
+        assert_eq!((file.kind, file.lines), (Kind::File, 2));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fifo_root() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("lc-test-{}-fifo", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let (fed, silent) = (dir.join("fed"), dir.join("silent"));
+        for fifo in [&fed, &silent] {
+            let _ = std::fs::remove_file(fifo);
+            assert!(std::process::Command::new("mkfifo")
+                .arg(fifo)
+                .status()?
+                .success());
+        }
+        let options = Options {
+            special_timeout: Duration::from_millis(300),
+            ..Options::default()
+        };
+        let walker = Walker::new(&crate::vfs::RealFs, options);
+
+        let writer = {
+            let fed = fed.clone();
+            std::thread::spawn(move || std::fs::write(fed, "a\nb\nc"))
+        };
+        let entry = walker.walk(&fed)?;
+        writer.join().unwrap()?;
+        assert_eq!((entry.lines, entry.bytes), (3, 5));
+
+        let err = walker.walk(&silent);
+        assert!(matches!(err, Err(LcError::Io(err)) if err.kind() == io::ErrorKind::TimedOut));
+        Ok(())
+    }
+
+    #[test]
+    fn walk_options() -> io::Result<()> {
+        let memory = memory();
+        let ignore = Options {
+            ignore: true,
+            ..Options::default()
+        };
+        let flat = Options {
+            recurse: false,
+            ..Options::default()
+        };
+        assert_eq!(
+            Walker::new(&memory, ignore).walk(Path::new("repo"))?.lines,
+            5
+        );
+        assert_eq!(Walker::new(&memory, flat).walk(Path::new("repo"))?.lines, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+// XXH3-64 with seed 0 and the default secret, the hash `xxhsum -H3` prints: fast, and the
+// same on every machine and build, so `--hash xxh3` values can be kept between runs
+const SECRET: [u8; 192] = [
+    0xb8, 0xfe, 0x6c, 0x39, 0x23, 0xa4, 0x4b, 0xbe, 0x7c, 0x01, 0x81, 0x2c, 0xf7, 0x21, 0xad, 0x1c,
+    0xde, 0xd4, 0x6d, 0xe9, 0x83, 0x90, 0x97, 0xdb, 0x72, 0x40, 0xa4, 0xa4, 0xb7, 0xb3, 0x67, 0x1f,
+    0xcb, 0x79, 0xe6, 0x4e, 0xcc, 0xc0, 0xe5, 0x78, 0x82, 0x5a, 0xd0, 0x7d, 0xcc, 0xff, 0x72, 0x21,
+    0xb8, 0x08, 0x46, 0x74, 0xf7, 0x43, 0x24, 0x8e, 0xe0, 0x35, 0x90, 0xe6, 0x81, 0x3a, 0x26, 0x4c,
+    0x3c, 0x28, 0x52, 0xbb, 0x91, 0xc3, 0x00, 0xcb, 0x88, 0xd0, 0x65, 0x8b, 0x1b, 0x53, 0x2e, 0xa3,
+    0x71, 0x64, 0x48, 0x97, 0xa2, 0x0d, 0xf9, 0x4e, 0x38, 0x19, 0xef, 0x46, 0xa9, 0xde, 0xac, 0xd8,
+    0xa8, 0xfa, 0x76, 0x3f, 0xe3, 0x9c, 0x34, 0x3f, 0xf9, 0xdc, 0xbb, 0xc7, 0xc7, 0x0b, 0x4f, 0x1d,
+    0x8a, 0x51, 0xe0, 0x4b, 0xcd, 0xb4, 0x59, 0x31, 0xc8, 0x9f, 0x7e, 0xc9, 0xd9, 0x78, 0x73, 0x64,
+    0xea, 0xc5, 0xac, 0x83, 0x34, 0xd3, 0xeb, 0xc3, 0xc5, 0x81, 0xa0, 0xff, 0xfa, 0x13, 0x63, 0xeb,
+    0x17, 0x0d, 0xdd, 0x51, 0xb7, 0xf0, 0xda, 0x49, 0xd3, 0x16, 0x55, 0x26, 0x29, 0xd4, 0x68, 0x9e,
+    0x2b, 0x16, 0xbe, 0x58, 0x7d, 0x47, 0xa1, 0xfc, 0x8f, 0xf8, 0xb8, 0xd1, 0x7a, 0xd0, 0x31, 0xce,
+    0x45, 0xcb, 0x3a, 0x8f, 0x95, 0x16, 0x04, 0x28, 0xaf, 0xd7, 0xfb, 0xca, 0xbb, 0x4b, 0x40, 0x7e,
+];
+
+const PRIME32_1: u64 = 0x9e37_79b1;
+const PRIME32_2: u64 = 0x85eb_ca77;
+const PRIME32_3: u64 = 0xc2b2_ae3d;
+const PRIME64_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME64_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME64_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME64_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME64_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+const STRIPE: usize = 64;
+const STRIPES_PER_BLOCK: usize = (SECRET.len() - STRIPE) / 8;
+// inputs up to this long are hashed whole, without the accumulators
+const MIDSIZE_MAX: usize = 240;
+
+fn read32(bytes: &[u8], at: usize) -> u64 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as u64
+}
+
+fn read64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+fn fold64(a: u64, b: u64) -> u64 {
+    let product = a as u128 * b as u128;
+    product as u64 ^ (product >> 64) as u64
+}
+
+fn xxh64_avalanche(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME64_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^ (hash >> 32)
+}
+
+fn avalanche(mut hash: u64) -> u64 {
+    hash ^= hash >> 37;
+    hash = hash.wrapping_mul(0x1656_6791_9e37_79f9);
+    hash ^ (hash >> 32)
+}
+
+fn rrmxmx(mut hash: u64, len: usize) -> u64 {
+    hash ^= hash.rotate_left(49) ^ hash.rotate_left(24);
+    hash = hash.wrapping_mul(0x9fb2_1c65_1e98_df25);
+    hash ^= (hash >> 35).wrapping_add(len as u64);
+    hash = hash.wrapping_mul(0x9fb2_1c65_1e98_df25);
+    hash ^ (hash >> 28)
+}
+
+fn mix16(input: &[u8], at: usize, secret: usize) -> u64 {
+    fold64(
+        read64(input, at) ^ read64(&SECRET, secret),
+        read64(input, at + 8) ^ read64(&SECRET, secret + 8),
+    )
+}
+
+fn short(input: &[u8]) -> u64 {
+    let len = input.len();
+    match len {
+        0 => xxh64_avalanche(read64(&SECRET, 56) ^ read64(&SECRET, 64)),
+        1..=3 => {
+            let combined = (input[0] as u64) << 16
+                | (input[len >> 1] as u64) << 24
+                | input[len - 1] as u64
+                | (len as u64) << 8;
+            xxh64_avalanche(combined ^ (read32(&SECRET, 0) ^ read32(&SECRET, 4)))
+        }
+        4..=8 => {
+            let combined = read32(input, len - 4).wrapping_add(read32(input, 0) << 32);
+            rrmxmx(combined ^ (read64(&SECRET, 8) ^ read64(&SECRET, 16)), len)
+        }
+        9..=16 => {
+            let low = read64(input, 0) ^ (read64(&SECRET, 24) ^ read64(&SECRET, 32));
+            let high = read64(input, len - 8) ^ (read64(&SECRET, 40) ^ read64(&SECRET, 48));
+            let acc = (len as u64)
+                .wrapping_add(low.swap_bytes())
+                .wrapping_add(high)
+                .wrapping_add(fold64(low, high));
+            avalanche(acc)
+        }
+        17..=128 => {
+            let mut acc = (len as u64).wrapping_mul(PRIME64_1);
+            let rounds = (len - 1) / 32;
+            for round in (0..=rounds).rev() {
+                acc = acc.wrapping_add(mix16(input, 16 * round, 32 * round));
+                acc = acc.wrapping_add(mix16(input, len - 16 * (round + 1), 32 * round + 16));
+            }
+            avalanche(acc)
+        }
+        _ => {
+            let mut acc = (len as u64).wrapping_mul(PRIME64_1);
+            for round in 0..8 {
+                acc = acc.wrapping_add(mix16(input, 16 * round, 16 * round));
+            }
+            acc = avalanche(acc);
+            for round in 8..len / 16 {
+                acc = acc.wrapping_add(mix16(input, 16 * round, 16 * (round - 8) + 3));
+            }
+            avalanche(acc.wrapping_add(mix16(input, len - 16, 136 - 17)))
+        }
+    }
+}
+
+fn accumulate(acc: &mut [u64; 8], stripe: &[u8], secret: usize) {
+    for lane in 0..8 {
+        let value = read64(stripe, 8 * lane);
+        let key = value ^ read64(&SECRET, secret + 8 * lane);
+        acc[lane ^ 1] = acc[lane ^ 1].wrapping_add(value);
+        acc[lane] = acc[lane].wrapping_add((key & 0xffff_ffff).wrapping_mul(key >> 32));
+    }
+}
+
+fn scramble(acc: &mut [u64; 8]) {
+    for (lane, acc) in acc.iter_mut().enumerate() {
+        *acc ^= *acc >> 47;
+        *acc ^= read64(&SECRET, SECRET.len() - STRIPE + 8 * lane);
+        *acc = acc.wrapping_mul(PRIME32_1);
+    }
+}
+
+// fed in pieces of any size, for files streamed rather than read whole
+pub struct Xxh3 {
+    acc: [u64; 8],
+    stripes: usize,
+    len: usize,
+    // everything while the input could still be short, then what isn't yet in `acc`
+    pending: Vec<u8>,
+    // the last stripe taken into `acc`, as the final stripe may reach back into it
+    previous: [u8; STRIPE],
+}
+
+impl Default for Xxh3 {
+    fn default() -> Xxh3 {
+        Xxh3 {
+            acc: [
+                PRIME32_3, PRIME64_1, PRIME64_2, PRIME64_3, PRIME64_4, PRIME32_2, PRIME64_5,
+                PRIME32_1,
+            ],
+            stripes: 0,
+            len: 0,
+            pending: Vec::new(),
+            previous: [0; STRIPE],
+        }
+    }
+}
+
+impl Xxh3 {
+    pub fn write(&mut self, input: &[u8]) {
+        self.len += input.len();
+        self.pending.extend_from_slice(input);
+        if self.len > MIDSIZE_MAX {
+            let pending = std::mem::take(&mut self.pending);
+            self.pending = self.stripes(&pending).to_vec();
+        }
+    }
+
+    // takes in every stripe that has at least a byte after it, and leaves the rest
+    fn stripes<'a>(&mut self, mut input: &'a [u8]) -> &'a [u8] {
+        while input.len() > STRIPE {
+            self.stripe(&input[..STRIPE]);
+            input = &input[STRIPE..];
+        }
+        input
+    }
+
+    fn stripe(&mut self, stripe: &[u8]) {
+        accumulate(
+            &mut self.acc,
+            stripe,
+            8 * (self.stripes % STRIPES_PER_BLOCK),
+        );
+        self.stripes += 1;
+        if self.stripes.is_multiple_of(STRIPES_PER_BLOCK) {
+            scramble(&mut self.acc);
+        }
+        self.previous.copy_from_slice(stripe);
+    }
+
+    pub fn finish(&self) -> u64 {
+        if self.len <= MIDSIZE_MAX {
+            return short(&self.pending);
+        }
+        let mut last = [0; STRIPE];
+        let reach = STRIPE - self.pending.len();
+        last[..reach].copy_from_slice(&self.previous[STRIPE - reach..]);
+        last[reach..].copy_from_slice(&self.pending);
+        let mut acc = self.acc;
+        accumulate(&mut acc, &last, SECRET.len() - STRIPE - 7);
+
+        let mut hash = (self.len as u64).wrapping_mul(PRIME64_1);
+        for pair in 0..4 {
+            let secret = 11 + 16 * pair;
+            hash = hash.wrapping_add(fold64(
+                acc[2 * pair] ^ read64(&SECRET, secret),
+                acc[2 * pair + 1] ^ read64(&SECRET, secret + 8),
+            ));
+        }
+        avalanche(hash)
+    }
+}
+
+pub fn xxh3(input: &[u8]) -> u64 {
+    if input.len() <= MIDSIZE_MAX {
+        return short(input);
+    }
+    let mut hasher = Xxh3 {
+        len: input.len(),
+        ..Xxh3::default()
+    };
+    hasher.pending = hasher.stripes(input).to_vec();
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // from libxxhash 0.8 over `(i * 7 + 3) % 256`, one length in every branch and around
+    // every stripe and block boundary
+    const EXPECTED: [(usize, u64); 33] = [
+        (0, 3244421341483603138),
+        (1, 1433843135270481901),
+        (2, 2058273368586827884),
+        (3, 12180141160879835164),
+        (4, 7895465118229274323),
+        (5, 11062565685660633911),
+        (8, 6941064856527638883),
+        (9, 18374517719163151272),
+        (15, 11439541028034589121),
+        (16, 13314990914799711621),
+        (17, 8163341949877205007),
+        (31, 17096134949297616051),
+        (32, 1873302701886544469),
+        (33, 4486878507168070088),
+        (64, 2917965298538373825),
+        (65, 9408609914592003654),
+        (96, 17331232145433315139),
+        (97, 2137665177113230204),
+        (128, 7440608504995537343),
+        (129, 14295761343243267012),
+        (200, 8389308914288017243),
+        (239, 2127415575477209850),
+        (240, 7229805477010515663),
+        (241, 10082113959289486871),
+        (255, 13149215926256561209),
+        (256, 4339360625268998362),
+        (1023, 15161797645365529668),
+        (1024, 11205349619999208113),
+        (1025, 9253807012321506678),
+        (1088, 3424848841118183642),
+        (2048, 12386592778227166929),
+        (5000, 8762507191635236893),
+        (100000, 866221029296703860),
+    ];
+
+    #[test]
+    fn known_values() {
+        assert_eq!(xxh3(b"abc"), 0x78af_5f94_892f_3950);
+        for (len, expected) in EXPECTED {
+            let input: Vec<u8> = (0..len).map(|i| (i * 7 + 3) as u8).collect();
+            assert_eq!(xxh3(&input), expected, "{len} bytes");
+            for piece in [1, 63, 64, 100, 4096] {
+                let mut hasher = Xxh3::default();
+                input.chunks(piece).for_each(|chunk| hasher.write(chunk));
+                assert_eq!(hasher.finish(), expected, "{len} bytes in {piece}s");
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::json::{escape, Json};
+
+// the JSON report in block style; strings stay double-quoted, which YAML reads as-is
+pub fn render(value: &Json) -> String {
+    let mut out = String::new();
+    match value {
+        Json::Object(fields) if !fields.is_empty() => mapping(fields, 0, &mut out),
+        Json::Array(values) if !values.is_empty() => sequence(values, 0, &mut out),
+        value => {
+            out.push_str(&scalar(value));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn scalar(value: &Json) -> String {
+    match value {
+        Json::Str(value) => escape(value),
+        Json::Array(_) => "[]".to_string(),
+        Json::Object(_) => "{}".to_string(),
+        value => value.to_string(),
+    }
+}
+
+fn key(key: &str) -> String {
+    let plain = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    ternary!(plain => key.to_string(); escape(key))
+}
+
+fn mapping(fields: &[(String, Json)], indent: usize, out: &mut String) {
+    for (name, value) in fields {
+        out.push_str(&" ".repeat(indent));
+        out.push_str(&key(name));
+        out.push(':');
+        match value {
+            Json::Object(fields) if !fields.is_empty() => {
+                out.push('\n');
+                mapping(fields, indent + 2, out);
+            }
+            Json::Array(values) if !values.is_empty() => {
+                out.push('\n');
+                sequence(values, indent + 2, out);
+            }
+            value => {
+                out.push(' ');
+                out.push_str(&scalar(value));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn sequence(values: &[Json], indent: usize, out: &mut String) {
+    for value in values {
+        // nested blocks are rendered one level deeper, then their first indent becomes the dash
+        let mut block = String::new();
+        match value {
+            Json::Object(fields) if !fields.is_empty() => mapping(fields, indent + 2, &mut block),
+            Json::Array(values) if !values.is_empty() => sequence(values, indent + 2, &mut block),
+            value => block = format!("{}{}\n", " ".repeat(indent + 2), scalar(value)),
+        }
+        out.push_str(&" ".repeat(indent));
+        out.push_str("- ");
+        out.push_str(&block[indent + 2..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_style() {
+        let value = Json::object(vec![
+            ("root", Json::str("a: b")),
+            (
+                "totals",
+                Json::object(vec![
+                    ("lines", Json::from(3u128)),
+                    ("ratio", Json::from(0.5)),
+                ]),
+            ),
+            ("cache", Json::Null),
+            ("empty", Json::Array(vec![])),
+            (
+                "files",
+                Json::Array(vec![
+                    Json::object(vec![
+                        ("path", Json::str("x.rs")),
+                        ("lines", Json::from(2u128)),
+                    ]),
+                    Json::Array(vec![Json::from(true), Json::from(false)]),
+                ]),
+            ),
+            ("odd key", Json::from(1u128)),
+        ]);
+        assert_eq!(
+            render(&value),
+            "root: \"a: b\"\n\
+             totals:\n  lines: 3\n  ratio: 0.5\n\
+             cache: null\n\
+             empty: []\n\
+             files:\n  - path: \"x.rs\"\n    lines: 2\n  - - true\n    - false\n\
+             \"odd key\": 1\n"
+        );
+    }
+}
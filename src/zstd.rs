@@ -0,0 +1,526 @@
+// a zstd encoder for `--compress zstd`: greedy LZ matches coded with the format's predefined
+// FSE tables and raw literals, so no table descriptions are written. Any zstd decoder reads
+// the result; it compresses less than libzstd but needs nothing beyond std.
+
+const MAGIC: u32 = 0xFD2F_B528;
+// 4 MiB, written as exponent 12 and mantissa 0
+const WINDOW_LOG: u32 = 22;
+const BLOCK_SIZE: usize = 128 * 1024;
+const MIN_MATCH: usize = 4;
+const HASH_LOG: u32 = 16;
+
+// RFC 8878's default distributions, -1 being a "less than one" probability
+const LL_LOG: u32 = 6;
+const LL_NORM: [i16; 36] = [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1,
+    -1, -1, -1, -1,
+];
+const ML_LOG: u32 = 6;
+const ML_NORM: [i16; 53] = [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+];
+const OF_LOG: u32 = 5;
+const OF_NORM: [i16; 29] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+];
+
+// baseline and extra bits of every literal length and match length code
+const LL_BASE: [u32; 36] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 20, 22, 24, 28, 32, 40, 48, 64,
+    128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 65536,
+];
+const LL_BITS: [u32; 36] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 4, 6, 7, 8, 9, 10, 11,
+    12, 13, 14, 15, 16,
+];
+const ML_BASE: [u32; 53] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+    28, 29, 30, 31, 32, 33, 34, 35, 37, 39, 41, 43, 47, 51, 59, 67, 83, 99, 131, 259, 515, 1027,
+    2051, 4099, 8195, 16387, 32771, 65539,
+];
+const ML_BITS: [u32; 53] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    1, 1, 1, 1, 2, 2, 3, 3, 4, 4, 5, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+];
+
+fn highbit(value: u32) -> u32 {
+    31 - value.leading_zeros()
+}
+
+// the last code whose baseline is at most `value`
+fn code(bases: &[u32], value: u32) -> usize {
+    bases.partition_point(|base| *base <= value) - 1
+}
+
+// bits go in from the low end of every byte; the decoder reads the stream backwards from
+// the closing 1 bit
+struct BitWriter {
+    bytes: Vec<u8>,
+    container: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            bytes: Vec::new(),
+            container: 0,
+            count: 0,
+        }
+    }
+
+    fn add(&mut self, value: u32, bits: u32) {
+        self.container |= (u64::from(value) & ((1 << bits) - 1)) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.bytes.push(self.container as u8);
+            self.container >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn close(mut self) -> Vec<u8> {
+        self.add(1, 1);
+        if self.count > 0 {
+            self.bytes.push(self.container as u8);
+        }
+        self.bytes
+    }
+}
+
+// the encoding side of one FSE table, laid out the way libzstd's FSE_buildCTable lays it
+// out, so it mirrors the decoding table every decoder builds from the same distribution
+struct Fse {
+    log: u32,
+    states: Vec<u32>,
+    // per symbol: (delta_bits, delta_state)
+    symbols: Vec<(u32, i32)>,
+}
+
+impl Fse {
+    fn new(norm: &[i16], log: u32) -> Fse {
+        let size = 1usize << log;
+        let mut spread = vec![0usize; size];
+        let mut cumul = vec![0usize; norm.len() + 1];
+        let mut high = size - 1;
+        for (symbol, &count) in norm.iter().enumerate() {
+            cumul[symbol + 1] = match count {
+                -1 => {
+                    spread[high] = symbol;
+                    high = high.wrapping_sub(1);
+                    cumul[symbol] + 1
+                }
+                count => cumul[symbol] + count as usize,
+            };
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in norm.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                spread[position] = symbol;
+                position = (position + step) & (size - 1);
+                while position > high {
+                    position = (position + step) & (size - 1);
+                }
+            }
+        }
+
+        let mut states = vec![0u32; size];
+        let mut next = cumul.clone();
+        for (state, &symbol) in spread.iter().enumerate() {
+            states[next[symbol]] = (size + state) as u32;
+            next[symbol] += 1;
+        }
+        let symbols = norm
+            .iter()
+            .zip(&cumul)
+            .map(|(&count, &start)| match count {
+                -1 | 1 => ((log << 16) - size as u32, start as i32 - 1),
+                count => {
+                    let count = count as u32;
+                    let bits = log - highbit(count - 1);
+                    ((bits << 16) - (count << bits), start as i32 - count as i32)
+                }
+            })
+            .collect();
+        Fse {
+            log,
+            states,
+            symbols,
+        }
+    }
+
+    fn state(&self, delta: i32, shifted: u32) -> u32 {
+        self.states[(shifted as i32 + delta) as usize]
+    }
+
+    fn init(&self, symbol: usize) -> u32 {
+        let (delta_bits, delta_state) = self.symbols[symbol];
+        let bits = (delta_bits + (1 << 15)) >> 16;
+        let value = (bits << 16) - delta_bits;
+        self.state(delta_state, value >> bits)
+    }
+
+    fn encode(&self, state: &mut u32, symbol: usize, out: &mut BitWriter) {
+        let (delta_bits, delta_state) = self.symbols[symbol];
+        let bits = (*state + delta_bits) >> 16;
+        out.add(*state, bits);
+        *state = self.state(delta_state, *state >> bits);
+    }
+
+    fn flush(&self, state: u32, out: &mut BitWriter) {
+        out.add(state, self.log);
+    }
+}
+
+struct Sequence {
+    literals: u32,
+    offset: u32,
+    length: u32,
+}
+
+struct Tables {
+    ll: Fse,
+    ml: Fse,
+    of: Fse,
+}
+
+// greedy matching against everything in the window, blocks included
+fn sequences(input: &[u8], start: usize, end: usize, hashes: &mut [usize]) -> Vec<Sequence> {
+    let hash = |at: usize| {
+        let word = u32::from_le_bytes(input[at..at + 4].try_into().unwrap());
+        (word.wrapping_mul(2_654_435_761) >> (32 - HASH_LOG)) as usize
+    };
+    let mut sequences = Vec::new();
+    let (mut at, mut anchor) = (start, start);
+    while at + MIN_MATCH <= end {
+        let slot = hash(at);
+        // slots hold position + 1, so 0 is empty
+        let candidate = hashes[slot].wrapping_sub(1);
+        hashes[slot] = at + 1;
+        let usable = candidate < at
+            && at - candidate < 1 << WINDOW_LOG
+            && input[candidate..candidate + MIN_MATCH] == input[at..at + MIN_MATCH];
+        if !usable {
+            at += 1;
+            continue;
+        }
+        let length = MIN_MATCH
+            + (input[candidate + MIN_MATCH..].iter())
+                .zip(&input[at + MIN_MATCH..end])
+                .take_while(|(a, b)| a == b)
+                .count();
+        sequences.push(Sequence {
+            literals: (at - anchor) as u32,
+            offset: (at - candidate) as u32,
+            length: length as u32,
+        });
+        at += length;
+        anchor = at;
+    }
+    sequences
+}
+
+fn compressed_block(
+    input: &[u8],
+    start: usize,
+    end: usize,
+    hashes: &mut [usize],
+    tables: &Tables,
+) -> Vec<u8> {
+    let sequences = sequences(input, start, end, hashes);
+    let mut literals = Vec::new();
+    let mut at = start;
+    for sequence in &sequences {
+        literals.extend_from_slice(&input[at..at + sequence.literals as usize]);
+        at += (sequence.literals + sequence.length) as usize;
+    }
+    literals.extend_from_slice(&input[at..end]);
+
+    // raw literals, with the 3-byte header that has room for a whole block
+    let size = literals.len() as u32;
+    let mut block = vec![
+        0x0C | ((size & 0xF) << 4) as u8,
+        (size >> 4) as u8,
+        (size >> 12) as u8,
+    ];
+    block.extend_from_slice(&literals);
+
+    let count = sequences.len();
+    match count {
+        0..=127 => block.push(count as u8),
+        128..=0x7EFF => block.extend_from_slice(&[(count >> 8) as u8 + 128, count as u8]),
+        _ => {
+            let rest = (count - 0x7F00) as u16;
+            block.push(0xFF);
+            block.extend_from_slice(&rest.to_le_bytes());
+        }
+    }
+    if count == 0 {
+        return block;
+    }
+    // predefined mode for all three
+    block.push(0);
+
+    let codes: Vec<(usize, usize, u32)> = sequences
+        .iter()
+        .map(|sequence| {
+            (
+                code(&LL_BASE, sequence.literals),
+                code(&ML_BASE, sequence.length),
+                highbit(sequence.offset + 3),
+            )
+        })
+        .collect();
+    let extras = |n: usize, out: &mut BitWriter| {
+        let (ll, ml, of) = codes[n];
+        let sequence = &sequences[n];
+        out.add(sequence.literals - LL_BASE[ll], LL_BITS[ll]);
+        out.add(sequence.length - ML_BASE[ml], ML_BITS[ml]);
+        out.add(sequence.offset + 3 - (1 << of), of);
+    };
+
+    // written last to first, so the decoder meets the first sequence first
+    let mut out = BitWriter::new();
+    let (ll, ml, of) = codes[count - 1];
+    let mut ml_state = tables.ml.init(ml);
+    let mut of_state = tables.of.init(of as usize);
+    let mut ll_state = tables.ll.init(ll);
+    extras(count - 1, &mut out);
+    for n in (0..count - 1).rev() {
+        let (ll, ml, of) = codes[n];
+        tables.of.encode(&mut of_state, of as usize, &mut out);
+        tables.ml.encode(&mut ml_state, ml, &mut out);
+        tables.ll.encode(&mut ll_state, ll, &mut out);
+        extras(n, &mut out);
+    }
+    tables.ml.flush(ml_state, &mut out);
+    tables.of.flush(of_state, &mut out);
+    tables.ll.flush(ll_state, &mut out);
+    block.extend(out.close());
+    block
+}
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let tables = Tables {
+        ll: Fse::new(&LL_NORM, LL_LOG),
+        ml: Fse::new(&ML_NORM, ML_LOG),
+        of: Fse::new(&OF_NORM, OF_LOG),
+    };
+    let mut hashes = vec![0usize; 1 << HASH_LOG];
+    let mut frame = MAGIC.to_le_bytes().to_vec();
+    // no content size, checksum or dictionary; a window descriptor follows
+    frame.push(0);
+    frame.push(((WINDOW_LOG - 10) << 3) as u8);
+
+    let mut start = 0;
+    loop {
+        let end = (start + BLOCK_SIZE).min(input.len());
+        let last = end == input.len();
+        let block = compressed_block(input, start, end, &mut hashes, &tables);
+        let (kind, body) = match block.len() < end - start {
+            true => (2, &block[..]),
+            false => (0, &input[start..end]),
+        };
+        let header = u32::from(last) | kind << 1 | (body.len() as u32) << 3;
+        frame.extend_from_slice(&header.to_le_bytes()[..3]);
+        frame.extend_from_slice(body);
+        if last {
+            return frame;
+        }
+        start = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a decoder for just what `compress` writes, built from RFC 8878's description of the
+    // decoding tables rather than from the encoder's
+    struct Decoding {
+        log: u32,
+        // (symbol, bits, baseline)
+        states: Vec<(usize, u32, u32)>,
+    }
+
+    fn decoding(norm: &[i16], log: u32) -> Decoding {
+        let size = 1usize << log;
+        let mut symbols = vec![usize::MAX; size];
+        let mut high = size - 1;
+        for (symbol, &count) in norm.iter().enumerate() {
+            if count == -1 {
+                symbols[high] = symbol;
+                high = high.wrapping_sub(1);
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in norm.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                symbols[position] = symbol;
+                position = (position + step) & (size - 1);
+                while position > high {
+                    position = (position + step) & (size - 1);
+                }
+            }
+        }
+        let mut next: Vec<u32> = norm.iter().map(|&count| count.max(1) as u32).collect();
+        let states = symbols
+            .iter()
+            .map(|&symbol| {
+                let state = next[symbol];
+                next[symbol] += 1;
+                let bits = log - highbit(state);
+                (symbol, bits, (state << bits) - size as u32)
+            })
+            .collect();
+        Decoding { log, states }
+    }
+
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        // bits left to read, counting from the start of the stream
+        position: usize,
+    }
+
+    impl BitReader<'_> {
+        fn read(&mut self, bits: u32) -> u32 {
+            let mut value = 0;
+            for _ in 0..bits {
+                self.position -= 1;
+                let bit = self.bytes[self.position / 8] >> (self.position % 8) & 1;
+                value = value << 1 | u32::from(bit);
+            }
+            value
+        }
+    }
+
+    fn decompress(frame: &[u8]) -> Vec<u8> {
+        assert_eq!(frame[..4], MAGIC.to_le_bytes());
+        let (ll, ml, of) = (
+            decoding(&LL_NORM, LL_LOG),
+            decoding(&ML_NORM, ML_LOG),
+            decoding(&OF_NORM, OF_LOG),
+        );
+        let mut output = Vec::new();
+        let mut at = 6;
+        loop {
+            let header = u32::from_le_bytes([frame[at], frame[at + 1], frame[at + 2], 0]);
+            let size = (header >> 3) as usize;
+            let block = &frame[at + 3..at + 3 + size];
+            at += 3 + size;
+            if header >> 1 & 3 == 0 {
+                output.extend_from_slice(block);
+            } else {
+                let literals_size =
+                    (block[0] >> 4) as usize | (block[1] as usize) << 4 | (block[2] as usize) << 12;
+                let literals = &block[3..3 + literals_size];
+                let mut rest = &block[3 + literals_size..];
+                let count = match rest[0] {
+                    count @ 0..=127 => {
+                        rest = &rest[1..];
+                        count as usize
+                    }
+                    255 => {
+                        let count = 0x7F00 + u16::from_le_bytes([rest[1], rest[2]]) as usize;
+                        rest = &rest[3..];
+                        count
+                    }
+                    high => {
+                        let count = ((high as usize - 128) << 8) + rest[1] as usize;
+                        rest = &rest[2..];
+                        count
+                    }
+                };
+                let mut used = 0;
+                if count > 0 {
+                    assert_eq!(rest[0], 0);
+                    let stream = &rest[1..];
+                    let last = stream[stream.len() - 1];
+                    let mut reader = BitReader {
+                        bytes: stream,
+                        position: stream.len() * 8 - 8 + highbit(u32::from(last)) as usize,
+                    };
+                    let mut ll_state = reader.read(ll.log) as usize;
+                    let mut of_state = reader.read(of.log) as usize;
+                    let mut ml_state = reader.read(ml.log) as usize;
+                    for n in 0..count {
+                        let of_code = of.states[of_state].0 as u32;
+                        let ml_code = ml.states[ml_state].0;
+                        let ll_code = ll.states[ll_state].0;
+                        let offset = (1 << of_code) + reader.read(of_code) - 3;
+                        let length = ML_BASE[ml_code] + reader.read(ML_BITS[ml_code]);
+                        let literal_length = LL_BASE[ll_code] + reader.read(LL_BITS[ll_code]);
+                        output.extend_from_slice(&literals[used..used + literal_length as usize]);
+                        used += literal_length as usize;
+                        for _ in 0..length {
+                            output.push(output[output.len() - offset as usize]);
+                        }
+                        if n + 1 < count {
+                            for (table, state) in [
+                                (&ll, &mut ll_state),
+                                (&ml, &mut ml_state),
+                                (&of, &mut of_state),
+                            ] {
+                                let (_, bits, baseline) = table.states[*state];
+                                *state = (baseline + reader.read(bits)) as usize;
+                            }
+                        }
+                    }
+                    assert_eq!(reader.position, 0);
+                }
+                output.extend_from_slice(&literals[used..]);
+            }
+            if header & 1 == 1 {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn pinned_frame() {
+        // checked with `zstd -d`: a match long enough for a length code whose predefined
+        // probability is "less than one"
+        let input = format!("lc counts lines; lc counts lines; {}|", "x".repeat(3000));
+        let frame = [
+            40, 181, 47, 253, 0, 96, 253, 0, 0, 60, 1, 0, 108, 99, 32, 99, 111, 117, 110, 116, 115,
+            32, 108, 105, 110, 101, 115, 59, 32, 120, 124, 2, 0, 180, 195, 249, 34, 205, 115, 9,
+        ];
+        assert_eq!(compress(input.as_bytes()), frame);
+    }
+
+    #[test]
+    fn round_trip() {
+        let report: String = (0..20_000)
+            .map(|n| {
+                format!(
+                    "{{\"path\": \"src/module{}/file{n}.rs\", \"lines\": {}}}\n",
+                    n % 7,
+                    n * 3
+                )
+            })
+            .collect();
+        let mut noise = Vec::new();
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for _ in 0..300_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            noise.push(state as u8);
+        }
+        for input in [
+            &b""[..],
+            b"a",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            report.as_bytes(),
+            &noise,
+        ] {
+            assert_eq!(decompress(&compress(input)), input);
+        }
+        assert!(compress(report.as_bytes()).len() < report.len() / 3);
+        // incompressible blocks are stored as they are
+        assert!(compress(&noise).len() < noise.len() + 20);
+    }
+}